@@ -0,0 +1,66 @@
+extern crate chess_player;
+#[macro_use]
+extern crate criterion;
+
+use criterion::{black_box, Criterion};
+
+use chess_player::chessboard::ChessBoard;
+use chess_player::search::{Engine, Limits};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+// Counts the leaf positions `depth` plies down, the same walk perft makes.
+fn walk(board: &mut ChessBoard, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut nodes = 0;
+    for mv in board.legal_moves() {
+        board.make_move(mv);
+        nodes += walk(board, depth - 1);
+        board.unmake_move();
+    }
+    nodes
+}
+
+fn move_generation(c: &mut Criterion) {
+    let start = ChessBoard::new();
+    let kiwipete = ChessBoard::from_fen(KIWIPETE).unwrap();
+    c.bench_function("legal_moves startpos", |b| b.iter(|| black_box(&start).legal_moves()));
+    c.bench_function("legal_moves kiwipete", |b| b.iter(|| black_box(&kiwipete).legal_moves()));
+    c.bench_function("is_check kiwipete", |b| b.iter(|| black_box(&kiwipete).is_check()));
+}
+
+fn make_unmake(c: &mut Criterion) {
+    let mut board = ChessBoard::from_fen(KIWIPETE).unwrap();
+    let moves = board.legal_moves();
+    c.bench_function("make_unmake kiwipete", |b| b.iter(|| {
+        for mv in &moves {
+            board.make_move(*mv);
+            board.unmake_move();
+        }
+    }));
+}
+
+fn tree_walk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("walk");
+    group.sample_size(10);
+    let mut start = ChessBoard::new();
+    let mut kiwipete = ChessBoard::from_fen(KIWIPETE).unwrap();
+    group.bench_function("startpos depth 3", |b| b.iter(|| walk(&mut start, 3)));
+    group.bench_function("kiwipete depth 2", |b| b.iter(|| walk(&mut kiwipete, 2)));
+    group.finish();
+}
+
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    group.sample_size(10);
+    let kiwipete = ChessBoard::from_fen(KIWIPETE).unwrap();
+    group.bench_function("kiwipete depth 3", |b| b.iter(|| {
+        Engine::new(Limits { depth: Some(3), time: None }).best_move(&kiwipete)
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, move_generation, make_unmake, tree_walk, search);
+criterion_main!(benches);
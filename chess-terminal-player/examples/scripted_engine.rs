@@ -0,0 +1,27 @@
+// A stand-in UCI engine for trying out the client side without a real engine installed.
+// It answers every `go` with the next move given on the command line, in long algebraic notation:
+//
+//     cargo build --example scripted_engine
+//     cargo run -- --uci-engine "target/debug/examples/scripted_engine e7e5 b8c6 g8f6"
+//
+// Once the script runs out it answers `bestmove 0000`.
+
+use std::env;
+use std::io::{self, BufRead, Write};
+
+fn main() {
+    let mut script = env::args().skip(1);
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for line in stdin.lock().lines().map_while(Result::ok) {
+        let reply = match line.split_whitespace().next() {
+            Some("uci") => String::from("id name Scripted Engine\nuciok"),
+            Some("isready") => String::from("readyok"),
+            Some("go") => format!("bestmove {}", script.next().unwrap_or_else(|| String::from("0000"))),
+            Some("quit") => break,
+            _ => continue,
+        };
+        writeln!(stdout, "{}", reply).unwrap();
+        stdout.flush().unwrap();
+    }
+}
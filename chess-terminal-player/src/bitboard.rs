@@ -0,0 +1,270 @@
+use crate::chessboard::{Colour, Piece, PieceKind};
+
+// A set of squares, one bit per square. Bit `row * 8 + col` stands for (row, col), so a1 is bit 0 and h8 bit 63.
+pub type Bitboard = u64;
+
+pub const RANK_1: Bitboard = 0xff;
+pub const RANK_8: Bitboard = RANK_1 << 56;
+pub const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+
+// (row, col) steps for the pieces that move a fixed distance.
+const KNIGHT_JUMPS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const WHITE_PAWN_CAPTURES: [(i32, i32); 2] = [(1, -1), (1, 1)];
+const BLACK_PAWN_CAPTURES: [(i32, i32); 2] = [(-1, -1), (-1, 1)];
+
+// (row, col) directions for the sliding pieces. The first four run towards higher bits, the rest towards lower.
+const RAY_DIRECTIONS: [(i32, i32); 8] = [(1, 0), (0, 1), (1, 1), (1, -1), (-1, 0), (0, -1), (-1, -1), (-1, 1)];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+
+// Attack tables, worked out once at compile time.
+const KNIGHT_ATTACKS: [Bitboard; 64] = step_attacks(&KNIGHT_JUMPS);
+const KING_ATTACKS: [Bitboard; 64] = step_attacks(&KING_STEPS);
+const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [step_attacks(&WHITE_PAWN_CAPTURES), step_attacks(&BLACK_PAWN_CAPTURES)];
+// Every square from a square to the edge of the board in each direction, the square itself left out.
+const RAYS: [[Bitboard; 64]; 8] = rays();
+
+// Order of the piece kinds within `Bitboards`.
+const KINDS: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+const fn step_attacks(steps: &[(i32, i32)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut i = 0;
+        while i < steps.len() {
+            let row = (square / 8) as i32 + steps[i].0;
+            let col = (square % 8) as i32 + steps[i].1;
+            if row >= 0 && row < 8 && col >= 0 && col < 8 {
+                table[square] |= 1 << (row * 8 + col);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+const fn rays() -> [[Bitboard; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let (row_step, col_step) = RAY_DIRECTIONS[direction];
+        let mut square = 0;
+        while square < 64 {
+            let mut row = (square / 8) as i32 + row_step;
+            let mut col = (square % 8) as i32 + col_step;
+            while row >= 0 && row < 8 && col >= 0 && col < 8 {
+                table[direction][square] |= 1 << (row * 8 + col);
+                row += row_step;
+                col += col_step;
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+pub fn index(square: (usize, usize)) -> usize {
+    square.0 * 8 + square.1
+}
+
+pub fn square(index: usize) -> (usize, usize) {
+    (index / 8, index % 8)
+}
+
+pub fn bit(square: (usize, usize)) -> Bitboard {
+    1 << index(square)
+}
+
+// The squares in a set as indices, lowest first.
+pub fn squares(set: Bitboard) -> Squares {
+    Squares(set)
+}
+
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(index)
+    }
+}
+
+pub fn colour_index(colour: Colour) -> usize {
+    if colour == Colour::White { 0 } else { 1 }
+}
+
+pub fn kind_index(kind: PieceKind) -> usize {
+    KINDS.iter().position(|k| *k == kind).unwrap_or(0)
+}
+
+pub fn knight_attacks(index: usize) -> Bitboard {
+    KNIGHT_ATTACKS[index]
+}
+
+pub fn king_attacks(index: usize) -> Bitboard {
+    KING_ATTACKS[index]
+}
+
+// Squares a pawn of `colour` on `index` could capture on.
+pub fn pawn_attacks(index: usize, colour: Colour) -> Bitboard {
+    PAWN_ATTACKS[colour_index(colour)][index]
+}
+
+// A ray stops at the first piece in its way, which is attacked. Rays running towards higher bits meet that
+// piece at their lowest set bit, the others at their highest.
+fn ray_attacks(direction: usize, index: usize, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[direction][index];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    let first = if direction < 4 { blockers.trailing_zeros() } else { 63 - blockers.leading_zeros() };
+    ray ^ RAYS[direction][first as usize]
+}
+
+pub fn bishop_attacks(index: usize, occupied: Bitboard) -> Bitboard {
+    BISHOP_DIRECTIONS.iter().fold(0, |set, d| set | ray_attacks(*d, index, occupied))
+}
+
+pub fn rook_attacks(index: usize, occupied: Bitboard) -> Bitboard {
+    ROOK_DIRECTIONS.iter().fold(0, |set, d| set | ray_attacks(*d, index, occupied))
+}
+
+pub fn queen_attacks(index: usize, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(index, occupied) | rook_attacks(index, occupied)
+}
+
+// Where the pieces stand, one set of squares for every kind and colour of piece.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Bitboards {
+    pieces: [[Bitboard; 6]; 2],  // By colour, white first, then by kind in the order of `KINDS`
+    colours: [Bitboard; 2],  // Every piece of each colour
+}
+
+impl Bitboards {
+    pub fn empty() -> Bitboards {
+        Bitboards { pieces: [[0; 6]; 2], colours: [0; 2] }
+    }
+
+    // The piece on a square, an empty piece if there is none.
+    pub fn piece(&self, index: usize) -> Piece {
+        let mask = 1 << index;
+        for colour in [Colour::White, Colour::Black].iter() {
+            let c = colour_index(*colour);
+            if self.colours[c] & mask == 0 {
+                continue;
+            }
+            for (k, kind) in KINDS.iter().enumerate() {
+                if self.pieces[c][k] & mask != 0 {
+                    return Piece::new(*kind, *colour);
+                }
+            }
+        }
+        Piece::empty()
+    }
+
+    // Puts a piece on a square, replacing whatever stood there.
+    pub fn add(&mut self, piece: Piece, index: usize) {
+        self.remove(index);
+        if piece.kind == PieceKind::None || piece.colour == Colour::None {
+            return;
+        }
+        let c = colour_index(piece.colour);
+        self.pieces[c][kind_index(piece.kind)] |= 1 << index;
+        self.colours[c] |= 1 << index;
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        let mask = !(1 << index);
+        for c in 0..2 {
+            if self.colours[c] & !mask == 0 {
+                continue;
+            }
+            self.colours[c] &= mask;
+            for set in self.pieces[c].iter_mut() {
+                *set &= mask;
+            }
+        }
+    }
+
+    pub fn pieces(&self, kind: PieceKind, colour: Colour) -> Bitboard {
+        if kind == PieceKind::None || colour == Colour::None {
+            return 0;
+        }
+        self.pieces[colour_index(colour)][kind_index(kind)]
+    }
+
+    // Both colours' pieces of a kind.
+    pub fn kind(&self, kind: PieceKind) -> Bitboard {
+        self.pieces(kind, Colour::White) | self.pieces(kind, Colour::Black)
+    }
+
+    pub fn colour(&self, colour: Colour) -> Bitboard {
+        match colour {
+            Colour::None => 0,
+            colour => self.colours[colour_index(colour)],
+        }
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colours[0] | self.colours[1]
+    }
+
+    pub fn king(&self, colour: Colour) -> Option<usize> {
+        squares(self.pieces(PieceKind::King, colour)).next()
+    }
+
+    // Squares the piece of `kind` and `colour` on `index` attacks, given the pieces in the way.
+    pub fn attacks_from(&self, kind: PieceKind, colour: Colour, index: usize) -> Bitboard {
+        let occupied = self.occupied();
+        match kind {
+            PieceKind::Pawn => pawn_attacks(index, colour),
+            PieceKind::Knight => knight_attacks(index),
+            PieceKind::Bishop => bishop_attacks(index, occupied),
+            PieceKind::Rook => rook_attacks(index, occupied),
+            PieceKind::Queen => queen_attacks(index, occupied),
+            PieceKind::King => king_attacks(index),
+            PieceKind::None => 0,
+        }
+    }
+
+    // Every square a piece of colour `by` attacks.
+    pub fn attacks(&self, by: Colour) -> Bitboard {
+        let mut set = 0;
+        for kind in KINDS.iter() {
+            for from in squares(self.pieces(*kind, by)) {
+                set |= self.attacks_from(*kind, by, from);
+            }
+        }
+        set
+    }
+
+    // Whether any piece of colour `by` attacks the square. Works backwards from the square: a knight there
+    // would reach every knight that attacks it, and so on for each kind of piece.
+    pub fn is_attacked(&self, index: usize, by: Colour) -> bool {
+        let occupied = self.occupied();
+        let queens = self.pieces(PieceKind::Queen, by);
+        pawn_attacks(index, by.opponent()) & self.pieces(PieceKind::Pawn, by) != 0
+            || knight_attacks(index) & self.pieces(PieceKind::Knight, by) != 0
+            || king_attacks(index) & self.pieces(PieceKind::King, by) != 0
+            || bishop_attacks(index, occupied) & (self.pieces(PieceKind::Bishop, by) | queens) != 0
+            || rook_attacks(index, occupied) & (self.pieces(PieceKind::Rook, by) | queens) != 0
+    }
+}
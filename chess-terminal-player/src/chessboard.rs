@@ -1,5 +1,6 @@
 use std::fmt;
-use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 extern crate termion;
 extern crate regex;
@@ -8,23 +9,44 @@ use termion::{
     clear,
     color,
     cursor,
+    style,
 };
 
 use regex::Regex;
 
+use crate::bitboard::{self, Bitboard, Bitboards};
+use crate::clock;
+use crate::theme::Theme;
+use crate::zobrist;
+
 #[path = "graphics.rs"]
 mod graphics;
 
 use graphics::{
+    ascii,
     borders,
     pieces as p,
     table,
 };
 
-const SPACE: &'static str = " ";
+const SPACE: &str = " ";
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+// Lines taken up by `ChessBoard::draw`, borders and column letters included.
+pub const BOARD_HEIGHT: u16 = 18;
+
+// Where the clocks and sidebar start, two columns clear of the board's right border.
+const SIDEBAR_COLUMN: u16 = 37;
+// Screen lines the move list may take, between the captured pieces of either side.
+const MOVE_LIST_LINES: std::ops::Range<u16> = 5..15;
+// One full move in the list, "12. Nf3     Nc6" and a gap before the next column.
+const MOVE_COLUMN_WIDTH: usize = 22;
+
+const PROMOTION_KINDS: [PieceKind; 4] = [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight];
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
-enum PieceKind {
+pub enum PieceKind {
     Pawn,
     Bishop,
     Knight,
@@ -35,23 +57,99 @@ enum PieceKind {
 }
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
-enum Colour {
+pub enum Colour {
     Black,
     White,
     None,
 }
 
+impl Colour {
+    pub fn opponent(&self) -> Colour {
+        match self {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+            Colour::None => Colour::None,
+        }
+    }
+
+    // Row direction pawns of this colour advance in, white moves up the board from a1.
+    fn pawn_direction(&self) -> i32 {
+        match self {
+            Colour::White => 1,
+            _ => -1,
+        }
+    }
+
+    fn pawn_start_row(&self) -> usize {
+        match self {
+            Colour::White => 1,
+            _ => 6,
+        }
+    }
+
+    // Row the pieces of this colour start on.
+    fn home_row(&self) -> usize {
+        match self {
+            Colour::White => 0,
+            _ => 7,
+        }
+    }
+
+    fn promotion_row(&self) -> usize {
+        match self {
+            Colour::White => 7,
+            _ => 0,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Hash, PartialEq, Eq)]
-struct Piece {
-    kind: PieceKind,
-    colour: Colour,
+pub struct Piece {
+    pub kind: PieceKind,
+    pub colour: Colour,
 }
 
 impl Piece {
-    fn new(kind: PieceKind, colour: Colour) -> Piece {
+    pub fn new(kind: PieceKind, colour: Colour) -> Piece {
         Piece {kind, colour}
     }
 
+    pub(crate) fn empty() -> Piece {
+        Piece::new(PieceKind::None, Colour::None)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.kind == PieceKind::None
+    }
+
+    fn from_fen_char(c: char) -> Option<Piece> {
+        let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+        let kind = match c.to_ascii_lowercase() {
+            'p' => PieceKind::Pawn,
+            'n' => PieceKind::Knight,
+            'b' => PieceKind::Bishop,
+            'r' => PieceKind::Rook,
+            'q' => PieceKind::Queen,
+            'k' => PieceKind::King,
+            _ => return None,
+        };
+        Some(Piece::new(kind, colour))
+    }
+
+    // FEN letter, upper case for white.
+    fn fen_char(&self) -> char {
+        let c = match self.kind {
+            PieceKind::Pawn => 'p',
+            PieceKind::Knight => 'n',
+            PieceKind::Bishop => 'b',
+            PieceKind::Rook => 'r',
+            PieceKind::Queen => 'q',
+            PieceKind::King => 'k',
+            PieceKind::None => ' ',
+        };
+        if self.colour == Colour::White { c.to_ascii_uppercase() } else { c }
+    }
+
     fn terminal_character(&self) -> String {
         let a = match (&self.colour, &self.kind) {
             (Colour::Black, PieceKind::Pawn) => p::BLACK_PAWN,
@@ -72,7 +170,12 @@ impl Piece {
     }
 
     fn render(&self) -> String {
-        format!("{}", self.terminal_character())
+        self.terminal_character()
+    }
+
+    // The FEN letter instead of the glyph, for terminals without the chess symbols.
+    fn render_ascii(&self) -> String {
+        self.fen_char().to_string()
     }
 }
 
@@ -88,10 +191,20 @@ impl fmt::Display for Piece {
     }
 }
 
+// Background a tile is drawn with instead of its square colour.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Highlight {
+    None,
+    Cursor,
+    Selected,
+    Destination,
+    LastMove,
+}
+
 struct Tile { }
 
 impl Tile {
-    fn render(row: usize, col:char, piece: &Option<Piece>) -> Result<String, String> {
+    fn render(row: usize, col:char, piece: &Option<Piece>, highlight: Highlight, options: &DrawOptions) -> Result<String, String> {
         if !['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'].contains(&col) {
             return Err(format!("Invalid column, {}", col));
         }
@@ -100,28 +213,238 @@ impl Tile {
         }
 
         let rendered_piece = match piece {
+            Some(p) if options.ascii => p.render_ascii(),
             Some(p) => p.render(),
             None => String::from(SPACE),
         };
 
-        let black_start_cols = vec!['a', 'c', 'e', 'g'];
+        let black_start_cols = ['a', 'c', 'e', 'g'];
         let starts_with_black = black_start_cols.contains(&col);
-        let row_is_even = row % 2 == 0;
+        let row_is_even = row.is_multiple_of(2);
 
-        match (starts_with_black, row_is_even) {
-            (true, true) => Ok(format!("{}{} {} {}", color::Bg(color::Reset), color::Bg(color::White), rendered_piece, color::Bg(color::Reset))),
-            (true, false) => Ok(format!("{}{} {} {}", color::Bg(color::Reset), color::Bg(color::Black), rendered_piece, color::Bg(color::Reset))),
-            (false, true) => Ok(format!("{}{} {} {}", color::Bg(color::Reset), color::Bg(color::Black), rendered_piece, color::Bg(color::Reset))),
-            (false, false) => Ok(format!("{}{} {} {}", color::Bg(color::Reset), color::Bg(color::White), rendered_piece, color::Bg(color::Reset))),
-        }
+        let theme = &options.theme;
+        let background = match (highlight, starts_with_black, row_is_even) {
+            (Highlight::Cursor, _, _) => theme.cursor,
+            (Highlight::Selected, _, _) => theme.selected,
+            (Highlight::Destination, _, _) => theme.destination,
+            (Highlight::LastMove, _, _) => theme.last_move,
+            (Highlight::None, true, true) => theme.light,
+            (Highlight::None, true, false) => theme.dark,
+            (Highlight::None, false, true) => theme.dark,
+            (Highlight::None, false, false) => theme.light,
+        };
+        let (foreground, reset) = match theme.pieces {
+            Some(shade) => (shade.fg(), color::Fg(color::Reset).to_string()),
+            None => (String::new(), String::new()),
+        };
+        Ok(format!("{}{}{} {} {}{}", color::Bg(color::Reset), background.bg(), foreground, rendered_piece,
+                   color::Bg(color::Reset), reset))
     }
 }
 
-enum Actions {
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Actions {
     Moves,
     Takes,
-    Promoted,
     Castled,
+    EnPassant,
+}
+
+// A single move in board coordinates, (row, col) with (0, 0) being a1. Castling is the king's move, to the g or
+// c file, the rook goes along with it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub action: Actions,
+    pub promotion: Option<PieceKind>,
+}
+
+impl Move {
+    fn new(from: (usize, usize), to: (usize, usize), action: Actions) -> Move {
+        Move { from, to, action, promotion: None }
+    }
+}
+
+// Why a piece of movetext could not be turned into a move.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SanError {
+    Empty,
+    Malformed(String),
+    IllegalMove(String),
+    Ambiguous(String),
+    MissingPromotion(String),
+    WrongSide(Colour, String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Empty => write!(f, "No move given"),
+            SanError::Malformed(m) => write!(f, "Can't read move, {}", m),
+            SanError::IllegalMove(m) => write!(f, "Illegal move, {}", m),
+            SanError::Ambiguous(m) => write!(f, "Ambiguous move, more than one piece can play {}", m),
+            SanError::MissingPromotion(m) => write!(f, "Missing promotion piece, {}", m),
+            SanError::WrongSide(colour, m) => write!(f, "{:?} to move, can't play {}", colour, m),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CastleSide {
+    King,
+    Queen,
+}
+
+impl CastleSide {
+    // The king always lands on the g or c file, whichever side it castles to.
+    fn of(mv: &Move) -> CastleSide {
+        if mv.to.1 == 6 { CastleSide::King } else { CastleSide::Queen }
+    }
+}
+
+// A move as written in Standard Algebraic Notation, before it is matched against a position.
+struct SanMove {
+    kind: PieceKind,
+    from_col: Option<usize>,
+    from_row: Option<usize>,
+    to: (usize, usize),
+    takes: bool,
+    promotion: Option<PieceKind>,
+    castle: Option<CastleSide>,
+}
+
+impl SanMove {
+    fn parse(text: &str) -> Result<SanMove, SanError> {
+        let m = text.trim();
+        if m.is_empty() {
+            return Err(SanError::Empty);
+        }
+        // Castling, or piece, disambiguation, capture, destination and promotion. Then check or mate
+        // suffixes and annotation glyphs, which don't change the move. Compiled once, on first use.
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let re = PATTERN.get_or_init(|| Regex::new(concat!(
+            r"^(?:(O-O-O|0-0-0)|(O-O|0-0)|([NBRQK])?([a-h])?([1-8])?(x|:)?([a-h])([1-8])(?:=?([NBRQ]))?)",
+            r"(?:\+{1,2}|#)?(?:!!|\?\?|!\?|\?!|!|\?)?$",
+        )).unwrap());
+        let capture = match re.captures(m) {
+            Some(capture) => capture,
+            None => return Err(SanError::Malformed(String::from(m))),
+        };
+
+        let castle = if capture.get(1).is_some() {
+            Some(CastleSide::Queen)
+        } else if capture.get(2).is_some() {
+            Some(CastleSide::King)
+        } else {
+            None
+        };
+        if castle.is_some() {
+            return Ok(SanMove {
+                kind: PieceKind::King,
+                from_col: None,
+                from_row: None,
+                to: (0, 0),
+                takes: false,
+                promotion: None,
+                castle,
+            });
+        }
+
+        let kind = match capture.get(3).map(|c| c.as_str()) {
+            Some(letter) => piece_kind_from_letter(letter),
+            None => PieceKind::Pawn,
+        };
+        let promotion = capture.get(9).map(|c| piece_kind_from_letter(c.as_str()));
+        if promotion.is_some() && kind != PieceKind::Pawn {
+            return Err(SanError::Malformed(String::from(m)));
+        }
+
+        Ok(SanMove {
+            kind,
+            from_col: capture.get(4).map(|c| col_from_letter(c.as_str())),
+            from_row: capture.get(5).map(|c| row_from_digit(c.as_str())),
+            to: (row_from_digit(&capture[8]), col_from_letter(&capture[7])),
+            takes: capture.get(6).is_some(),
+            promotion,
+            castle,
+        })
+    }
+
+    // Whether `mv`, made by a piece of `kind`, is the move written.
+    fn matches(&self, mv: &Move, kind: PieceKind) -> bool {
+        if let Some(side) = self.castle {
+            return mv.action == Actions::Castled && CastleSide::of(mv) == side;
+        }
+        mv.action != Actions::Castled
+            && kind == self.kind
+            && mv.to == self.to
+            && self.from_col.is_none_or(|c| c == mv.from.1)
+            && self.from_row.is_none_or(|r| r == mv.from.0)
+            && (!self.takes || mv.action == Actions::Takes || mv.action == Actions::EnPassant)
+            // A pawn only takes with its file written, "d5" is never exd5.
+            && (kind != PieceKind::Pawn || self.from_col.is_some() || mv.action == Actions::Moves)
+            && (self.promotion.is_none() || self.promotion == mv.promotion)
+    }
+}
+
+fn san_letter(kind: PieceKind) -> &'static str {
+    match kind {
+        PieceKind::Bishop => "B",
+        PieceKind::King => "K",
+        PieceKind::Knight => "N",
+        PieceKind::Queen => "Q",
+        PieceKind::Rook => "R",
+        _ => "",
+    }
+}
+
+// The regular expressions above only let through valid letters and digits.
+fn piece_kind_from_letter(letter: &str) -> PieceKind {
+    match letter {
+        "B" => PieceKind::Bishop,
+        "K" => PieceKind::King,
+        "N" => PieceKind::Knight,
+        "Q" => PieceKind::Queen,
+        "R" => PieceKind::Rook,
+        _ => PieceKind::None,
+    }
+}
+
+fn col_from_letter(letter: &str) -> usize {
+    match letter {
+        "a" => 0,
+        "b" => 1,
+        "c" => 2,
+        "d" => 3,
+        "e" => 4,
+        "f" => 5,
+        "g" => 6,
+        "h" => 7,
+        _ => 8,
+    }
+}
+
+fn row_from_digit(digit: &str) -> usize {
+    match digit.parse::<usize>() {
+        Ok(n) if (1..9).contains(&n) => n - 1,
+        _ => 8,
+    }
+}
+
+// Algebraic name of a square, (0, 0) is a1.
+pub fn square_name(square: (usize, usize)) -> String {
+    let files = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+    format!("{}{}", files[square.1], square.0 + 1)
+}
+
+pub fn parse_square(text: &str) -> Option<(usize, usize)> {
+    if text.len() != 2 || !text.is_char_boundary(1) {
+        return None;
+    }
+    let col = col_from_letter(&text[0..1]);
+    let row = row_from_digit(&text[1..2]);
+    if col < 8 && row < 8 { Some((row, col)) } else { None }
 }
 
 #[derive(Debug)]
@@ -130,222 +453,1284 @@ enum Instructions {
     Remove((usize, usize)),
 }
 
+// Which castling moves are still allowed, lost once the king or the rook in question has moved.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    fn all() -> CastlingRights {
+        CastlingRights {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+
+    fn allows(&self, colour: Colour, side: CastleSide) -> bool {
+        match (colour, side) {
+            (Colour::White, CastleSide::King) => self.white_kingside,
+            (Colour::White, CastleSide::Queen) => self.white_queenside,
+            (Colour::Black, CastleSide::King) => self.black_kingside,
+            (Colour::Black, CastleSide::Queen) => self.black_queenside,
+            _ => false,
+        }
+    }
+
+    fn set(&mut self, colour: Colour, side: CastleSide, allowed: bool) {
+        match (colour, side) {
+            (Colour::White, CastleSide::King) => self.white_kingside = allowed,
+            (Colour::White, CastleSide::Queen) => self.white_queenside = allowed,
+            (Colour::Black, CastleSide::King) => self.black_kingside = allowed,
+            (Colour::Black, CastleSide::Queen) => self.black_queenside = allowed,
+            _ => {},
+        }
+    }
+
+    // A move from or to a castling rook's starting square means that rook has either moved or been taken.
+    fn clear_rook(&mut self, square: (usize, usize), rooks: &CastlingRooks) {
+        for colour in [Colour::White, Colour::Black].iter() {
+            for side in [CastleSide::King, CastleSide::Queen].iter() {
+                if square == (colour.home_row(), rooks.col(*colour, *side)) {
+                    self.set(*colour, *side, false);
+                }
+            }
+        }
+    }
+}
+
+// Files of the rooks each side castles with, the corners in standard chess. In Chess960 they are wherever
+// the rooks started, on either side of the king.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct CastlingRooks {
+    cols: [[usize; 2]; 2],  // White then black, queenside then kingside
+}
+
+impl CastlingRooks {
+    fn corners() -> CastlingRooks {
+        CastlingRooks { cols: [[0, 7]; 2] }
+    }
+
+    fn col(&self, colour: Colour, side: CastleSide) -> usize {
+        self.cols[if colour == Colour::Black { 1 } else { 0 }][if side == CastleSide::King { 1 } else { 0 }]
+    }
+
+    fn set(&mut self, colour: Colour, side: CastleSide, col: usize) {
+        self.cols[if colour == Colour::Black { 1 } else { 0 }][if side == CastleSide::King { 1 } else { 0 }] = col;
+    }
+}
+
+// Everything about a position besides where the pieces stand.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GameState {
+    pub side_to_move: Colour,
+    pub castling: CastlingRights,
+    pub en_passant: Option<(usize, usize)>,  // Square a pawn skipped over with a double push on the last move
+    pub halfmove_clock: u32,  // Moves since the last capture or pawn move
+    pub fullmove_number: u32,  // Starts at 1, goes up after every black move
+}
+
+impl GameState {
+    fn new() -> GameState {
+        GameState {
+            side_to_move: Colour::White,
+            castling: CastlingRights::all(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    // Moves the game state on past `mv`, made by `piece`.
+    fn advance(&mut self, mv: &Move, piece: Piece, rooks: &CastlingRooks) {
+        let is_pawn = piece.kind == PieceKind::Pawn;
+
+        if is_pawn || mv.action == Actions::Takes || mv.action == Actions::EnPassant {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        self.en_passant = if is_pawn && (mv.from.0 as i32 - mv.to.0 as i32).abs() == 2 {
+            Some(((mv.from.0 + mv.to.0) / 2, mv.from.1))
+        } else {
+            None
+        };
+
+        if piece.kind == PieceKind::King {
+            match piece.colour {
+                Colour::White => {
+                    self.castling.white_kingside = false;
+                    self.castling.white_queenside = false;
+                },
+                _ => {
+                    self.castling.black_kingside = false;
+                    self.castling.black_queenside = false;
+                },
+            }
+        }
+        self.castling.clear_rook(mv.from, rooks);
+        self.castling.clear_rook(mv.to, rooks);
+
+        if self.side_to_move == Colour::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = self.side_to_move.opponent();
+    }
+}
+
+// What is wrong with a position given in Forsyth-Edwards Notation.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FenError {
+    FieldCount(usize),
+    RankCount(usize),
+    RankLength(usize, String),  // (rank number, rank text)
+    UnknownPiece(char),
+    KingCount(Colour, usize),
+    PawnOnBackRank(String),
+    SideToMove(String),
+    NotToMoveInCheck(Colour),
+    Castling(String),
+    EnPassant(String),
+    HalfmoveClock(String),
+    FullmoveNumber(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::FieldCount(n) => write!(f, "Expected 4 to 6 fields, found {}", n),
+            FenError::RankCount(n) => write!(f, "Expected 8 ranks, found {}", n),
+            FenError::RankLength(rank, text) => write!(f, "Rank {} doesn't cover 8 squares, {}", rank, text),
+            FenError::UnknownPiece(c) => write!(f, "Unknown piece, {}", c),
+            FenError::KingCount(colour, n) => write!(f, "{:?} needs exactly one king, found {}", colour, n),
+            FenError::PawnOnBackRank(square) => write!(f, "Pawn on the first or last rank, {}", square),
+            FenError::SideToMove(s) => write!(f, "Invalid side to move, {}", s),
+            FenError::NotToMoveInCheck(colour) => write!(f, "{:?} is in check with the other side to move", colour),
+            FenError::Castling(s) => write!(f, "Invalid castling rights, {}", s),
+            FenError::EnPassant(s) => write!(f, "Invalid en passant square, {}", s),
+            FenError::HalfmoveClock(s) => write!(f, "Invalid halfmove clock, {}", s),
+            FenError::FullmoveNumber(s) => write!(f, "Invalid fullmove number, {}", s),
+        }
+    }
+}
+
+// How a game came to an end.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    Checkmate(Colour),  // Winner
+    Resignation(Colour),  // Winner
+    Timeout(Colour),  // Winner, the other side's flag fell
+    TimeoutVsInsufficientMaterial,  // A flag fell, but the other side couldn't have mated
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    Agreement,  // Both players agreed to a draw
+}
+
+impl GameResult {
+    pub fn winner(&self) -> Option<Colour> {
+        match self {
+            GameResult::Checkmate(colour) | GameResult::Resignation(colour) | GameResult::Timeout(colour) => Some(*colour),
+            _ => None,
+        }
+    }
+
+    // Result token as written at the end of PGN movetext.
+    pub fn pgn_token(&self) -> &'static str {
+        match self.winner() {
+            Some(Colour::White) => "1-0",
+            Some(Colour::Black) => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameResult::Checkmate(colour) => write!(f, "Checkmate, {:?} wins", colour),
+            GameResult::Resignation(colour) => write!(f, "{:?} resigns, {:?} wins", colour.opponent(), colour),
+            GameResult::Timeout(colour) => write!(f, "{:?} ran out of time, {:?} wins", colour.opponent(), colour),
+            GameResult::TimeoutVsInsufficientMaterial => write!(f, "Flag fell, but the other side can't mate, draw"),
+            GameResult::Stalemate => write!(f, "Stalemate, draw"),
+            GameResult::ThreefoldRepetition => write!(f, "Threefold repetition, draw"),
+            GameResult::FiftyMoveRule => write!(f, "Fifty moves without a capture or pawn move, draw"),
+            GameResult::InsufficientMaterial => write!(f, "Insufficient material, draw"),
+            GameResult::Agreement => write!(f, "Draw agreed"),
+        }
+    }
+}
+
+// How `ChessBoard::draw` shows the board.
+#[derive(Clone, Debug, Default)]
+pub struct DrawOptions {
+    pub flipped: bool,  // Black's side at the bottom
+    pub theme: Theme,
+    pub ascii: bool,  // Letters for the pieces and plain ASCII borders, for terminals without the Unicode glyphs
+    pub hide_coordinates: bool,  // Leaves out the rank numbers and file letters
+    pub cursor: Option<(usize, usize)>,
+    pub selected: Option<(usize, usize)>,
+    pub destinations: Vec<(usize, usize)>,  // Where the selected piece can move
+    pub clocks: Option<[Duration; 2]>,  // Time left for white and black, drawn beside the board
+    pub width: Option<u16>,  // Terminal width, the sidebar fills what there is beside the board
+    pub panel: Vec<String>,  // Lines drawn beside the board in place of the clocks and move list, like an analysis
+}
+
+impl DrawOptions {
+    fn highlight(&self, square: (usize, usize), last_move: Option<&Move>) -> Highlight {
+        if self.cursor == Some(square) {
+            Highlight::Cursor
+        } else if self.selected == Some(square) {
+            Highlight::Selected
+        } else if self.destinations.contains(&square) {
+            Highlight::Destination
+        } else if last_move.is_some_and(|mv| mv.from == square || mv.to == square) {
+            Highlight::LastMove
+        } else {
+            Highlight::None
+        }
+    }
+}
+
+// Everything needed to take a move back exactly.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MoveRecord {
+    pub mv: Move,
+    pub piece: Piece,  // The piece that moved, a pawn for promotions
+    pub captured: Option<Piece>,
+    pub previous_state: GameState,  // Castling rights, en passant square and clocks before the move
+}
+
+#[derive(Clone)]
 pub struct ChessBoard {
-    // Two mappings, one from board to pieces, the other from pieces to board.
-    boardstate: [[Piece; 8]; 8],  // Outer array for rows, inner array for columns BoardState[0][0] is a1; BoardState[7][7] is h8
-    piecemap : HashMap<(Piece), Vec<(usize, usize)> >,  // (PIECETYPE, NUMBER) -> (ROW, COL)
+    bitboards: Bitboards,  // Where every piece stands, one set of squares per kind and colour
+    state: GameState,
+    castling_rooks: CastlingRooks,
+    chess960: bool,  // Castling moves are written king takes rook in UCI
+    hash: u64,  // Zobrist hash of the current position, kept up to date move by move
+    positions: Vec<u64>,  // Hash of every position reached so far, to spot repetitions
+    history: Vec<MoveRecord>,  // Moves played, most recent last
+    undone: Vec<Move>,  // Moves taken back with `undo`, most recent last
+}
+
+impl Default for ChessBoard {
+    fn default() -> ChessBoard {
+        ChessBoard::new()
+    }
 }
 
 impl ChessBoard {
     pub fn new() -> ChessBoard {
-        let mut pm = HashMap::new();
-        pm.insert(Piece::new(PieceKind::Rook, Colour::White), vec![(0, 0), (0, 7)]);
-        pm.insert(Piece::new(PieceKind::Knight, Colour::White), vec![(0, 1), (0, 6)]);
-        pm.insert(Piece::new(PieceKind::Bishop, Colour::White), vec![(0, 2), (0, 5)]);
-        pm.insert(Piece::new(PieceKind::Queen, Colour::White), vec![(0, 3)]);
-        pm.insert(Piece::new(PieceKind::King, Colour::White), vec![(0, 4)]);
-        pm.insert(Piece::new(PieceKind::Rook, Colour::Black), vec![(0, 0), (0, 7)]);
-        pm.insert(Piece::new(PieceKind::Knight, Colour::Black), vec![(0, 1), (0, 6)]);
-        pm.insert(Piece::new(PieceKind::Bishop, Colour::Black), vec![(0, 2), (0, 5)]);
-        pm.insert(Piece::new(PieceKind::Queen, Colour::Black), vec![(0, 3)]);
-        pm.insert(Piece::new(PieceKind::King, Colour::Black), vec![(0, 4)]);
-
-        let white_pawn = Piece::new(PieceKind::Pawn, Colour::White);
-        let black_pawn = Piece::new(PieceKind::Pawn, Colour::Black);
-
-
-        for i in 0..8 {
-            let w = pm.entry(white_pawn).or_insert(Vec::new());
-            w.push((1, i));
-            let b = pm.entry(black_pawn).or_insert(Vec::new());
-            b.push((7, i));
-        }
-
-        let state = [
-            [
-                Piece::new(PieceKind::Rook, Colour::White),     // a1
-                Piece::new(PieceKind::Knight, Colour::White),
-                Piece::new(PieceKind::Bishop, Colour::White),
-                Piece::new(PieceKind::Queen, Colour::White),
-                Piece::new(PieceKind::King, Colour::White),
-                Piece::new(PieceKind::Bishop, Colour::White),
-                Piece::new(PieceKind::Knight, Colour::White),
-                Piece::new(PieceKind::Rook, Colour::White),     // h1
-            ],
-            [Piece::new(PieceKind::Pawn, Colour::White); 8],
-            [Piece::new(PieceKind::None, Colour::None); 8],
-            [Piece::new(PieceKind::None, Colour::None); 8],
-            [Piece::new(PieceKind::None, Colour::None); 8],
-            [Piece::new(PieceKind::None, Colour::None); 8],
-            [Piece::new(PieceKind::Pawn, Colour::Black); 8],
-            [
-                Piece::new(PieceKind::Rook, Colour::Black),     // a8
-                Piece::new(PieceKind::Knight, Colour::Black),
-                Piece::new(PieceKind::Bishop, Colour::Black),
-                Piece::new(PieceKind::Queen, Colour::Black),
-                Piece::new(PieceKind::King, Colour::Black),
-                Piece::new(PieceKind::Bishop, Colour::Black),
-                Piece::new(PieceKind::Knight, Colour::Black),
-                Piece::new(PieceKind::Rook, Colour::Black),     // h8
-            ]
+        let back_rank = [
+            PieceKind::Rook,    // a file
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Queen,
+            PieceKind::King,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+            PieceKind::Rook,    // h file
         ];
+        let mut bitboards = Bitboards::empty();
+        for (col, kind) in back_rank.iter().enumerate() {
+            bitboards.add(Piece::new(*kind, Colour::White), bitboard::index((0, col)));
+            bitboards.add(Piece::new(PieceKind::Pawn, Colour::White), bitboard::index((1, col)));
+            bitboards.add(Piece::new(PieceKind::Pawn, Colour::Black), bitboard::index((6, col)));
+            bitboards.add(Piece::new(*kind, Colour::Black), bitboard::index((7, col)));
+        }
+
+        let mut board = ChessBoard {
+            bitboards,
+            state: GameState::new(),
+            castling_rooks: CastlingRooks::corners(),
+            chess960: false,
+            hash: 0,
+            positions: Vec::new(),
+            history: Vec::new(),
+            undone: Vec::new(),
+        };
+        board.hash = board.compute_hash();
+        board.positions.push(board.hash);
+        board
+    }
+
+    // Sets up one of the 960 starting positions of Fischer Random chess, numbered the usual way from 0 to 959.
+    // Number 518 is the standard setup.
+    pub fn chess960(number: u32) -> Option<ChessBoard> {
+        if number >= 960 {
+            return None;
+        }
+        let mut rank = [None; 8];
+        let place = |rank: &mut [Option<char>; 8], nth: usize, letter: char| {
+            let col = (0..8).filter(|col| rank[*col].is_none()).nth(nth).unwrap();
+            rank[col] = Some(letter);
+        };
+        // A bishop on a light square, b, d, f or h, and one on a dark square, a, c, e or g.
+        rank[(number % 4) as usize * 2 + 1] = Some('B');
+        rank[(number / 4 % 4) as usize * 2] = Some('B');
+        // Then the queen on one of the six empty squares, the knights on two of the five left, and the king
+        // between the rooks on the last three.
+        let rest = number / 16;
+        place(&mut rank, (rest % 6) as usize, 'Q');
+        let knights = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+        let (first, second) = knights[(rest / 6) as usize];
+        place(&mut rank, second, 'N');
+        place(&mut rank, first, 'N');
+        for letter in ['R', 'K', 'R'].iter() {
+            place(&mut rank, 0, *letter);
+        }
+        let back_rank: String = rank.iter().flatten().collect();
+        let mut board = ChessBoard::from_back_rank(&back_rank).ok()?;
+        board.chess960 = true;
+        Some(board)
+    }
 
-        ChessBoard {
-            boardstate: state,
-            piecemap: ChessBoard::build_piecemap(state),
+    // Sets up any arrangement of the pieces on the back ranks, given as white's pieces from a to h like "RNBQKBNR"
+    // and mirrored for black, with the pawns in front. Castling is allowed with the outermost rook on either
+    // side of the king, as in Chess960.
+    pub fn from_back_rank(pieces: &str) -> Result<ChessBoard, FenError> {
+        if let Some(c) = pieces.chars().find(|c| !"KQRBN".contains(*c)) {
+            return Err(FenError::UnknownPiece(c));
         }
+        if pieces.len() != 8 {
+            return Err(FenError::RankLength(1, String::from(pieces)));
+        }
+        let king = pieces.find('K').ok_or(FenError::KingCount(Colour::White, 0))?;
+        let mut castling = String::new();
+        if pieces[king + 1..].contains('R') { castling.push('K'); }
+        if pieces[..king].contains('R') { castling.push('Q'); }
+        castling.push_str(&castling.to_lowercase());
+        if castling.is_empty() { castling.push('-'); }
+        let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {} - 0 1", pieces.to_lowercase(), pieces, castling);
+        let mut board = ChessBoard::from_fen(&fen)?;
+        board.chess960 = pieces != "RNBQKBNR";
+        Ok(board)
+    }
+
+    // Sets up a position from Forsyth-Edwards Notation. The move clocks may be left off, as they are in EPD.
+    pub fn from_fen(fen: &str) -> Result<ChessBoard, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+
+        // Piece placement, ranks from 8 down to 1, files a to h within each rank.
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::RankCount(ranks.len()));
+        }
+        let mut bitboards = Bitboards::empty();
+        for (i, rank) in ranks.iter().enumerate() {
+            let row = 7 - i;
+            let mut col = 0;
+            for c in rank.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    if skip == 0 {
+                        return Err(FenError::RankLength(row + 1, String::from(*rank)));
+                    }
+                    col += skip as usize;
+                } else {
+                    let piece = Piece::from_fen_char(c).ok_or(FenError::UnknownPiece(c))?;
+                    if col < 8 {
+                        bitboards.add(piece, bitboard::index((row, col)));
+                    }
+                    col += 1;
+                }
+                if col > 8 { break; }
+            }
+            if col != 8 {
+                return Err(FenError::RankLength(row + 1, String::from(*rank)));
+            }
+        }
+        for colour in [Colour::White, Colour::Black].iter() {
+            let count = bitboards.pieces(PieceKind::King, *colour).count_ones() as usize;
+            if count != 1 {
+                return Err(FenError::KingCount(*colour, count));
+            }
+        }
+        if let Some(index) = bitboard::squares(bitboards.kind(PieceKind::Pawn) & (bitboard::RANK_1 | bitboard::RANK_8)).next() {
+            return Err(FenError::PawnOnBackRank(square_name(bitboard::square(index))));
+        }
+
+        let side_to_move = match fields[1] {
+            "w" => Colour::White,
+            "b" => Colour::Black,
+            other => return Err(FenError::SideToMove(String::from(other))),
+        };
+        // Otherwise the side to move could take the king.
+        let waiting = side_to_move.opponent();
+        if bitboards.king(waiting).is_some_and(|king| bitboards.is_attacked(king, side_to_move)) {
+            return Err(FenError::NotToMoveInCheck(waiting));
+        }
+
+        // KQkq name the outermost rook on each side of the king, as in X-FEN. Shredder-FEN and X-FEN give
+        // the rook's file instead, A to H for white and a to h for black, needed in Chess960.
+        let mut castling = CastlingRights {
+            white_kingside: false,
+            white_queenside: false,
+            black_kingside: false,
+            black_queenside: false,
+        };
+        let mut castling_rooks = CastlingRooks::corners();
+        let mut chess960 = false;
+        if fields[2] != "-" {
+            let error = || FenError::Castling(String::from(fields[2]));
+            for c in fields[2].chars() {
+                let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+                let row = colour.home_row();
+                let king = match bitboard::square(bitboards.king(colour).ok_or_else(error)?) {
+                    (king_row, col) if king_row == row => col,
+                    _ => return Err(error()),
+                };
+                let rook = Piece::new(PieceKind::Rook, colour);
+                let is_rook = |col: &usize| bitboards.piece(bitboard::index((row, *col))) == rook;
+                let (side, col) = match c.to_ascii_lowercase() {
+                    'k' => (CastleSide::King, (king + 1..8).rev().find(is_rook).unwrap_or(7)),
+                    'q' => (CastleSide::Queen, (0..king).find(is_rook).unwrap_or(0)),
+                    file @ 'a'..='h' => {
+                        let col = file as usize - 'a' as usize;
+                        if col == king { return Err(error()); }
+                        chess960 = true;
+                        (if col > king { CastleSide::King } else { CastleSide::Queen }, col)
+                    },
+                    _ => return Err(error()),
+                };
+                if castling.allows(colour, side) {
+                    return Err(error());  // Same right given twice
+                }
+                castling.set(colour, side, true);
+                castling_rooks.set(colour, side, col);
+                chess960 |= king != 4 || col != if side == CastleSide::King { 7 } else { 0 };
+            }
+        }
+
+        // The skipped square is behind a pawn that just moved, so on the sixth rank when white is to move.
+        let en_passant_row = if side_to_move == Colour::White { 5 } else { 2 };
+        let en_passant = match fields[3] {
+            "-" => None,
+            text => match parse_square(text) {
+                Some(square) if square.0 == en_passant_row => Some(square),
+                _ => return Err(FenError::EnPassant(String::from(text))),
+            },
+        };
+
+        let halfmove_clock = match fields.get(4) {
+            Some(text) => text.parse::<u32>().map_err(|_| FenError::HalfmoveClock(String::from(*text)))?,
+            None => 0,
+        };
+        let fullmove_number = match fields.get(5) {
+            Some(text) => match text.parse::<u32>() {
+                Ok(n) if n > 0 => n,
+                _ => return Err(FenError::FullmoveNumber(String::from(*text))),
+            },
+            None => 1,
+        };
+
+        let mut board = ChessBoard {
+            bitboards,
+            state: GameState {
+                side_to_move,
+                castling,
+                en_passant,
+                halfmove_clock,
+                fullmove_number,
+            },
+            castling_rooks,
+            chess960,
+            hash: 0,
+            positions: Vec::new(),
+            history: Vec::new(),
+            undone: Vec::new(),
+        };
+        board.hash = board.compute_hash();
+        board.positions.push(board.hash);
+        Ok(board)
     }
 
-    fn build_piecemap(boardstate: [[Piece; 8]; 8]) -> HashMap<(Piece), Vec<(usize, usize)> > {
-        let mut pm = HashMap::new();
-        for (row, cols) in boardstate.iter().enumerate() {
-            for (col, piece) in cols.iter().enumerate() {
-                if piece.kind == PieceKind::None {continue;}
-                let list = pm.entry(*piece).or_insert(Vec::new());
-                list.push((row, col));
+    pub fn to_fen(&self) -> String {
+        let mut ranks = Vec::new();
+        for row in (0..8).rev() {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for col in 0..8 {
+                let piece = self.piece((row, col));
+                if piece.is_empty() {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    rank.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                rank.push(piece.fen_char());
+            }
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+            }
+            ranks.push(rank);
+        }
+
+        let side_to_move = match self.state.side_to_move {
+            Colour::Black => "b",
+            _ => "w",
+        };
+
+        // KQkq unless another rook stands further out than the castling rook, then the rook's file.
+        let mut castling = String::new();
+        for colour in [Colour::White, Colour::Black].iter() {
+            for (side, letter) in [(CastleSide::King, 'k'), (CastleSide::Queen, 'q')].iter() {
+                if !self.state.castling.allows(*colour, *side) { continue; }
+                let col = self.castling_rooks.col(*colour, *side);
+                let beyond = if *side == CastleSide::King { col + 1..8 } else { 0..col };
+                let rook = Piece::new(PieceKind::Rook, *colour);
+                let letter = if beyond.into_iter().any(|c| self.piece((colour.home_row(), c)) == rook) {
+                    (b'a' + col as u8) as char
+                } else {
+                    *letter
+                };
+                castling.push(if *colour == Colour::White { letter.to_ascii_uppercase() } else { letter });
             }
         }
-        pm
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match self.state.en_passant {
+            Some(square) => square_name(square),
+            None => String::from("-"),
+        };
+
+        format!("{} {} {} {} {} {}", ranks.join("/"), side_to_move, castling, en_passant,
+                self.state.halfmove_clock, self.state.fullmove_number)
     }
 
-    pub fn draw(&self) {
+    // Draws the board at the top left of the terminal. Every line is placed with the cursor so that
+    // drawing also works in raw mode.
+    pub fn draw(&self, options: &DrawOptions) {
         let rows = [1, 2, 3, 4, 5, 6, 7, 8];
         let cols = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
-        // Clear screen
-        print!("{}{}", clear::All, cursor::Goto(1, 1));
-        for (row, pieces) in self.boardstate.iter().enumerate().rev() {  // a1 is at the bottom
+        // a1 is at the bottom, unless the board is flipped to black's side.
+        let row_order: Vec<usize> = if options.flipped { (0..8).collect() } else { (0..8).rev().collect() };
+        let col_order: Vec<usize> = if options.flipped { (0..8).rev().collect() } else { (0..8).collect() };
+        let (top, mid, bottom, wall, line_between) = if options.ascii {
+            (ascii::TOP, ascii::MID, ascii::BOTTOM, ascii::VERTICAL_WALL, ascii::VERTICAL_LINE)
+        } else {
+            (borders::TOP, borders::MID, borders::BOTTOM, table::VERTICAL_WALL, table::VERTICAL_LINE)
+        };
+        let last_move = self.history.last().map(|record| &record.mv);
+
+        let mut lines = Vec::new();
+        for (i, row) in row_order.iter().enumerate() {
             // Horizontal lines
-            if row == 7 { println!(" {}", borders::TOP); }
-            else {println!(" {}", borders::MID)}
+            if i == 0 { lines.push(format!(" {}", top)); }
+            else { lines.push(format!(" {}", mid)); }
 
-            for (col, piece) in pieces.iter().enumerate() {
-                // Vertical Lines
-                if col == 0 { print!("{}{}", rows[row], table::VERTICAL_WALL) }
-                else { print!("{}", table::VERTICAL_LINE) }
+            let mut line = String::new();
+            for (j, col) in col_order.iter().enumerate() {
+                // Vertical Lines, the rank number before the first
+                if j == 0 && options.hide_coordinates { line.push_str(&format!("{}{}", SPACE, wall)); }
+                else if j == 0 { line.push_str(&format!("{}{}", rows[*row], wall)); }
+                else { line.push_str(line_between); }
 
-                match Tile::render(rows[row], cols[col], &Some(*piece)) {
-                    Ok(tile) => print!("{}", tile),
-                    Err(msg) => print!("{}", msg),
+                let highlight = options.highlight((*row, *col), last_move);
+                match Tile::render(rows[*row], cols[*col], &self.piece_at((*row, *col)), highlight, options) {
+                    Ok(tile) => line.push_str(&tile),
+                    Err(msg) => line.push_str(&msg),
                 }
             }
-            println!("{}", table::VERTICAL_WALL);
+            line.push_str(wall);
+            lines.push(line);
         }
-        println!(" {}", borders::BOTTOM);
+        lines.push(format!(" {}", bottom));
         // Column letters
-        print!("  ");
-        for col in cols.iter() {
-            print!(" {}  ", col);
-        }
-        println!();
-    }
-
-    pub fn move_piece(&mut self, movetext: &'static str) {
-        // for x in &self.piecemap {
-        //     println!("BEFORE {:?}", x);
-        // }
-        match self.parse_piece_move(movetext, Colour::White) {
-            Some(instructions) =>  {
-                for inst in instructions {
-                    match inst {
-                        Instructions::Add(piece, pos) => {
-                            self.boardstate[pos.0][pos.1] = piece
-                        },
-                        Instructions::Remove(pos) => {
-                            self.boardstate[pos.0][pos.1] = Piece::new(PieceKind::None, Colour::None)
-                        },
-                    }
-                }
-            },
-            None => {}
-        }
-        self.piecemap = ChessBoard::build_piecemap(self.boardstate);
-        // for x in &self.piecemap {
-        //     println!("AFTER {:?}", x);
-        // }
-    }
-
-    fn parse_piece_move(&self, x: &'static str, color: Colour) -> Option<Vec<Instructions>> {
-        // So far only moves, no captures, no disambiguation.
-        let m = x.trim();
-        match Regex::new(r"([BKNQR]*)([abcdefgh])([12345678])") {
-            Ok(re) => {
-                match re.captures(m) {
-                    Some(capture) => {
-                        // Match regex results.
-                        let piece_kind = match &capture[1] {
-                            "B" => PieceKind::Bishop,
-                            "K" => PieceKind::King,
-                            "N" => PieceKind::Knight,
-                            "Q" => PieceKind::Queen,
-                            "R" => PieceKind::Rook,
-                            "" => PieceKind::Pawn,
-                            _ => PieceKind::None,
-                        };
-                        let col: usize = match &capture[2] {
-                            "a" => 0,
-                            "b" => 1,
-                            "c" => 2,
-                            "d" => 3,
-                            "e" => 4,
-                            "f" => 5,
-                            "g" => 6,
-                            "h" => 7,
-                            _ => 8,
-                        };
-                        let row = match &capture[3] {
-                            x if ["1", "2", "3", "4", "5", "6", "7", "8"].contains(&x) => match x.parse::<usize>() {
-                                Ok(n) => n - 1,
-                                Err(_) => 8,
-                            },
-                            _ => 8,
-                        };
-                        if piece_kind != PieceKind::None && col < 8 && row < 8 {
-                            return self.compile_instructions(piece_kind, color, (row, col), Actions::Moves).ok();
-                        }
-                        None
-                    },
-                    None => None,
+        let mut letters = String::from("  ");
+        for col in col_order.iter() {
+            let letter = if options.hide_coordinates { ' ' } else { cols[*col] };
+            letters.push_str(&format!(" {}  ", letter));
+        }
+        lines.push(letters);
+
+        // Clear screen
+        print!("{}", clear::All);
+        for (i, line) in lines.iter().enumerate() {
+            print!("{}{}", cursor::Goto(1, i as u16 + 1), line);
+        }
+        for (x, y, text) in self.sidebar(options) {
+            print!("{}{}", cursor::Goto(x, y), text);
+        }
+        print!("{}", cursor::Goto(1, BOARD_HEIGHT + 1));
+    }
+
+    // What goes beside the board, as (x, y, text) placed like `cursor::Goto`. Each side's clock is level with its
+    // first rank and its captures just inside that, the move list in between, in as many columns as there is room for.
+    // A panel takes the place of all of them.
+    fn sidebar(&self, options: &DrawOptions) -> Vec<(u16, u16, String)> {
+        if !options.panel.is_empty() {
+            let room = options.width.map_or(usize::MAX, |width| (width + 1).saturating_sub(SIDEBAR_COLUMN) as usize);
+            return options.panel.iter().take(BOARD_HEIGHT as usize - 2).enumerate()
+                .map(|(i, line)| (SIDEBAR_COLUMN, i as u16 + 2, line.chars().take(room).collect()))
+                .collect();
+        }
+        let (top, bottom) = if options.flipped { (Colour::White, Colour::Black) } else { (Colour::Black, Colour::White) };
+        let mut lines = Vec::new();
+        if let Some(clocks) = options.clocks {
+            for (y, colour) in [(2, top), (16, bottom)].iter() {
+                let time = clock::format_time(clocks[bitboard::colour_index(*colour)]);
+                if *colour == self.state.side_to_move {
+                    lines.push((SIDEBAR_COLUMN, *y, format!("{}{:?} {}{}", style::Invert, colour, time, style::Reset)));
+                } else {
+                    lines.push((SIDEBAR_COLUMN, *y, format!("{:?} {}", colour, time)));
                 }
+            }
+        }
+        let room = match options.width {
+            Some(width) => (width + 1).saturating_sub(SIDEBAR_COLUMN) as usize,
+            None => return lines,
+        };
+
+        // Each side's captures and how far ahead in material that leaves it.
+        let balance = self.material_balance();
+        for (y, colour) in [(3, top), (15, bottom)].iter() {
+            let mut text: String = self.captured(*colour).iter()
+                .map(|piece| if options.ascii { piece.render_ascii() } else { piece.render() })
+                .collect();
+            let lead = if *colour == Colour::White { balance } else { -balance };
+            if lead > 0 {
+                text.push_str(&format!(" +{}", lead));
+            }
+            if !text.is_empty() && text.chars().count() <= room {
+                lines.push((SIDEBAR_COLUMN, *y, text));
+            }
+        }
+
+        // The latest moves, filling the columns top to bottom, left to right.
+        let columns = room / MOVE_COLUMN_WIDTH;
+        let rows = self.move_rows();
+        let shown = (MOVE_LIST_LINES.len() * columns).min(rows.len());
+        for (i, row) in rows[rows.len() - shown..].iter().enumerate() {
+            let y = MOVE_LIST_LINES.start + (i % MOVE_LIST_LINES.len()) as u16;
+            let x = SIDEBAR_COLUMN + (i / MOVE_LIST_LINES.len() * MOVE_COLUMN_WIDTH) as u16;
+            lines.push((x, y, row.clone()));
+        }
+        lines
+    }
+
+    // The moves played in SAN, numbered two to a line, "1. e4       e5". A game starting with black to move
+    // leaves white's first move blank.
+    fn move_rows(&self) -> Vec<String> {
+        let start = match self.history.first() {
+            Some(record) => record.previous_state,
+            None => return Vec::new(),
+        };
+        let mut sans = self.san_history();
+        let mut number = start.fullmove_number;
+        if start.side_to_move == Colour::Black {
+            sans.insert(0, String::from("..."));
+        }
+        sans.chunks(2).map(|pair| {
+            let row = format!("{:>3}. {:<8}{}", number, pair[0], pair.get(1).map_or("", |s| s.as_str()));
+            number += 1;
+            row
+        }).collect()
+    }
+
+    // The moves played so far in Standard Algebraic Notation, from wherever the board was set up.
+    pub fn san_history(&self) -> Vec<String> {
+        let mut board = self.clone();
+        while board.unmake_move().is_some() {}
+        self.history.iter().map(|record| {
+            let san = board.san(&record.mv);
+            board.make_move(record.mv);
+            san
+        }).collect()
+    }
+
+    // Pieces `by` has taken so far, the most valuable first.
+    pub fn captured(&self, by: Colour) -> Vec<Piece> {
+        let mut pieces: Vec<Piece> = self.history.iter()
+            .filter(|record| record.piece.colour == by)
+            .filter_map(|record| record.captured)
+            .collect();
+        pieces.sort_by_key(|piece| std::cmp::Reverse(bitboard::kind_index(piece.kind)));
+        pieces
+    }
+
+    // White's material less black's, counting pawns 1, knights and bishops 3, rooks 5 and queens 9.
+    pub fn material_balance(&self) -> i32 {
+        let values = [(PieceKind::Pawn, 1), (PieceKind::Knight, 3), (PieceKind::Bishop, 3), (PieceKind::Rook, 5), (PieceKind::Queen, 9)];
+        values.iter().map(|(kind, value)| {
+            let count = |colour| self.bitboards.pieces(*kind, colour).count_ones() as i32;
+            value * (count(Colour::White) - count(Colour::Black))
+        }).sum()
+    }
+
+    // The square drawn at terminal position (x, y), both counted from 1 like `cursor::Goto`.
+    pub fn square_at(x: u16, y: u16, options: &DrawOptions) -> Option<(usize, usize)> {
+        // Each rank takes a line of tiles after a line of border, each tile 3 columns after a wall.
+        if y < 2 || !(y - 2).is_multiple_of(2) || x < 3 || (x - 3) % 4 == 3 {
+            return None;
+        }
+        let (line, tile) = (((y - 2) / 2) as usize, ((x - 3) / 4) as usize);
+        if line > 7 || tile > 7 {
+            return None;
+        }
+        if options.flipped { Some((line, 7 - tile)) } else { Some((7 - line, tile)) }
+    }
+
+    pub fn piece_at(&self, square: (usize, usize)) -> Option<Piece> {
+        let piece = self.piece(square);
+        if piece.is_empty() { None } else { Some(piece) }
+    }
+
+    // Where every piece stands, for the evaluation.
+    pub(crate) fn bitboards(&self) -> &Bitboards {
+        &self.bitboards
+    }
+
+    // The piece on a square, an empty piece if there is none.
+    fn piece(&self, square: (usize, usize)) -> Piece {
+        self.bitboards.piece(bitboard::index(square))
+    }
+
+    pub fn move_piece(&mut self, movetext: &str) -> Result<Move, SanError> {
+        let mv = self.parse_san(movetext)?;
+        self.make_move(mv);
+        Ok(mv)
+    }
+
+    // Finds the legal move for the side to move written as `movetext` in Standard Algebraic Notation.
+    pub fn parse_san(&self, movetext: &str) -> Result<Move, SanError> {
+        let colour = self.state.side_to_move;
+        match self.parse_piece_move(movetext, colour) {
+            // Tell apart moves that only the other side could play.
+            Err(SanError::IllegalMove(m)) if self.parse_piece_move(movetext, colour.opponent()).is_ok() => {
+                Err(SanError::WrongSide(colour, m))
             },
-            Err(_) => None,
+            result => result,
         }
     }
 
-    fn compile_instructions(&self, piece_kind: PieceKind, colour: Colour, destination: (usize, usize), action: Actions) -> Result<Vec<Instructions>, &'static str> {
-        let piece = Piece::new(piece_kind, colour);
-        match self.piecemap.get(&piece) {
-            Some(v) => {
-                for pos in v { // Go through all pieces that matches the type and find which can perform the action. There should only be one.
-                    if self.verify_action(&pos, &destination, &action) {
-                        return Ok(vec![
-                            Instructions::Add(piece, destination),
-                            Instructions::Remove(*pos)
-                        ]);
-                    }
+    // Standard Algebraic Notation for `mv`, which must be one of `legal_moves`.
+    pub fn san(&self, mv: &Move) -> String {
+        let piece = self.piece(mv.from);
+        let mut text = match (mv.action, piece.kind) {
+            (Actions::Castled, _) => match CastleSide::of(mv) {
+                CastleSide::King => String::from("O-O"),
+                CastleSide::Queen => String::from("O-O-O"),
+            },
+            (action, PieceKind::Pawn) => {
+                let mut text = String::new();
+                if action == Actions::Takes || action == Actions::EnPassant {
+                    text.push_str(&square_name(mv.from)[0..1]);
+                    text.push('x');
                 }
-                Err("Invalid move")
+                text.push_str(&square_name(mv.to));
+                if let Some(kind) = mv.promotion {
+                    text.push('=');
+                    text.push_str(san_letter(kind));
+                }
+                text
             },
-            None => Err("Can't find the piece."),
-        }
-    }
-
-    fn verify_action(&self, from: &(usize, usize), to: &(usize, usize), action: &Actions) -> bool {
-        // from is always guarantied to have a piece
-        let piece = self.boardstate[from.0][from.1];
-        match piece.kind {
-            PieceKind::Pawn => {
-                match action {
-                    Actions::Moves => {
-                        if from.1 == to.1 {  // If pawn moves, it should be on the same column.
-                            true
-                        } else {
-                            false
-                        }
+            (action, kind) => {
+                let mut text = String::from(san_letter(kind));
+                // Name the file, then the rank, then both, until no other piece of the same kind fits.
+                let rivals: Vec<Move> = self.legal_moves_for(piece.colour).into_iter()
+                    .filter(|m| m.to == mv.to && m.from != mv.from && m.action != Actions::Castled)
+                    .filter(|m| self.piece(m.from).kind == kind)
+                    .collect();
+                if !rivals.is_empty() {
+                    let from = square_name(mv.from);
+                    if rivals.iter().all(|m| m.from.1 != mv.from.1) {
+                        text.push_str(&from[0..1]);
+                    } else if rivals.iter().all(|m| m.from.0 != mv.from.0) {
+                        text.push_str(&from[1..2]);
+                    } else {
+                        text.push_str(&from);
                     }
-                    _ => false
                 }
+                if action == Actions::Takes {
+                    text.push('x');
+                }
+                text.push_str(&square_name(mv.to));
+                text
             },
-            _ => false
+        };
+
+        let mut after = self.clone();
+        after.make_move(*mv);
+        if after.is_check() {
+            text.push(if after.legal_moves().is_empty() { '#' } else { '+' });
+        }
+        text
+    }
+
+    // Plays a move, which must be one of `legal_moves`, and passes the turn to the other side.
+    pub fn make_move(&mut self, mv: Move) {
+        let piece = self.piece(mv.from);
+        let captured = match mv.action {
+            Actions::Takes => Some(self.piece(mv.to)),
+            Actions::EnPassant => Some(self.piece((mv.from.0, mv.to.1))),
+            _ => None,
+        };
+        self.history.push(MoveRecord { mv, piece, captured, previous_state: self.state });
+        // Playing the move that was undone last keeps the rest of the undone moves, anything else drops them.
+        if self.undone.last() == Some(&mv) {
+            self.undone.pop();
+        } else {
+            self.undone.clear();
+        }
+
+        let instructions = self.compile_instructions(&mv);
+        self.hash ^= self.state_hash();
+        self.hash ^= ChessBoard::apply_instructions(&mut self.bitboards, &instructions);
+        self.state.advance(&mv, piece, &self.castling_rooks);
+        self.hash ^= self.state_hash();
+        self.positions.push(self.hash);
+    }
+
+    // Takes back the last move played, putting back whatever it captured and the game state before it.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let record = self.history.pop()?;
+        let mv = record.mv;
+        let mut removals = vec![Instructions::Remove(mv.to)];
+        let mut additions = vec![Instructions::Add(record.piece, mv.from)];
+        match (mv.action, record.captured) {
+            (Actions::Takes, Some(captured)) => additions.push(Instructions::Add(captured, mv.to)),
+            (Actions::EnPassant, Some(captured)) => additions.push(Instructions::Add(captured, (mv.from.0, mv.to.1))),
+            (Actions::Castled, _) => {
+                let (rook_from, rook_to) = self.castle_rook_cols(&mv);
+                let rook = self.piece((mv.from.0, rook_to));
+                removals.push(Instructions::Remove((mv.from.0, rook_to)));
+                additions.push(Instructions::Add(rook, (mv.from.0, rook_from)));
+            },
+            _ => {},
+        }
+        removals.append(&mut additions);
+        ChessBoard::apply_instructions(&mut self.bitboards, &removals);
+        self.state = record.previous_state;
+        self.positions.pop();
+        self.hash = self.positions.last().copied().unwrap_or_else(|| self.compute_hash());
+        Some(mv)
+    }
+
+    // Takes back the last move, keeping it to be played again with `redo`.
+    pub fn undo(&mut self) -> Option<Move> {
+        let mv = self.unmake_move()?;
+        self.undone.push(mv);
+        Some(mv)
+    }
+
+    // Plays the last move taken back with `undo` again.
+    pub fn redo(&mut self) -> Option<Move> {
+        let mv = *self.undone.last()?;
+        self.make_move(mv);
+        Some(mv)
+    }
+
+    // Moves played so far, oldest first.
+    pub fn history(&self) -> &[MoveRecord] {
+        &self.history
+    }
+
+    pub fn side_to_move(&self) -> Colour {
+        self.state.side_to_move
+    }
+
+    pub fn game_state(&self) -> GameState {
+        self.state
+    }
+
+    fn parse_piece_move(&self, x: &str, color: Colour) -> Result<Move, SanError> {
+        let san = SanMove::parse(x)?;
+        let candidates: Vec<Move> = self.legal_moves_for(color).into_iter()
+            .filter(|m| san.matches(m, self.piece(m.from).kind))
+            .collect();
+        match candidates.len() {
+            0 => Err(SanError::IllegalMove(String::from(x.trim()))),
+            1 => Ok(candidates[0]),
+            // Every promotion shares the same squares, that is a missing piece rather than an ambiguity.
+            _ if san.promotion.is_none() && candidates.iter().all(|m| m.promotion.is_some()) => {
+                Err(SanError::MissingPromotion(String::from(x.trim())))
+            },
+            _ => Err(SanError::Ambiguous(String::from(x.trim()))),
+        }
+    }
+
+    // Counts the positions reached after every sequence of `depth` legal moves, to check move generation
+    // against known totals.
+    pub fn perft(&self, depth: u32) -> u64 {
+        let mut board = self.clone();
+        board.count_leaves(depth)
+    }
+
+    // Perft split by first move, to narrow down where move generation goes wrong.
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        let mut board = self.clone();
+        let mut counts = Vec::new();
+        for mv in self.legal_moves() {
+            board.make_move(mv);
+            counts.push((mv, board.count_leaves(depth.saturating_sub(1))));
+            board.unmake_move();
+        }
+        counts
+    }
+
+    fn count_leaves(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mv in moves {
+            self.make_move(mv);
+            nodes += self.count_leaves(depth - 1);
+            self.unmake_move();
+        }
+        nodes
+    }
+
+    // All moves the side to move can make.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.legal_moves_for(self.state.side_to_move)
+    }
+
+    // All moves `colour` can make that do not leave its own king in check.
+    fn legal_moves_for(&self, colour: Colour) -> Vec<Move> {
+        self.pseudo_legal_moves(colour).into_iter()
+            .filter(|m| !self.leaves_king_in_check(m, colour))
+            .collect()
+    }
+
+    // All moves following the movement rules of each piece, ignoring whether the king is left in check.
+    fn pseudo_legal_moves(&self, colour: Colour) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let own = self.bitboards.colour(colour);
+        for kind in [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King].iter() {
+            for from in bitboard::squares(self.bitboards.pieces(*kind, colour)) {
+                match kind {
+                    PieceKind::Pawn => self.pawn_moves(from, colour, &mut moves),
+                    PieceKind::King => {
+                        self.target_moves(from, bitboard::king_attacks(from) & !own, &mut moves);
+                        self.castle_moves(from, colour, &mut moves);
+                    },
+                    _ => self.target_moves(from, self.bitboards.attacks_from(*kind, colour, from) & !own, &mut moves),
+                }
+            }
+        }
+        moves
+    }
+
+    fn pawn_moves(&self, from: usize, colour: Colour, moves: &mut Vec<Move>) {
+        let empty = !self.bitboards.occupied();
+        let from_square = bitboard::square(from);
+        let forward = |square: (usize, usize)| (((square.0 as i32) + colour.pawn_direction()) as usize, square.1);
+        let mut targets = Vec::new();
+
+        // Pushes, two rows only from the starting row and only if both squares are free. Pawns never stand
+        // on the last row, so one row forward is always on the board.
+        let one = forward(from_square);
+        if empty & bitboard::bit(one) != 0 {
+            targets.push(Move::new(from_square, one, Actions::Moves));
+            if from_square.0 == colour.pawn_start_row() {
+                let two = forward(one);
+                if empty & bitboard::bit(two) != 0 {
+                    targets.push(Move::new(from_square, two, Actions::Moves));
+                }
+            }
+        }
+
+        // Diagonal captures.
+        let attacks = bitboard::pawn_attacks(from, colour);
+        for to in bitboard::squares(attacks & self.bitboards.colour(colour.opponent())) {
+            targets.push(Move::new(from_square, bitboard::square(to), Actions::Takes));
+        }
+        if let Some(en_passant) = self.state.en_passant {
+            if attacks & bitboard::bit(en_passant) != 0 {
+                targets.push(Move::new(from_square, en_passant, Actions::EnPassant));
+            }
+        }
+
+        for mv in targets {
+            if mv.to.0 == colour.promotion_row() {
+                for kind in PROMOTION_KINDS.iter() {
+                    moves.push(Move { promotion: Some(*kind), ..mv });
+                }
+            } else {
+                moves.push(mv);
+            }
+        }
+    }
+
+    // A move to every square in `targets`, a capture where there is a piece.
+    fn target_moves(&self, from: usize, targets: Bitboard, moves: &mut Vec<Move>) {
+        let occupied = self.bitboards.occupied();
+        for to in bitboard::squares(targets) {
+            let action = if occupied & (1 << to) != 0 { Actions::Takes } else { Actions::Moves };
+            moves.push(Move::new(bitboard::square(from), bitboard::square(to), action));
+        }
+    }
+
+    // The king and rook must not have moved. Whatever the starting files, as in Chess960, the king ends on the
+    // g or c file with the rook beside it on the inside. Every square either passes over must be empty but for
+    // the two of them, and the king may not castle out of, through or into check.
+    fn castle_moves(&self, from: usize, colour: Colour, moves: &mut Vec<Move>) {
+        let row = colour.home_row();
+        let (king_row, king_col) = bitboard::square(from);
+        if king_row != row { return; }
+        let rook = Piece::new(PieceKind::Rook, colour);
+        for (side, king_to, rook_to) in [(CastleSide::King, 6, 5), (CastleSide::Queen, 2, 3)].iter() {
+            if !self.state.castling.allows(colour, *side) { continue; }
+            let rook_col = self.castling_rooks.col(colour, *side);
+            if self.piece((row, rook_col)) != rook || (rook_col > king_col) != (*side == CastleSide::King) { continue; }
+            let span = |a: usize, b: usize| a.min(b)..=a.max(b);
+            let clear = span(king_col, *king_to).chain(span(rook_col, *rook_to))
+                .all(|col| col == king_col || col == rook_col || self.piece((row, col)).is_empty());
+            if !clear { continue; }
+            // Attacks are seen through the castling pieces, which won't be standing where they were.
+            let mut without = self.bitboards;
+            without.remove(from);
+            without.remove(bitboard::index((row, rook_col)));
+            let safe = span(king_col, *king_to).all(|col| !without.is_attacked(bitboard::index((row, col)), colour.opponent()));
+            if safe {
+                moves.push(Move::new((row, king_col), (row, *king_to), Actions::Castled));
+            }
+        }
+    }
+
+    // Whether the side to move is in check.
+    pub fn is_check(&self) -> bool {
+        let colour = self.state.side_to_move;
+        match self.bitboards.king(colour) {
+            Some(king) => self.bitboards.is_attacked(king, colour.opponent()),
+            None => false,
+        }
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.is_check() && self.legal_moves().is_empty()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_check() && self.legal_moves().is_empty()
+    }
+
+    // The current position has been seen at least three times with the same side to move and rights.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetitions() >= 3
+    }
+
+    // The current position has been seen before, enough for a search to treat it as a draw.
+    pub fn is_repetition(&self) -> bool {
+        self.repetitions() >= 2
+    }
+
+    // Only positions since the last capture or pawn move, with the same side to move, can be the same.
+    fn repetitions(&self) -> usize {
+        self.positions.iter().rev()
+            .take(self.state.halfmove_clock as usize + 1)
+            .step_by(2)
+            .filter(|key| **key == self.hash)
+            .count()
+    }
+
+    pub fn is_fifty_move_rule(&self) -> bool {
+        self.state.halfmove_clock >= 100
+    }
+
+    // Neither side can mate: bare kings, a single minor piece, or only bishops all on one square colour.
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy = self.bitboards.kind(PieceKind::Pawn) | self.bitboards.kind(PieceKind::Rook) | self.bitboards.kind(PieceKind::Queen);
+        if heavy != 0 {
+            return false;
+        }
+        let knights = self.bitboards.kind(PieceKind::Knight);
+        let bishops = self.bitboards.kind(PieceKind::Bishop);
+        // Light squares, b1 and a2 among them.
+        let light: Bitboard = 0x55aa_55aa_55aa_55aa;
+        let minors = (knights | bishops).count_ones();
+        minors <= 1 || (knights == 0 && (bishops & light == 0 || bishops & !light == 0))
+    }
+
+    // Whether `colour` has enough left to mate with the other side's help, which decides a flag fall:
+    // anything but a bare king, or a lone minor piece while the other side has more than its king.
+    pub fn can_checkmate(&self, colour: Colour) -> bool {
+        let king = self.bitboards.pieces(PieceKind::King, colour);
+        let pieces = self.bitboards.colour(colour) & !king;
+        let minors = self.bitboards.kind(PieceKind::Knight) | self.bitboards.kind(PieceKind::Bishop);
+        match pieces.count_ones() {
+            0 => false,
+            1 if pieces & minors != 0 => self.bitboards.colour(colour.opponent()).count_ones() > 1,
+            _ => true,
+        }
+    }
+
+    // Why the game is over, or None while the side to move can still play on.
+    pub fn result(&self) -> Option<GameResult> {
+        if self.legal_moves().is_empty() {
+            if self.is_check() {
+                return Some(GameResult::Checkmate(self.state.side_to_move.opponent()));
+            }
+            return Some(GameResult::Stalemate);
+        }
+        if self.is_insufficient_material() {
+            Some(GameResult::InsufficientMaterial)
+        } else if self.is_threefold_repetition() {
+            Some(GameResult::ThreefoldRepetition)
+        } else if self.is_fifty_move_rule() {
+            Some(GameResult::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    // Every square a piece of colour `by` attacks, whether it is empty or occupied by either side.
+    pub fn attack_map(&self, by: Colour) -> [[bool; 8]; 8] {
+        let attacks = self.bitboards.attacks(by);
+        let mut map = [[false; 8]; 8];
+        for index in bitboard::squares(attacks) {
+            let (row, col) = bitboard::square(index);
+            map[row][col] = true;
+        }
+        map
+    }
+
+    // Zobrist hash of the position, the same for the same pieces, side to move, castling rights and en passant
+    // capture whatever moves led there.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // The hash worked out from scratch, rather than move by move.
+    fn compute_hash(&self) -> u64 {
+        let mut hash = self.state_hash();
+        for index in bitboard::squares(self.bitboards.occupied()) {
+            hash ^= zobrist::piece_key(self.bitboards.piece(index), index);
+        }
+        hash
+    }
+
+    // The part of the hash that doesn't depend on where the pieces stand. En passant only counts while
+    // a pawn can actually take.
+    fn state_hash(&self) -> u64 {
+        let mut hash = zobrist::castling_key(self.state.castling);
+        if self.state.side_to_move == Colour::Black {
+            hash ^= zobrist::side_key();
+        }
+        if let Some(square) = self.capturable_en_passant() {
+            hash ^= zobrist::en_passant_key(square.1);
+        }
+        hash
+    }
+
+    fn capturable_en_passant(&self) -> Option<(usize, usize)> {
+        let square = self.state.en_passant?;
+        let colour = self.state.side_to_move;
+        let pawns = bitboard::pawn_attacks(bitboard::index(square), colour.opponent())
+            & self.bitboards.pieces(PieceKind::Pawn, colour);
+        let capturable = bitboard::squares(pawns)
+            .any(|from| !self.leaves_king_in_check(&Move::new(bitboard::square(from), square, Actions::EnPassant), colour));
+        if capturable { Some(square) } else { None }
+    }
+
+    // Plays just enough of the move on a copy of the bitboards to see whether the king is attacked after it.
+    // What a pawn promotes to can't expose the king, and castling is only generated when the king is safe.
+    fn leaves_king_in_check(&self, mv: &Move, colour: Colour) -> bool {
+        if mv.action == Actions::Castled {
+            return false;
+        }
+        let mut bitboards = self.bitboards;
+        let piece = bitboards.piece(bitboard::index(mv.from));
+        bitboards.remove(bitboard::index(mv.from));
+        bitboards.add(piece, bitboard::index(mv.to));
+        if mv.action == Actions::EnPassant {
+            bitboards.remove(bitboard::index((mv.from.0, mv.to.1)));
+        }
+        match bitboards.king(colour) {
+            Some(king) => bitboards.is_attacked(king, colour.opponent()),
+            None => false,
+        }
+    }
+
+    fn compile_instructions(&self, mv: &Move) -> Vec<Instructions> {
+        let piece = self.piece(mv.from);
+        let placed = match mv.promotion {
+            Some(kind) => Piece::new(kind, piece.colour),
+            None => piece,
+        };
+        if mv.action == Actions::Castled {
+            // Both come off before either goes back, in Chess960 one may land where the other stood.
+            let (rook_from, rook_to) = self.castle_rook_cols(mv);
+            let rook = self.piece((mv.from.0, rook_from));
+            return vec![
+                Instructions::Remove(mv.from),
+                Instructions::Remove((mv.from.0, rook_from)),
+                Instructions::Add(piece, mv.to),
+                Instructions::Add(rook, (mv.from.0, rook_to)),
+            ];
+        }
+        let mut instructions = vec![
+            Instructions::Remove(mv.from),
+            Instructions::Add(placed, mv.to),
+        ];
+        if mv.action == Actions::EnPassant {
+            // The captured pawn is beside the moving pawn, not on the square it moves to.
+            instructions.push(Instructions::Remove((mv.from.0, mv.to.1)));
+        }
+        instructions
+    }
+
+    // Columns the rook moves from and to when castling, it ends up on the other side of the king.
+    fn castle_rook_cols(&self, mv: &Move) -> (usize, usize) {
+        let colour = if mv.from.0 == Colour::White.home_row() { Colour::White } else { Colour::Black };
+        let side = CastleSide::of(mv);
+        let to = if side == CastleSide::King { 5 } else { 3 };
+        (self.castling_rooks.col(colour, side), to)
+    }
+
+    // Where the rook that castles with `mv` stands, for writing castling as the king taking its own rook.
+    pub fn castling_rook(&self, mv: &Move) -> Option<(usize, usize)> {
+        if mv.action != Actions::Castled {
+            return None;
+        }
+        Some((mv.from.0, self.castle_rook_cols(mv).0))
+    }
+
+    // Whether the game is Chess960, or some other start where castling doesn't begin from the usual files.
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    pub(crate) fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    // Returns the change to the Zobrist hash, the keys of every piece taken off or put on the board.
+    fn apply_instructions(bitboards: &mut Bitboards, instructions: &[Instructions]) -> u64 {
+        let mut change = 0;
+        for inst in instructions {
+            match inst {
+                Instructions::Add(piece, pos) => {
+                    let index = bitboard::index(*pos);
+                    change ^= zobrist::piece_key(bitboards.piece(index), index) ^ zobrist::piece_key(*piece, index);
+                    bitboards.add(*piece, index)
+                },
+                Instructions::Remove(pos) => {
+                    let index = bitboard::index(*pos);
+                    change ^= zobrist::piece_key(bitboards.piece(index), index);
+                    bitboards.remove(index)
+                },
+            }
         }
+        change
     }
 }
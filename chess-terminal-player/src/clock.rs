@@ -0,0 +1,258 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::chessboard::Colour;
+
+// Moves assumed left to play when the time control doesn't say, for working out how long the engine may think.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+// Kept back from the engine's share of the clock for drawing the board and handing the move over.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(100);
+// Below this the clock shows tenths of a second.
+const TENTHS_BELOW: Duration = Duration::from_secs(10);
+
+// Time given back for every move made.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bonus {
+    None,
+    Fischer(Duration),  // Added after every move
+    Bronstein(Duration),  // Up to this much of the time the move took is given back
+}
+
+// How much time each side gets. With `moves` set the time is for that many moves, and the same time is added
+// again whenever a side completes them, like 40 moves in 90 minutes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub time: Duration,
+    pub moves: Option<u32>,
+    pub bonus: Bonus,
+}
+
+// What is wrong with a time control given as text.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TimeControlError(String);
+
+impl fmt::Display for TimeControlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid time control, {}", self.0)
+    }
+}
+
+// The PGN TimeControl tag's notation in seconds: "300+2" for five minutes with a two second increment,
+// "40/5400" for 40 moves in 90 minutes. Bronstein delay has no PGN notation, it is written "300d2" here.
+impl FromStr for TimeControl {
+    type Err = TimeControlError;
+
+    fn from_str(text: &str) -> Result<TimeControl, TimeControlError> {
+        let error = || TimeControlError(String::from(text));
+        let (moves, rest) = match text.split_once('/') {
+            Some((moves, rest)) => (Some(moves.parse::<u32>().ok().filter(|n| *n > 0).ok_or_else(error)?), rest),
+            None => (None, text),
+        };
+        let (time, bonus) = match rest.find(['+', 'd']) {
+            Some(i) => {
+                let seconds = rest[i + 1..].parse::<u64>().map_err(|_| error())?;
+                let bonus = if &rest[i..i + 1] == "+" { Bonus::Fischer } else { Bonus::Bronstein };
+                (&rest[..i], bonus(Duration::from_secs(seconds)))
+            },
+            None => (rest, Bonus::None),
+        };
+        let time = time.parse::<u64>().ok().filter(|s| *s > 0).ok_or_else(error)?;
+        Ok(TimeControl { time: Duration::from_secs(time), moves, bonus })
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(moves) = self.moves {
+            write!(f, "{}/", moves)?;
+        }
+        write!(f, "{}", self.time.as_secs())?;
+        match self.bonus {
+            Bonus::None => Ok(()),
+            Bonus::Fischer(increment) => write!(f, "+{}", increment.as_secs()),
+            Bonus::Bronstein(delay) => write!(f, "d{}", delay.as_secs()),
+        }
+    }
+}
+
+// Time on the display of a chess clock: 1:05:00, 4:59, and 0:07.3 in the last seconds.
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if time < TENTHS_BELOW {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    } else if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+// Clocks of both sides after a move, to go back to when the move is taken back.
+#[derive(Copy, Clone, Debug)]
+struct Reading {
+    remaining: [Duration; 2],  // White first
+    moves: [u32; 2],  // Moves made by each side, for the moves per period
+    mover: Option<Colour>,  // Who pressed the clock, None before the first move
+}
+
+// A chess clock. Only one side's time runs at a time, and pressing the clock after a move hands over to the
+// other side. Every call takes the current time, so the clock can be driven by a test as well as the game.
+pub struct Clock {
+    control: TimeControl,
+    reading: Reading,
+    running: Option<(Colour, Instant)>,  // Side whose time runs, since when
+    flagged: Option<Colour>,  // Side that ran out of time
+    history: Vec<Reading>,  // Readings after each move, most recent last
+    undone: Vec<Reading>,  // Readings of moves taken back, most recent last
+}
+
+fn side(colour: Colour) -> usize {
+    if colour == Colour::Black { 1 } else { 0 }
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        Clock {
+            control,
+            reading: Reading { remaining: [control.time; 2], moves: [0; 2], mover: None },
+            running: None,
+            flagged: None,
+            history: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    pub fn running(&self) -> Option<Colour> {
+        self.running.map(|(colour, _)| colour)
+    }
+
+    pub fn flagged(&self) -> Option<Colour> {
+        self.flagged
+    }
+
+    pub fn remaining(&self, colour: Colour, now: Instant) -> Duration {
+        let left = self.reading.remaining[side(colour)];
+        match self.running {
+            Some((running, since)) if running == colour => left.saturating_sub(now.saturating_duration_since(since)),
+            _ => left,
+        }
+    }
+
+    // Sets a side's time running, unless it already runs or a flag has fallen.
+    pub fn start(&mut self, colour: Colour, now: Instant) {
+        if self.flagged.is_some() || self.running() == Some(colour) {
+            return;
+        }
+        self.stop(now);
+        self.running = Some((colour, now));
+    }
+
+    // Stops the clock with the running side charged for its time, when the game is over.
+    pub fn stop(&mut self, now: Instant) {
+        self.tick(now);
+        if let Some((colour, since)) = self.running.take() {
+            let left = &mut self.reading.remaining[side(colour)];
+            *left = left.saturating_sub(now.saturating_duration_since(since));
+        }
+    }
+
+    // Drops the flag of the running side if its time is up.
+    pub fn tick(&mut self, now: Instant) {
+        if let Some((colour, _)) = self.running {
+            if self.remaining(colour, now).is_zero() {
+                self.reading.remaining[side(colour)] = Duration::ZERO;
+                self.running = None;
+                self.flagged = Some(colour);
+            }
+        }
+    }
+
+    // Ends the running side's move: charges it for the time taken, adds its bonus and starts the other side.
+    // Too late if the flag fell before the press.
+    pub fn press(&mut self, now: Instant) {
+        self.tick(now);
+        let (colour, since) = match self.running {
+            Some(running) => running,
+            None => return,
+        };
+        let taken = now.saturating_duration_since(since);
+        let s = side(colour);
+        self.reading.remaining[s] -= taken;
+        self.reading.remaining[s] += match self.control.bonus {
+            Bonus::None => Duration::ZERO,
+            Bonus::Fischer(increment) => increment,
+            Bonus::Bronstein(delay) => taken.min(delay),
+        };
+        self.reading.moves[s] += 1;
+        if let Some(moves) = self.control.moves {
+            if self.reading.moves[s].is_multiple_of(moves) {
+                self.reading.remaining[s] += self.control.time;
+            }
+        }
+        self.reading.mover = Some(colour);
+        self.history.push(self.reading);
+        self.undone.clear();
+        self.running = Some((colour.opponent(), now));
+    }
+
+    // Goes back to the clocks as they were before the last move, with the mover's time running again.
+    pub fn undo(&mut self, now: Instant) {
+        if let Some(reading) = self.history.pop() {
+            self.undone.push(reading);
+            self.restore(reading.mover, now);
+        }
+    }
+
+    pub fn redo(&mut self, now: Instant) {
+        if let Some(reading) = self.undone.pop() {
+            self.history.push(reading);
+            self.restore(reading.mover.map(|mover| mover.opponent()), now);
+        }
+    }
+
+    fn restore(&mut self, to_move: Option<Colour>, now: Instant) {
+        self.reading = match self.history.last() {
+            Some(reading) => *reading,
+            None => Reading { remaining: [self.control.time; 2], moves: [0; 2], mover: None },
+        };
+        self.flagged = None;
+        self.running = to_move.map(|colour| (colour, now));
+    }
+
+    // Time the mover had left after each move so far, in the order the moves were played.
+    pub fn move_times(&self) -> Vec<Duration> {
+        self.history.iter()
+            .filter_map(|reading| reading.mover.map(|mover| reading.remaining[side(mover)]))
+            .collect()
+    }
+
+    // How long a side may think about its next move: an even share of its time for the moves left in the
+    // period, plus most of the bonus it gets back, never more than it has.
+    pub fn think_time(&self, colour: Colour, now: Instant) -> Duration {
+        let left = self.remaining(colour, now);
+        let moves_to_go = match self.control.moves {
+            Some(moves) => moves - self.reading.moves[side(colour)] % moves,
+            None => DEFAULT_MOVES_TO_GO,
+        };
+        let bonus = match self.control.bonus {
+            Bonus::None => Duration::ZERO,
+            Bonus::Fischer(bonus) | Bonus::Bronstein(bonus) => bonus,
+        };
+        let share = left / moves_to_go + bonus * 3 / 4;
+        share.min(left.saturating_sub(MOVE_OVERHEAD)).max(Duration::from_millis(1))
+    }
+
+    // How long until the running side's time on display changes, so it can be drawn again.
+    pub fn next_change(&self, now: Instant) -> Option<Duration> {
+        let (colour, _) = self.running?;
+        let left = self.remaining(colour, now);
+        let step = if left < TENTHS_BELOW { 100 } else { 1000 };
+        let until = left.as_millis() as u64 % step;
+        Some(Duration::from_millis(until + 1))
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+
+use crate::chessboard::{ChessBoard, FenError, Move, SanError};
+use crate::uci;
+
+// Extended Position Description: the first four fields of FEN, then operations, each an opcode and its
+// operands ended by a semicolon, like `bm Qxf7+; id "WAC.001";`. Some files carry the two move clocks of FEN
+// as well, ahead of the operations.
+
+#[derive(Debug)]
+pub enum EpdError {
+    Fen(FenError),
+    Unclosed(String),  // An operation with a string that is never closed
+    Move(String, SanError),  // (opcode, reason)
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EpdError::Fen(e) => write!(f, "Invalid EPD position, {}", e),
+            EpdError::Unclosed(operation) => write!(f, "String is never closed, {}", operation),
+            EpdError::Move(opcode, e) => write!(f, "Invalid {} operation, {}", opcode, e),
+        }
+    }
+}
+
+// A position and the operations about it.
+pub struct Epd {
+    pub board: ChessBoard,
+    operations: Vec<(String, Vec<String>)>,  // (opcode, operands), in the order written
+}
+
+impl Epd {
+    pub fn parse(line: &str) -> Result<Epd, EpdError> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let clocks = fields.len() >= 6 && fields[4..6].iter().all(|field| field.parse::<u32>().is_ok());
+        let position_fields = if clocks { 6 } else { 4.min(fields.len()) };
+        let board = ChessBoard::from_fen(&fields[..position_fields].join(" ")).map_err(EpdError::Fen)?;
+
+        // Operations start after the position, found again in the line so quoted strings keep their spaces.
+        let mut rest = line.trim_start();
+        for _ in 0..position_fields {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            rest = &rest[end..];
+        }
+        let mut operations = Vec::new();
+        for operation in split_operations(rest)? {
+            let mut words = operation.into_iter();
+            if let Some(opcode) = words.next() {
+                operations.push((opcode, words.collect()));
+            }
+        }
+        Ok(Epd { board, operations })
+    }
+
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter().find(|(name, _)| name == opcode).map(|(_, operands)| operands.as_slice())
+    }
+
+    // The name the `id` operation gives the position.
+    pub fn id(&self) -> Option<&str> {
+        self.operands("id").and_then(|operands| operands.first()).map(String::as_str)
+    }
+
+    // Moves of an operation that lists alternatives in the position, like the best moves of `bm` or the moves
+    // to avoid of `am`. None when the operation isn't there.
+    pub fn moves(&self, opcode: &str) -> Result<Option<Vec<Move>>, EpdError> {
+        let operands = match self.operands(opcode) {
+            Some(operands) => operands,
+            None => return Ok(None),
+        };
+        operands.iter()
+            .map(|text| parse_move(&self.board, text).map_err(|e| EpdError::Move(opcode.to_string(), e)))
+            .collect::<Result<Vec<Move>, EpdError>>()
+            .map(Some)
+    }
+
+    // Moves of an operation that is a line of play from the position, like the principal variation of `pv`.
+    pub fn line(&self, opcode: &str) -> Result<Option<Vec<Move>>, EpdError> {
+        let operands = match self.operands(opcode) {
+            Some(operands) => operands,
+            None => return Ok(None),
+        };
+        let mut board = self.board.clone();
+        let mut moves = Vec::new();
+        for text in operands {
+            let mv = parse_move(&board, text).map_err(|e| EpdError::Move(opcode.to_string(), e))?;
+            board.make_move(mv);
+            moves.push(mv);
+        }
+        Ok(Some(moves))
+    }
+}
+
+// A move in SAN, the notation EPD is written in, or in the long algebraic notation some files use instead.
+pub fn parse_move(board: &ChessBoard, text: &str) -> Result<Move, SanError> {
+    board.parse_san(text).or_else(|e| uci::parse_move(board, text).ok_or(e))
+}
+
+// Splits the operations at their semicolons into words, a string in double quotes being one word.
+fn split_operations(text: &str) -> Result<Vec<Vec<String>>, EpdError> {
+    let mut operations = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                words.push(std::mem::take(&mut word));
+                quoted = false;
+            },
+            '"' => quoted = true,
+            '\\' if quoted => word.extend(chars.next()),
+            _ if quoted => word.push(c),
+            ';' | ' ' | '\t' => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                if c == ';' && !words.is_empty() {
+                    operations.push(std::mem::take(&mut words));
+                }
+            },
+            _ => word.push(c),
+        }
+    }
+    if quoted {
+        return Err(EpdError::Unclosed(text.trim().to_string()));
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    // The last operation's semicolon is sometimes left off.
+    if !words.is_empty() {
+        operations.push(words);
+    }
+    Ok(operations)
+}
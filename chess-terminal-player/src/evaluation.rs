@@ -0,0 +1,299 @@
+use crate::bitboard::{self, Bitboard, FILE_A};
+use crate::chessboard::{ChessBoard, Colour, Piece, PieceKind};
+
+// Piece values in centipawns.
+pub const PAWN_VALUE: i32 = 100;
+pub const KNIGHT_VALUE: i32 = 320;
+pub const BISHOP_VALUE: i32 = 330;
+pub const ROOK_VALUE: i32 = 500;
+pub const QUEEN_VALUE: i32 = 900;
+
+// Centipawns for each square a piece can go to that no enemy pawn guards.
+const KNIGHT_MOBILITY: i32 = 4;
+const BISHOP_MOBILITY: i32 = 5;
+const ROOK_MOBILITY: i32 = 2;
+const QUEEN_MOBILITY: i32 = 1;
+
+// King safety while there is material to attack it: pawns standing in front of the king, files beside it
+// without a pawn of its own, and every square around it an enemy piece attacks, weighted by the attacker.
+const SHIELD_BONUS: i32 = 10;
+const OPEN_FILE_PENALTY: i32 = 20;
+const KNIGHT_ATTACK: i32 = 4;
+const BISHOP_ATTACK: i32 = 4;
+const ROOK_ATTACK: i32 = 6;
+const QUEEN_ATTACK: i32 = 10;
+
+const DOUBLED_PAWN_PENALTY: i32 = 15;
+const ISOLATED_PAWN_PENALTY: i32 = 15;
+// Bonus for a pawn no enemy pawn can stop, by how many ranks it has come from its own side.
+const PASSED_PAWN_BONUS: [i32; 8] = [0, 10, 15, 25, 40, 65, 100, 0];
+
+// Piece-square tables from white's side, written the way the board is seen: a8 first, h1 last.
+// Black uses the same tables mirrored top to bottom.
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+// The king hides behind its pawns while there is material to attack it, then heads for the centre.
+const KING_MIDDLEGAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+pub fn piece_value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn => PAWN_VALUE,
+        PieceKind::Knight => KNIGHT_VALUE,
+        PieceKind::Bishop => BISHOP_VALUE,
+        PieceKind::Rook => ROOK_VALUE,
+        PieceKind::Queen => QUEEN_VALUE,
+        _ => 0,
+    }
+}
+
+// Bonus for a piece standing on `square`, (row, col) with (0, 0) being a1.
+fn square_bonus(piece: Piece, square: (usize, usize), endgame: bool) -> i32 {
+    let table = match piece.kind {
+        PieceKind::Pawn => &PAWN_TABLE,
+        PieceKind::Knight => &KNIGHT_TABLE,
+        PieceKind::Bishop => &BISHOP_TABLE,
+        PieceKind::Rook => &ROOK_TABLE,
+        PieceKind::Queen => &QUEEN_TABLE,
+        PieceKind::King if endgame => &KING_ENDGAME_TABLE,
+        PieceKind::King => &KING_MIDDLEGAME_TABLE,
+        PieceKind::None => return 0,
+    };
+    let line = match piece.colour {
+        Colour::White => 7 - square.0,
+        _ => square.0,
+    };
+    table[line * 8 + square.1]
+}
+
+// Endgame once the queens are gone, or a side with a queen has at most one minor piece besides.
+fn is_endgame(board: &ChessBoard) -> bool {
+    let mut queens = [0, 0];
+    let mut minors = [0, 0];
+    let mut rooks = [0, 0];
+    for row in 0..8 {
+        for col in 0..8 {
+            if let Some(piece) = board.piece_at((row, col)) {
+                let side = if piece.colour == Colour::White { 0 } else { 1 };
+                match piece.kind {
+                    PieceKind::Queen => queens[side] += 1,
+                    PieceKind::Knight | PieceKind::Bishop => minors[side] += 1,
+                    PieceKind::Rook => rooks[side] += 1,
+                    _ => {},
+                }
+            }
+        }
+    }
+    (0..2).all(|side| queens[side] == 0 || (rooks[side] == 0 && minors[side] <= 1))
+}
+
+// The static evaluation split into its terms, each in centipawns from white's point of view.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Breakdown {
+    pub material: i32,
+    pub placement: i32,  // The piece-square bonuses
+    pub mobility: i32,
+    pub king_safety: i32,
+    pub pawn_structure: i32,
+}
+
+impl Breakdown {
+    pub fn total(&self) -> i32 {
+        self.material + self.placement + self.mobility + self.king_safety + self.pawn_structure
+    }
+}
+
+pub fn breakdown(board: &ChessBoard) -> Breakdown {
+    let endgame = is_endgame(board);
+    let mut terms = Breakdown::default();
+    for row in 0..8 {
+        for col in 0..8 {
+            if let Some(piece) = board.piece_at((row, col)) {
+                let sign = if piece.colour == Colour::White { 1 } else { -1 };
+                terms.material += sign * piece_value(piece.kind);
+                terms.placement += sign * square_bonus(piece, (row, col), endgame);
+            }
+        }
+    }
+    let both = |term: fn(&ChessBoard, Colour) -> i32| term(board, Colour::White) - term(board, Colour::Black);
+    terms.mobility = both(mobility);
+    terms.king_safety = if endgame { 0 } else { both(king_safety) };
+    terms.pawn_structure = both(pawn_structure);
+    terms
+}
+
+// Static evaluation in centipawns from the side to move's point of view, the sum of the terms in `breakdown`.
+pub fn evaluate(board: &ChessBoard) -> i32 {
+    let score = breakdown(board).total();
+    if board.side_to_move() == Colour::White { score } else { -score }
+}
+
+// Squares the pawns of `colour` attack.
+fn pawn_guard(board: &ChessBoard, colour: Colour) -> Bitboard {
+    bitboard::squares(board.bitboards().pieces(PieceKind::Pawn, colour))
+        .fold(0, |set, from| set | bitboard::pawn_attacks(from, colour))
+}
+
+fn mobility(board: &ChessBoard, colour: Colour) -> i32 {
+    let bitboards = board.bitboards();
+    let reachable = !bitboards.colour(colour) & !pawn_guard(board, colour.opponent());
+    let weights = [
+        (PieceKind::Knight, KNIGHT_MOBILITY),
+        (PieceKind::Bishop, BISHOP_MOBILITY),
+        (PieceKind::Rook, ROOK_MOBILITY),
+        (PieceKind::Queen, QUEEN_MOBILITY),
+    ];
+    weights.iter().map(|(kind, weight)| {
+        bitboard::squares(bitboards.pieces(*kind, colour))
+            .map(|from| weight * (bitboards.attacks_from(*kind, colour, from) & reachable).count_ones() as i32)
+            .sum::<i32>()
+    }).sum()
+}
+
+fn king_safety(board: &ChessBoard, colour: Colour) -> i32 {
+    let bitboards = board.bitboards();
+    let king = match bitboards.king(colour) {
+        Some(king) => king,
+        None => return 0,
+    };
+    let (row, col) = bitboard::square(king);
+    let pawns = bitboards.pieces(PieceKind::Pawn, colour);
+    let forward = if colour == Colour::White { 1 } else { -1 };
+    let mut score = 0;
+    for file in col.saturating_sub(1)..=(col + 1).min(7) {
+        let on_file = pawns & (FILE_A << file);
+        if on_file == 0 {
+            score -= OPEN_FILE_PENALTY;
+        }
+        // A pawn right in front counts fully, one a square further on half.
+        for (step, bonus) in [(1, SHIELD_BONUS), (2, SHIELD_BONUS / 2)].iter() {
+            let shield_row = row as i32 + forward * step;
+            if (0..8).contains(&shield_row) && on_file & bitboard::bit((shield_row as usize, file)) != 0 {
+                score += bonus;
+            }
+        }
+    }
+    let zone = bitboard::king_attacks(king) | bitboard::bit((row, col));
+    let weights = [
+        (PieceKind::Knight, KNIGHT_ATTACK),
+        (PieceKind::Bishop, BISHOP_ATTACK),
+        (PieceKind::Rook, ROOK_ATTACK),
+        (PieceKind::Queen, QUEEN_ATTACK),
+    ];
+    let enemy = colour.opponent();
+    for (kind, weight) in weights.iter() {
+        for from in bitboard::squares(bitboards.pieces(*kind, enemy)) {
+            score -= weight * (bitboards.attacks_from(*kind, enemy, from) & zone).count_ones() as i32;
+        }
+    }
+    score
+}
+
+fn pawn_structure(board: &ChessBoard, colour: Colour) -> i32 {
+    let bitboards = board.bitboards();
+    let pawns = bitboards.pieces(PieceKind::Pawn, colour);
+    let enemy = bitboards.pieces(PieceKind::Pawn, colour.opponent());
+    let mut score = 0;
+    for file in 0..8 {
+        let count = (pawns & (FILE_A << file)).count_ones() as i32;
+        if count > 1 {
+            score -= DOUBLED_PAWN_PENALTY * (count - 1);
+        }
+    }
+    for from in bitboard::squares(pawns) {
+        let (row, col) = bitboard::square(from);
+        let beside = neighbour_files(col);
+        if pawns & beside == 0 {
+            score -= ISOLATED_PAWN_PENALTY;
+        }
+        // Passed when no enemy pawn stands ahead on its own file or either next to it.
+        let ahead = match colour {
+            Colour::White => u64::MAX << ((row + 1) * 8),
+            _ => (1 << (row * 8)) - 1,
+        };
+        if enemy & ahead & (beside | FILE_A << col) == 0 {
+            let advanced = if colour == Colour::White { row } else { 7 - row };
+            score += PASSED_PAWN_BONUS[advanced];
+        }
+    }
+    score
+}
+
+// The files either side of `col`.
+fn neighbour_files(col: usize) -> Bitboard {
+    let left = if col > 0 { FILE_A << (col - 1) } else { 0 };
+    let right = if col < 7 { FILE_A << (col + 1) } else { 0 };
+    left | right
+}
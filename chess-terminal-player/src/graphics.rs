@@ -1,44 +1,52 @@
 #[allow(dead_code)]
 pub mod table {
-    pub const BOTTOM_RIGHT_CORNER: &'static str = "╝";
-    pub const BOTTOM_LEFT_CORNER: &'static str = "╚";
-    pub const TOP_RIGHT_CORNER: &'static str = "╗";
-    pub const TOP_LEFT_CORNER: &'static str = "╔";
-    pub const HORIZONTAL_WALL: &'static str = "═";
-    pub const VERTICAL_WALL: &'static str = "║";
-    pub const HORIZONTAL_LEFT_DT: &'static str = "╠";
-    pub const HORIZONTAL_RIGHT_DT: &'static str = "╣";
-    pub const VERTICAL_BOTTOM_DT: &'static str = "╩";
-    pub const VERTICAL_TOP_DT: &'static str = "╦";
+    pub const BOTTOM_RIGHT_CORNER: &str = "╝";
+    pub const BOTTOM_LEFT_CORNER: &str = "╚";
+    pub const TOP_RIGHT_CORNER: &str = "╗";
+    pub const TOP_LEFT_CORNER: &str = "╔";
+    pub const HORIZONTAL_WALL: &str = "═";
+    pub const VERTICAL_WALL: &str = "║";
+    pub const HORIZONTAL_LEFT_DT: &str = "╠";
+    pub const HORIZONTAL_RIGHT_DT: &str = "╣";
+    pub const VERTICAL_BOTTOM_DT: &str = "╩";
+    pub const VERTICAL_TOP_DT: &str = "╦";
 
-    pub const HORIZONTAL_LINE: &'static str = "─";
-    pub const VERTICAL_LINE: &'static str = "│";
-    pub const CROSS: &'static str = "┼";
+    pub const HORIZONTAL_LINE: &str = "─";
+    pub const VERTICAL_LINE: &str = "│";
+    pub const CROSS: &str = "┼";
 
-    pub const HORIZONTAL_LEFT_T: &'static str = "╟";
-    pub const HORIZONTAL_RIGHT_T: &'static str = "╢";
-    pub const VERTICAL_BOTTOM_T: &'static str = "╧";
-    pub const VERTICAL_TOP_T: &'static str = "╤";
+    pub const HORIZONTAL_LEFT_T: &str = "╟";
+    pub const HORIZONTAL_RIGHT_T: &str = "╢";
+    pub const VERTICAL_BOTTOM_T: &str = "╧";
+    pub const VERTICAL_TOP_T: &str = "╤";
 }
 #[allow(dead_code)]
 pub mod pieces {
-    pub const WHITE_KING: &'static str = "♔";
-    pub const WHITE_QUEEN: &'static str = "♕";
-    pub const WHITE_ROOK: &'static str = "♖";
-    pub const WHITE_BISHOP: &'static str = "♗";
-    pub const WHITE_KNIGHT: &'static str = "♘";
-    pub const WHITE_PAWN: &'static str = "♙";
+    pub const WHITE_KING: &str = "♔";
+    pub const WHITE_QUEEN: &str = "♕";
+    pub const WHITE_ROOK: &str = "♖";
+    pub const WHITE_BISHOP: &str = "♗";
+    pub const WHITE_KNIGHT: &str = "♘";
+    pub const WHITE_PAWN: &str = "♙";
 
-    pub const BLACK_KING: &'static str = "♚";
-    pub const BLACK_QUEEN: &'static str = "♛";
-    pub const BLACK_ROOK: &'static str = "♜";
-    pub const BLACK_BISHOP: &'static str = "♝";
-    pub const BLACK_KNIGHT: &'static str = "♞";
-    pub const BLACK_PAWN: &'static str = "♟";
+    pub const BLACK_KING: &str = "♚";
+    pub const BLACK_QUEEN: &str = "♛";
+    pub const BLACK_ROOK: &str = "♜";
+    pub const BLACK_BISHOP: &str = "♝";
+    pub const BLACK_KNIGHT: &str = "♞";
+    pub const BLACK_PAWN: &str = "♟";
 }
 #[allow(dead_code)]
 pub mod borders {
-    pub const TOP: &'static str = "╔═══╤═══╤═══╤═══╤═══╤═══╤═══╤═══╗";
-    pub const BOTTOM: &'static str = "╚═══╧═══╧═══╧═══╧═══╧═══╧═══╧═══╝";
-    pub const MID: &'static str = "╟───┼───┼───┼───┼───┼───┼───┼───╢";
+    pub const TOP: &str = "╔═══╤═══╤═══╤═══╤═══╤═══╤═══╤═══╗";
+    pub const BOTTOM: &str = "╚═══╧═══╧═══╧═══╧═══╧═══╧═══╧═══╝";
+    pub const MID: &str = "╟───┼───┼───┼───┼───┼───┼───┼───╢";
+}
+#[allow(dead_code)]
+pub mod ascii {
+    pub const TOP: &str = "+---+---+---+---+---+---+---+---+";
+    pub const BOTTOM: &str = "+---+---+---+---+---+---+---+---+";
+    pub const MID: &str = "+---+---+---+---+---+---+---+---+";
+    pub const VERTICAL_WALL: &str = "|";
+    pub const VERTICAL_LINE: &str = "|";
 }
@@ -0,0 +1,20 @@
+extern crate termion;
+extern crate regex;
+
+mod bitboard;
+pub mod book;
+pub mod chessboard;
+pub mod clock;
+pub mod epd;
+pub mod evaluation;
+pub mod network;
+pub mod pgn;
+pub mod puzzle;
+pub mod search;
+pub mod session;
+pub mod suite;
+pub mod tablebase;
+pub mod theme;
+mod transposition;
+pub mod uci;
+mod zobrist;
@@ -1,15 +1,253 @@
-extern crate termion;
+extern crate chess_player;
 
-use termion::terminal_size;
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
 
-mod chessboard;
-use chessboard::ChessBoard;
+use chess_player::book::Book;
+use chess_player::chessboard::{ChessBoard, Colour};
+use chess_player::clock::TimeControl;
+use chess_player::network::DEFAULT_PORT;
+use chess_player::puzzle::{self, PuzzleStats, Trainer};
+use chess_player::search::Limits;
+use chess_player::session::Session;
+use chess_player::suite::Suite;
+use chess_player::tablebase::Tablebase;
+use chess_player::theme::{self, THEME_NAMES};
+use chess_player::uci::{self, UciClient};
+
+// How long the engine thinks on each position of a test suite when not given a depth or a time.
+const SUITE_MOVETIME: Duration = Duration::from_secs(1);
+
+const USAGE: &str = "Usage: chess-player [--uci] [--suite FILE]... [--engine white|black] [--uci-engine COMMAND] [--depth N] [--movetime MS] \
+                     [--clock CONTROL] [--theme NAME] [--ascii] [--book FILE] [--syzygy DIR] \
+                     [--chess960 N|random] [--start PIECES] [--host [ADDRESS:]PORT [--side white|black]] \
+                     [--join HOST[:PORT]] [--puzzles FILE [--puzzle-stats FILE]]";
 
 fn main() {
-    let mut board = match terminal_size() {
-        Ok(_) => ChessBoard::new(),
-        _ => panic!("Can't get terminal size!")
-    };
-    board.move_piece("e4");
-    board.draw();
+    let mut engine_side = None;
+    let mut engine_command = None;
+    let mut limits = Limits { depth: None, time: None };
+    let mut clock = None;
+    let mut theme = None;
+    let mut book = None;
+    let mut tablebase = None;
+    let mut puzzles = None;
+    let mut puzzle_stats = puzzle::default_stats_path();
+    let mut suites = Vec::new();
+    let mut board = ChessBoard::new();
+    let mut host = None;
+    let mut join = None;
+    let mut side = Colour::White;
+    let mut ascii = !theme::unicode_locale();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--uci" {
+            let stdin = io::stdin();
+            if let Err(e) = uci::run(stdin.lock(), io::stdout()) {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+        if arg == "--ascii" {
+            ascii = true;
+            continue;
+        }
+        let value = args.next();
+        match (arg.as_str(), value.as_deref()) {
+            ("--engine", Some("white")) => engine_side = Some(Colour::White),
+            ("--engine", Some("black")) => engine_side = Some(Colour::Black),
+            ("--uci-engine", Some(command)) => engine_command = Some(command.to_string()),
+            ("--depth", Some(n)) if n.parse::<u32>().is_ok() => limits.depth = n.parse().ok(),
+            ("--movetime", Some(ms)) if ms.parse::<u64>().is_ok() => {
+                limits.time = ms.parse().ok().map(Duration::from_millis);
+            },
+            ("--theme", Some(name)) => match theme::for_terminal(name) {
+                Some(named) => theme = Some(named),
+                None => {
+                    eprintln!("Unknown theme, {}, try {}", name, THEME_NAMES.join(", "));
+                    process::exit(2);
+                },
+            },
+            ("--book", Some(path)) => match Book::open(path) {
+                Ok(opened) => book = Some(opened),
+                Err(e) => {
+                    eprintln!("Can't open {}: {}", path, e);
+                    process::exit(1);
+                },
+            },
+            ("--syzygy", Some(dir)) => match Tablebase::open(dir) {
+                Ok(opened) => tablebase = Some(opened),
+                Err(e) => {
+                    eprintln!("Can't open {}: {}", dir, e);
+                    process::exit(1);
+                },
+            },
+            ("--puzzles", Some(path)) => match puzzle::load(path) {
+                Ok(loaded) if loaded.is_empty() => {
+                    eprintln!("No puzzles in {}", path);
+                    process::exit(1);
+                },
+                Ok(loaded) => puzzles = Some(loaded),
+                Err(e) => {
+                    eprintln!("Can't read {}: {}", path, e);
+                    process::exit(1);
+                },
+            },
+            ("--suite", Some(path)) => suites.push(path.to_string()),
+            ("--puzzle-stats", Some(path)) => puzzle_stats = PathBuf::from(path),
+            ("--chess960", Some(number)) => {
+                // Any of the 960 when not given a number.
+                let number = match number {
+                    "random" => Some((RandomState::new().build_hasher().finish() % 960) as u32),
+                    n => n.parse::<u32>().ok(),
+                };
+                match number.and_then(ChessBoard::chess960) {
+                    Some(start) => board = start,
+                    None => {
+                        eprintln!("Chess960 positions are numbered 0 to 959");
+                        process::exit(2);
+                    },
+                }
+            },
+            ("--start", Some(pieces)) => match ChessBoard::from_back_rank(pieces) {
+                Ok(start) => board = start,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(2);
+                },
+            },
+            // A bare port listens on every address, a bare host name is joined on the default port.
+            ("--host", Some(addr)) if addr.contains(':') => host = Some(addr.to_string()),
+            ("--host", Some(port)) => host = Some(format!("0.0.0.0:{}", port)),
+            ("--join", Some(addr)) if addr.contains(':') => join = Some(addr.to_string()),
+            ("--join", Some(name)) => join = Some(format!("{}:{}", name, DEFAULT_PORT)),
+            ("--side", Some("white")) => side = Colour::White,
+            ("--side", Some("black")) => side = Colour::Black,
+            ("--clock", Some(control)) => match control.parse::<TimeControl>() {
+                Ok(control) => clock = Some(control),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(2);
+                },
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            },
+        }
+    }
+    if !suites.is_empty() {
+        if limits.depth.is_none() && limits.time.is_none() {
+            limits.time = Some(SUITE_MOVETIME);
+        }
+        run_suites(&suites, limits);
+        return;
+    }
+    // On the clock the engine goes by its share of the time left instead.
+    if limits.depth.is_none() && limits.time.is_none() && clock.is_none() {
+        limits.depth = Some(4);
+    }
+
+    if (host.is_some() || join.is_some()) && (engine_side.is_some() || engine_command.is_some() || clock.is_some()) {
+        eprintln!("Games over the network are played without an engine or a clock");
+        process::exit(2);
+    }
+
+    if puzzles.is_some() && (host.is_some() || join.is_some() || engine_side.is_some() || engine_command.is_some()
+                             || clock.is_some()) {
+        eprintln!("Puzzles are solved without an engine, a clock or a game over the network");
+        process::exit(2);
+    }
+
+    let mut session = Session::new(board);
+    if let Some(puzzles) = puzzles {
+        let stats = match PuzzleStats::load(&puzzle_stats) {
+            Ok(stats) => stats,
+            Err(e) => {
+                eprintln!("Can't read {}: {}", puzzle_stats.display(), e);
+                process::exit(1);
+            },
+        };
+        if let Some(trainer) = Trainer::new(puzzles, stats) {
+            session.set_puzzles(trainer, puzzle_stats);
+        }
+    }
+    if let Some(addr) = host {
+        if let Err(e) = session.host(&addr, side) {
+            eprintln!("Can't listen on {}: {}", addr, e);
+            process::exit(1);
+        }
+    } else if let Some(addr) = join {
+        if let Err(e) = session.join(&addr) {
+            eprintln!("Can't connect to {}: {}", addr, e);
+            process::exit(1);
+        }
+    }
+    if let Some(command) = engine_command {
+        // The program and its arguments, split on whitespace.
+        let mut words = command.split_whitespace().map(String::from);
+        let program = words.next().unwrap_or_default();
+        let args: Vec<String> = words.collect();
+        if let Err(e) = UciClient::spawn(&program, &args).and_then(|client| session.set_external_engine(client)) {
+            eprintln!("Can't start {}: {}", program, e);
+            process::exit(1);
+        }
+        engine_side = engine_side.or(Some(Colour::Black));
+    }
+    session.set_engine(engine_side, limits);
+    if let Some(book) = book {
+        session.set_book(book);
+    }
+    if let Some(tablebase) = tablebase {
+        session.set_tablebase(tablebase);
+    }
+    let options = session.draw_options();
+    options.ascii = ascii;
+    if let Some(theme) = theme {
+        options.theme = theme;
+    }
+    if let Some(control) = clock {
+        session.set_clock(control);
+    }
+    if let Err(e) = session.run() {
+        eprintln!("{}", e);
+    }
+}
+
+// Runs the engine on each test suite, listing every position as it is done and then the number solved.
+fn run_suites(paths: &[String], limits: Limits) {
+    let (mut solved, mut total) = (0, 0);
+    for path in paths {
+        let suite = match Suite::open(path) {
+            Ok(suite) => suite,
+            Err(e) => {
+                eprintln!("Can't read {}: {}", path, e);
+                process::exit(1);
+            },
+        };
+        let outcomes = suite.run(limits, |test, outcome| {
+            let played = outcome.played.as_deref().unwrap_or("none");
+            if outcome.solved {
+                println!("{:<16} {:<8} solved", outcome.id, played);
+            } else {
+                println!("{:<16} {:<8} missed, {}", outcome.id, played, test.expected());
+            }
+        });
+        let count = outcomes.iter().filter(|outcome| outcome.solved).count();
+        println!("{}: {} of {} solved{}\n", suite.name, count, outcomes.len(), percentage(count, outcomes.len()));
+        solved += count;
+        total += outcomes.len();
+    }
+    if paths.len() > 1 {
+        println!("Total: {} of {} solved{}", solved, total, percentage(solved, total));
+    }
+}
+
+fn percentage(count: usize, total: usize) -> String {
+    (count * 100).checked_div(total).map_or_else(String::new, |percent| format!(" ({}%)", percent))
 }
@@ -0,0 +1,266 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::chessboard::{ChessBoard, Colour, GameResult};
+
+// Two players at their own terminals, one hosting the game and the other joining it, talking over TCP one
+// line at a time:
+//
+//   hello 1 black               the host greets with the protocol version and the side the joiner plays
+//   hello 1                     the joiner greets back
+//   move Nf3                    a move in SAN, checked against the board before it is played
+//   offer-draw                  offering a draw, or taking up the other side's offer
+//   resign
+//   sync                        asking for the game as the host has it
+//   sync <FEN> moves e4 e5 ...  the game as the host has it, the start position and the moves since, followed
+//                               by `resigned white` or `drawn` once a player has resigned or a draw was agreed
+//
+// The host keeps the game going when the connection drops, and the joiner keeps trying to connect again.
+// Once connected the host sends the game, so both are back in step.
+
+pub const PROTOCOL_VERSION: u32 = 2;
+pub const DEFAULT_PORT: u16 = 7464;
+// How long the joiner waits between attempts to connect again.
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Hello { version: u32, colour: Option<Colour> },  // The side the receiver plays, sent by the host
+    Move(String),
+    OfferDraw,
+    Resign,
+    SyncRequest,
+    Sync { fen: String, moves: Vec<String>, ended: Option<GameResult> },  // Only resignations and agreed draws
+}
+
+// A line that isn't one of the messages.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MessageError(String);
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid message, {}", self.0)
+    }
+}
+
+impl FromStr for Message {
+    type Err = MessageError;
+
+    fn from_str(line: &str) -> Result<Message, MessageError> {
+        let error = || MessageError(String::from(line));
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["hello", version] => Ok(Message::Hello { version: version.parse().map_err(|_| error())?, colour: None }),
+            ["hello", version, colour] => Ok(Message::Hello {
+                version: version.parse().map_err(|_| error())?,
+                colour: Some(parse_colour(colour).ok_or_else(error)?),
+            }),
+            ["move", san] => Ok(Message::Move(String::from(san))),
+            ["offer-draw"] => Ok(Message::OfferDraw),
+            ["resign"] => Ok(Message::Resign),
+            ["sync"] => Ok(Message::SyncRequest),
+            ["sync", ..] => {
+                let (words, ended) = match words[..] {
+                    [.., "drawn"] => (&words[..words.len() - 1], Some(GameResult::Agreement)),
+                    [.., "resigned", colour] => {
+                        let loser = parse_colour(colour).ok_or_else(error)?;
+                        (&words[..words.len() - 2], Some(GameResult::Resignation(loser.opponent())))
+                    },
+                    _ => (&words[..], None),
+                };
+                let moves_at = words.iter().position(|w| *w == "moves").unwrap_or(words.len());
+                if moves_at == 1 {
+                    return Err(error());
+                }
+                Ok(Message::Sync {
+                    fen: words[1..moves_at].join(" "),
+                    moves: words.iter().skip(moves_at + 1).map(|m| String::from(*m)).collect(),
+                    ended,
+                })
+            },
+            _ => Err(error()),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Hello { version, colour: None } => write!(f, "hello {}", version),
+            Message::Hello { version, colour: Some(colour) } => write!(f, "hello {} {}", version, colour_name(*colour)),
+            Message::Move(san) => write!(f, "move {}", san),
+            Message::OfferDraw => write!(f, "offer-draw"),
+            Message::Resign => write!(f, "resign"),
+            Message::SyncRequest => write!(f, "sync"),
+            Message::Sync { fen, moves, ended } => {
+                write!(f, "sync {}", fen)?;
+                if !moves.is_empty() {
+                    write!(f, " moves {}", moves.join(" "))?;
+                }
+                match ended {
+                    Some(GameResult::Resignation(winner)) => write!(f, " resigned {}", colour_name(winner.opponent())),
+                    Some(GameResult::Agreement) => write!(f, " drawn"),
+                    _ => Ok(()),
+                }
+            },
+        }
+    }
+}
+
+fn parse_colour(name: &str) -> Option<Colour> {
+    match name {
+        "white" => Some(Colour::White),
+        "black" => Some(Colour::Black),
+        _ => None,
+    }
+}
+
+fn colour_name(colour: Colour) -> &'static str {
+    if colour == Colour::Black { "black" } else { "white" }
+}
+
+impl Message {
+    // The whole game on a board, to bring the other side into step, with how it ended if a player resigned or
+    // the two agreed a draw. Other endings are for each side to see on its own board or clock.
+    pub fn sync(board: &ChessBoard, ended: Option<GameResult>) -> Message {
+        let mut start = board.clone();
+        while start.unmake_move().is_some() {}
+        let ended = ended.filter(|result| matches!(result, GameResult::Resignation(_) | GameResult::Agreement));
+        Message::Sync { fen: start.to_fen(), moves: board.san_history(), ended }
+    }
+}
+
+// The board a sync message describes, with every move checked as it is played.
+pub fn replay(fen: &str, moves: &[String]) -> Result<ChessBoard, String> {
+    let mut board = ChessBoard::from_fen(fen).map_err(|e| e.to_string())?;
+    for san in moves {
+        board.move_piece(san).map_err(|e| e.to_string())?;
+    }
+    Ok(board)
+}
+
+// What happened on the connection, passed on as it happens from the peer's own thread.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PeerEvent {
+    Connected,
+    Received(Message),
+    Garbled(MessageError),
+    Disconnected,
+}
+
+// One end of a game over TCP. A thread looks after the connection, reading messages as they come and
+// connecting again after it drops: the host waits for the joiner to come back, the joiner dials again.
+pub struct Peer {
+    stream: Arc<Mutex<Option<TcpStream>>>,  // The connection while there is one, for writing
+    closed: Arc<AtomicBool>,
+    local_addr: SocketAddr,
+}
+
+impl Peer {
+    // Listens on `addr` for the other player. Port 0 picks a free port, see `local_addr`. The thread is left
+    // blocked waiting for a connection when the peer is closed.
+    pub fn host<A, F>(addr: A, mut notify: F) -> io::Result<Peer>
+    where
+        A: ToSocketAddrs,
+        F: FnMut(PeerEvent) + Send + 'static,
+    {
+        let listener = TcpListener::bind(addr)?;
+        let peer = Peer::new(listener.local_addr()?);
+        let (shared, closed) = (peer.stream.clone(), peer.closed.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if closed.load(Ordering::Relaxed) { break; }
+                if let Ok(stream) = stream {
+                    serve(stream, &shared, &mut notify);
+                }
+            }
+        });
+        Ok(peer)
+    }
+
+    // Connects to the host at `addr`, failing at once if it can't be reached.
+    pub fn join<A, F>(addr: A, mut notify: F) -> io::Result<Peer>
+    where
+        A: ToSocketAddrs,
+        F: FnMut(PeerEvent) + Send + 'static,
+    {
+        let addr = addr.to_socket_addrs()?.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to"))?;
+        let mut stream = TcpStream::connect(addr)?;
+        let peer = Peer::new(stream.local_addr()?);
+        let (shared, closed) = (peer.stream.clone(), peer.closed.clone());
+        thread::spawn(move || loop {
+            serve(stream, &shared, &mut notify);
+            stream = loop {
+                if closed.load(Ordering::Relaxed) { return; }
+                thread::sleep(RECONNECT_INTERVAL);
+                if let Ok(stream) = TcpStream::connect(addr) { break stream; }
+            };
+            if closed.load(Ordering::Relaxed) { return; }
+        });
+        Ok(peer)
+    }
+
+    fn new(local_addr: SocketAddr) -> Peer {
+        Peer { stream: Arc::new(Mutex::new(None)), closed: Arc::new(AtomicBool::new(false)), local_addr }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.lock().unwrap().is_some()
+    }
+
+    pub fn send(&self, message: &Message) -> io::Result<()> {
+        match self.stream.lock().unwrap().as_mut() {
+            Some(stream) => writeln!(stream, "{}", message),
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, "not connected")),
+        }
+    }
+
+    // Drops the connection as if the network had, to be made again.
+    pub fn disconnect(&self) {
+        if let Some(stream) = self.stream.lock().unwrap().as_ref() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    // Drops the connection for good.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.disconnect();
+    }
+}
+
+impl Drop for Peer {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// Reads messages off a connection until it drops.
+fn serve<F: FnMut(PeerEvent)>(stream: TcpStream, shared: &Mutex<Option<TcpStream>>, notify: &mut F) {
+    let reader = match stream.try_clone() {
+        Ok(reader) => reader,
+        Err(_) => return,
+    };
+    *shared.lock().unwrap() = Some(stream);
+    notify(PeerEvent::Connected);
+    for line in BufReader::new(reader).lines().map_while(Result::ok) {
+        if line.trim().is_empty() { continue; }
+        notify(match line.parse() {
+            Ok(message) => PeerEvent::Received(message),
+            Err(e) => PeerEvent::Garbled(e),
+        });
+    }
+    *shared.lock().unwrap() = None;
+    notify(PeerEvent::Disconnected);
+}
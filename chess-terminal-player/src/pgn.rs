@@ -0,0 +1,595 @@
+use std::fmt;
+use std::io::{self, BufRead};
+use std::time::Duration;
+
+use crate::chessboard::{ChessBoard, Colour, FenError, SanError, STARTING_FEN};
+use crate::clock::Clock;
+
+// Tags every PGN game carries, in the order they are written out.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+// Move suffix annotations and the Numeric Annotation Glyphs they stand for.
+const SUFFIX_NAGS: [(&str, u8); 6] = [("!!", 3), ("??", 4), ("!?", 5), ("?!", 6), ("!", 1), ("?", 2)];
+
+const LINE_WIDTH: usize = 79;
+
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    Tag(String),
+    UnclosedComment,
+    UnbalancedVariation,
+    UnexpectedCharacter(char),
+    Fen(FenError),
+    Move(usize, SanError),  // (ply, counted from 1, reason)
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::Io(e) => write!(f, "Can't read PGN, {}", e),
+            PgnError::Tag(t) => write!(f, "Malformed tag pair, {}", t),
+            PgnError::UnclosedComment => write!(f, "Comment is never closed"),
+            PgnError::UnbalancedVariation => write!(f, "Unbalanced variation parentheses"),
+            PgnError::UnexpectedCharacter(c) => write!(f, "Unexpected character, {}", c),
+            PgnError::Fen(e) => write!(f, "Invalid FEN tag, {}", e),
+            PgnError::Move(ply, e) => write!(f, "Move {}: {}", ply, e),
+        }
+    }
+}
+
+impl From<io::Error> for PgnError {
+    fn from(e: io::Error) -> PgnError {
+        PgnError::Io(e)
+    }
+}
+
+// A move of the movetext with everything annotated on it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnMove {
+    pub san: String,
+    pub nags: Vec<u8>,
+    pub comment_before: Option<String>,  // Only found at the start of a game or variation
+    pub comment: Option<String>,
+    pub variations: Vec<Vec<PgnMove>>,  // Alternatives to this move
+}
+
+impl PgnMove {
+    pub fn new(san: &str) -> PgnMove {
+        PgnMove {
+            san: String::from(san),
+            nags: Vec::new(),
+            comment_before: None,
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+
+    // Time left on the mover's clock, from a `[%clk 1:05:23]` command in the comment.
+    pub fn clock(&self) -> Option<Duration> {
+        let comment = self.comment.as_ref()?;
+        let start = comment.find("[%clk ")? + "[%clk ".len();
+        let end = start + comment[start..].find(']')?;
+        let fields: Vec<&str> = comment[start..end].trim().split(':').collect();
+        if fields.len() != 3 {
+            return None;
+        }
+        let hours = fields[0].parse::<u64>().ok()?;
+        let minutes = fields[1].parse::<u64>().ok()?;
+        let seconds = fields[2].parse::<f64>().ok().filter(|s| *s >= 0.0)?;
+        Some(Duration::from_secs(hours * 3600 + minutes * 60) + Duration::from_secs_f64(seconds))
+    }
+
+    // Writes the time left into the comment as `[%clk h:mm:ss]`, replacing any time already there.
+    pub fn set_clock(&mut self, time: Duration) {
+        let seconds = time.as_secs();
+        let command = format!("[%clk {}:{:02}:{:02}]", seconds / 3600, seconds / 60 % 60, seconds % 60);
+        let rest = match self.comment.take() {
+            Some(comment) => match comment.find("[%clk ") {
+                Some(start) => match comment[start..].find(']') {
+                    Some(end) => {
+                        let rest = format!("{} {}", &comment[..start], &comment[start + end + 1..]);
+                        rest.split_whitespace().collect::<Vec<&str>>().join(" ")
+                    },
+                    None => comment,
+                },
+                None => comment,
+            },
+            None => String::new(),
+        };
+        self.comment = Some(if rest.is_empty() { command } else { format!("{} {}", command, rest) });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,  // Main line
+    pub result: String,
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
+impl Game {
+    // An empty game with the seven tag roster filled with unknowns.
+    pub fn new() -> Game {
+        Game {
+            tags: SEVEN_TAG_ROSTER.iter().map(|(k, v)| (String::from(*k), String::from(*v))).collect(),
+            moves: Vec::new(),
+            result: String::from("*"),
+        }
+    }
+
+    // The moves played on a board so far, starting from wherever the board was set up.
+    pub fn from_board(board: &ChessBoard) -> Game {
+        let mut start = board.clone();
+        while start.unmake_move().is_some() {}
+
+        let mut game = Game::new();
+        let fen = start.to_fen();
+        if board.is_chess960() {
+            game.set_tag("Variant", "Chess960");
+        }
+        if fen != STARTING_FEN {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &fen);
+        }
+        for san in board.san_history() {
+            game.moves.push(PgnMove::new(&san));
+        }
+        if let Some(result) = board.result() {
+            game.result = String::from(result.pgn_token());
+            game.set_tag("Result", result.pgn_token());
+        }
+        game
+    }
+
+    // Records a clocked game: the time control as a tag, and the time left after every move.
+    pub fn set_clock(&mut self, clock: &Clock) {
+        self.set_tag("TimeControl", &clock.control().to_string());
+        for (m, time) in self.moves.iter_mut().zip(clock.move_times()) {
+            m.set_clock(time);
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(k, _)| k == name) {
+            Some(tag) => tag.1 = String::from(value),
+            None => self.tags.push((String::from(name), String::from(value))),
+        }
+    }
+
+    // Board the movetext starts from, given by the FEN tag or the standard starting position. A Variant tag
+    // of Chess960 marks the game as Fischer Random even when the setup looks like standard chess.
+    pub fn starting_board(&self) -> Result<ChessBoard, PgnError> {
+        let mut board = match self.tag("FEN") {
+            Some(fen) => ChessBoard::from_fen(fen).map_err(PgnError::Fen)?,
+            None => ChessBoard::new(),
+        };
+        if self.tag("Variant").is_some_and(|v| v.eq_ignore_ascii_case("chess960") || v.eq_ignore_ascii_case("fischerandom")) {
+            board.set_chess960(true);
+        }
+        Ok(board)
+    }
+
+    // Plays the main line through a board, checking every move is legal.
+    pub fn replay(&self) -> Result<ChessBoard, PgnError> {
+        let mut board = self.starting_board()?;
+        for (ply, m) in self.moves.iter().enumerate() {
+            let mv = board.parse_san(&m.san).map_err(|e| PgnError::Move(ply + 1, e))?;
+            board.make_move(mv);
+        }
+        Ok(board)
+    }
+
+    // Reads a single game, tag pairs followed by movetext.
+    pub fn parse(text: &str) -> Result<Game, PgnError> {
+        let mut game = Game { tags: Vec::new(), moves: Vec::new(), result: String::from("*") };
+        let mut lines: Vec<Vec<PgnMove>> = vec![Vec::new()];  // Stack of the main line and open variations
+        let mut pending_comment: Option<String> = None;
+        let mut result = None;
+
+        for token in Tokenizer::new(text) {
+            match token? {
+                Token::Tag(name, value) => game.tags.push((name, value)),
+                Token::Comment(comment) => {
+                    let line = lines.last_mut().unwrap();
+                    match line.last_mut() {
+                        Some(m) if pending_comment.is_none() => append_comment(&mut m.comment, comment),
+                        _ => append_comment(&mut pending_comment, comment),
+                    }
+                },
+                Token::Nag(nag) => {
+                    if let Some(m) = lines.last_mut().unwrap().last_mut() {
+                        m.nags.push(nag);
+                    }
+                },
+                Token::Move(san, nags) => {
+                    let mut m = PgnMove::new(&san);
+                    m.nags = nags;
+                    m.comment_before = pending_comment.take();
+                    lines.last_mut().unwrap().push(m);
+                },
+                Token::OpenVariation => {
+                    if lines.last().unwrap().is_empty() {
+                        return Err(PgnError::UnbalancedVariation);
+                    }
+                    lines.push(Vec::new());
+                },
+                Token::CloseVariation => {
+                    if lines.len() < 2 {
+                        return Err(PgnError::UnbalancedVariation);
+                    }
+                    let variation = lines.pop().unwrap();
+                    lines.last_mut().unwrap().last_mut().unwrap().variations.push(variation);
+                },
+                Token::Result(r) => result = Some(r),
+            }
+        }
+        if lines.len() != 1 {
+            return Err(PgnError::UnbalancedVariation);
+        }
+        game.moves = lines.pop().unwrap();
+        // A comment after the last move, or a game without moves.
+        if let Some(comment) = pending_comment {
+            if let Some(m) = game.moves.last_mut() {
+                append_comment(&mut m.comment, comment);
+            }
+        }
+        game.result = match (result, game.tag("Result")) {
+            (Some(r), _) => r,
+            (None, Some(r)) => String::from(r),
+            (None, None) => String::from("*"),
+        };
+        Ok(game)
+    }
+
+    // Move numbers carry on from the FEN tag. A FEN that can't be read is written back as it was, with the
+    // moves numbered from 1 with white to move, so a game read from a file can always be written out again.
+    fn write_movetext(&self, tokens: &mut Vec<String>) {
+        let first_ply = match self.starting_board() {
+            Ok(board) => {
+                let state = board.game_state();
+                let black = if state.side_to_move == Colour::Black { 1 } else { 0 };
+                state.fullmove_number as usize * 2 - 2 + black
+            },
+            Err(_) => 0,
+        };
+        write_line(&self.moves, first_ply, tokens);
+    }
+}
+
+impl fmt::Display for Game {
+    // Export format: the seven tag roster in order, other tags, then movetext wrapped at 79 columns.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, default) in SEVEN_TAG_ROSTER.iter() {
+            let value = match *name {
+                "Result" => self.result.as_str(),
+                _ => self.tag(name).unwrap_or(default),
+            };
+            writeln!(f, "[{} \"{}\"]", name, escape(value))?;
+        }
+        for (name, value) in self.tags.iter() {
+            if SEVEN_TAG_ROSTER.iter().any(|(k, _)| k == name) { continue; }
+            writeln!(f, "[{} \"{}\"]", name, escape(value))?;
+        }
+        writeln!(f)?;
+
+        let mut tokens = Vec::new();
+        self.write_movetext(&mut tokens);
+        tokens.push(self.result.clone());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+// Writes the moves of a line starting at `ply`, counted from 0 for white's first move of the game.
+fn write_line(moves: &[PgnMove], ply: usize, tokens: &mut Vec<String>) {
+    let mut needs_number = true;  // Black moves get "N..." after anything that interrupts the line
+    for (i, m) in moves.iter().enumerate() {
+        let ply = ply + i;
+        if let Some(comment) = &m.comment_before {
+            tokens.push(format!("{{{}}}", comment));
+            needs_number = true;
+        }
+        if ply.is_multiple_of(2) {
+            tokens.push(format!("{}.", ply / 2 + 1));
+        } else if needs_number {
+            tokens.push(format!("{}...", ply / 2 + 1));
+        }
+        tokens.push(m.san.clone());
+        needs_number = false;
+        for nag in m.nags.iter() {
+            tokens.push(format!("${}", nag));
+        }
+        if let Some(comment) = &m.comment {
+            tokens.push(format!("{{{}}}", comment));
+            needs_number = true;
+        }
+        // Parentheses go against the moves they enclose, as the export format asks.
+        for variation in m.variations.iter() {
+            let start = tokens.len();
+            write_line(variation, ply, tokens);
+            if tokens.len() > start {
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+            } else {
+                tokens.push(String::from("()"));
+            }
+            needs_number = true;
+        }
+    }
+}
+
+fn append_comment(target: &mut Option<String>, comment: String) {
+    match target {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(&comment);
+        },
+        None => *target = Some(comment),
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    Move(String, Vec<u8>),  // (SAN without suffix annotations, NAGs for the annotations)
+    OpenVariation,
+    CloseVariation,
+    Result(String),
+}
+
+struct Tokenizer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line_start: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(text: &'a str) -> Tokenizer<'a> {
+        Tokenizer { chars: text.chars().peekable(), line_start: true }
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> String {
+        let mut taken = String::new();
+        while let Some(c) = self.chars.peek() {
+            if !predicate(*c) { break; }
+            taken.push(*c);
+            self.chars.next();
+        }
+        taken
+    }
+
+    fn skip_line(&mut self) {
+        self.take_while(|c| c != '\n');
+    }
+
+    fn tag(&mut self) -> Result<Token, PgnError> {
+        let inner = self.take_while(|c| c != ']');
+        if self.chars.next() != Some(']') {
+            return Err(PgnError::Tag(inner));
+        }
+        let trimmed = inner.trim();
+        let (name, rest) = match trimmed.find(char::is_whitespace) {
+            Some(i) => (&trimmed[..i], trimmed[i..].trim()),
+            None => return Err(PgnError::Tag(inner.clone())),
+        };
+        if !rest.starts_with('"') || !rest.ends_with('"') || rest.len() < 2 {
+            return Err(PgnError::Tag(inner.clone()));
+        }
+        let mut value = String::new();
+        let mut escaped = false;
+        for c in rest[1..rest.len() - 1].chars() {
+            if escaped || c != '\\' {
+                value.push(c);
+                escaped = false;
+            } else {
+                escaped = true;
+            }
+        }
+        Ok(Token::Tag(String::from(name), value))
+    }
+
+    // Move numbers, SAN, results and anything glued to them.
+    fn symbol(&mut self) -> Option<Token> {
+        let word = self.take_while(|c| !c.is_whitespace() && !"{}()[];$".contains(c));
+        if RESULTS.contains(&word.as_str()) {
+            return Some(Token::Result(word));
+        }
+        // Drop a move number, "12." or "12...", possibly written straight before the move.
+        let digits = word.find(|c: char| !c.is_ascii_digit()).unwrap_or(word.len());
+        let san = if digits > 0 && word[digits..].starts_with('.') {
+            word[digits..].trim_start_matches('.')
+        } else {
+            word.trim_start_matches('.')
+        };
+        if san.is_empty() {
+            return None;
+        }
+        let mut san = san;
+        let mut nags = Vec::new();
+        for (suffix, nag) in SUFFIX_NAGS.iter() {
+            if san.ends_with(suffix) {
+                san = &san[..san.len() - suffix.len()];
+                nags.push(*nag);
+                break;
+            }
+        }
+        Some(Token::Move(String::from(san), nags))
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let c = *self.chars.peek()?;
+            // Lines starting with % are escaped from PGN altogether.
+            if self.line_start && c == '%' {
+                self.skip_line();
+                continue;
+            }
+            self.line_start = c == '\n';
+            if c.is_whitespace() {
+                self.chars.next();
+                continue;
+            }
+            match c {
+                '[' => {
+                    self.chars.next();
+                    return Some(self.tag());
+                },
+                '{' => {
+                    self.chars.next();
+                    let comment = self.take_while(|c| c != '}');
+                    if self.chars.next() != Some('}') {
+                        return Some(Err(PgnError::UnclosedComment));
+                    }
+                    return Some(Ok(Token::Comment(comment.split_whitespace().collect::<Vec<&str>>().join(" "))));
+                },
+                ';' => {
+                    self.chars.next();
+                    let comment = self.take_while(|c| c != '\n');
+                    return Some(Ok(Token::Comment(String::from(comment.trim()))));
+                },
+                '$' => {
+                    self.chars.next();
+                    let digits = self.take_while(|c| c.is_ascii_digit());
+                    return match digits.parse::<u8>() {
+                        Ok(nag) => Some(Ok(Token::Nag(nag))),
+                        Err(_) => Some(Err(PgnError::UnexpectedCharacter('$'))),
+                    };
+                },
+                '(' => {
+                    self.chars.next();
+                    return Some(Ok(Token::OpenVariation));
+                },
+                ')' => {
+                    self.chars.next();
+                    return Some(Ok(Token::CloseVariation));
+                },
+                ']' | '}' => {
+                    self.chars.next();
+                    return Some(Err(PgnError::UnexpectedCharacter(c)));
+                },
+                _ => {
+                    if let Some(token) = self.symbol() {
+                        return Some(Ok(token));
+                    }
+                },
+            }
+        }
+    }
+}
+
+// Reads games one at a time from a PGN file or any other buffered source.
+pub struct PgnReader<R: BufRead> {
+    source: R,
+    next_line: Option<String>,  // First line of the next game, read while looking for the end of the last one
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(source: R) -> PgnReader<R> {
+        PgnReader { source, next_line: None }
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, PgnError> {
+        if let Some(line) = self.next_line.take() {
+            return Ok(Some(line));
+        }
+        let mut line = String::new();
+        match self.source.read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line)),
+        }
+    }
+
+    // Collects the text of one game. The movetext ends where the next tag section starts, taking care
+    // not to be fooled by brackets inside comments.
+    fn read_game_text(&mut self) -> Result<Option<String>, PgnError> {
+        let mut text = String::new();
+        let mut in_movetext = false;
+        let mut in_comment = false;
+        while let Some(line) = self.read_line()? {
+            let trimmed = line.trim_start();
+            if !in_comment && trimmed.starts_with('[') && in_movetext {
+                self.next_line = Some(line);
+                break;
+            }
+            if !in_comment && !trimmed.is_empty() && !trimmed.starts_with('[') && !trimmed.starts_with('%') {
+                in_movetext = true;
+            }
+            // Braces only count outside tag values and `;` comments, both of which end with the line.
+            let mut in_quotes = false;
+            let mut escaped = false;
+            for c in line.chars() {
+                if in_quotes {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => in_quotes = false,
+                        _ => {},
+                    }
+                    continue;
+                }
+                match c {
+                    '}' if in_comment => in_comment = false,
+                    _ if in_comment => {},
+                    '{' => in_comment = true,
+                    '"' => in_quotes = true,
+                    ';' => break,
+                    _ => {},
+                }
+            }
+            text.push_str(&line);
+        }
+        if text.trim().is_empty() { Ok(None) } else { Ok(Some(text)) }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_game_text() {
+            Ok(Some(text)) => Some(Game::parse(&text)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::chessboard::{ChessBoard, Colour, Move};
+use crate::epd::{self, Epd};
+
+// Tactics puzzles, each a position and the line that solves it, and the solver's rating, kept between sessions.
+// CSV rows are either `FEN,moves[,rating]`, the moves starting with the solver's, or the Lichess puzzle
+// database's `PuzzleId,FEN,Moves,Rating,...`, where the first move is the one the solver answers. Moves are in
+// SAN or long algebraic notation, separated by spaces. EPD records give the solution as the line of `pv`, or as
+// the best move of `bm`, with `id` naming the puzzle.
+
+pub const DEFAULT_RATING: i32 = 1500;
+// How far one puzzle moves the rating, the K factor of the Elo formula.
+const K_FACTOR: f64 = 32.0;
+const STATS_FILE: &str = ".chess-player-puzzles";
+
+#[derive(Debug)]
+pub enum PuzzleError {
+    Io(io::Error),
+    Invalid(usize, String),  // (line, reason)
+    Stats(String),  // A line of the stats file that can't be read
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PuzzleError::Io(e) => write!(f, "{}", e),
+            PuzzleError::Invalid(line, reason) => write!(f, "Invalid puzzle on line {}, {}", line, reason),
+            PuzzleError::Stats(line) => write!(f, "Invalid puzzle stats, {}", line),
+        }
+    }
+}
+
+impl From<io::Error> for PuzzleError {
+    fn from(e: io::Error) -> PuzzleError {
+        PuzzleError::Io(e)
+    }
+}
+
+#[derive(Clone)]
+pub struct Puzzle {
+    pub id: String,
+    pub board: ChessBoard,  // The solver to move, with the move leading up to it made when the file gives one
+    pub solution: Vec<Move>,  // The solver's moves and the replies between them
+    pub rating: Option<i32>,
+}
+
+impl Puzzle {
+    pub fn solver(&self) -> Colour {
+        self.board.side_to_move()
+    }
+}
+
+// Reads a file of puzzles, EPD when its name ends in .epd and CSV otherwise.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Puzzle>, PuzzleError> {
+    let text = fs::read_to_string(&path)?;
+    let is_epd = path.as_ref().extension().is_some_and(|extension| extension.eq_ignore_ascii_case("epd"));
+    if is_epd { parse_epd(&text) } else { parse_csv(&text) }
+}
+
+pub fn parse_csv(text: &str) -> Result<Vec<Puzzle>, PuzzleError> {
+    let mut puzzles = Vec::new();
+    for (number, line) in numbered_lines(text) {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let puzzle = if let Ok(board) = ChessBoard::from_fen(fields[0]) {
+            let moves = fields.get(1).copied().unwrap_or_default();
+            let solution = play_out(&board, moves).map_err(|reason| PuzzleError::Invalid(number, reason))?;
+            Puzzle { id: number.to_string(), board, solution, rating: parse_rating(fields.get(2)) }
+        } else if let Some(Ok(mut board)) = fields.get(1).map(|fen| ChessBoard::from_fen(fen)) {
+            let moves = fields.get(2).copied().unwrap_or_default();
+            let mut solution = play_out(&board, moves).map_err(|reason| PuzzleError::Invalid(number, reason))?;
+            if solution.is_empty() {
+                return Err(PuzzleError::Invalid(number, String::from("no moves")));
+            }
+            board.make_move(solution.remove(0));
+            Puzzle { id: fields[0].to_string(), board, solution, rating: parse_rating(fields.get(3)) }
+        } else if puzzles.is_empty() && number == first_line(text) {
+            // The column names.
+            continue;
+        } else {
+            return Err(PuzzleError::Invalid(number, String::from("no FEN in the first two fields")));
+        };
+        if puzzle.solution.is_empty() {
+            return Err(PuzzleError::Invalid(number, String::from("no solution")));
+        }
+        puzzles.push(puzzle);
+    }
+    Ok(puzzles)
+}
+
+pub fn parse_epd(text: &str) -> Result<Vec<Puzzle>, PuzzleError> {
+    let mut puzzles = Vec::new();
+    for (number, line) in numbered_lines(text) {
+        let invalid = |e: epd::EpdError| PuzzleError::Invalid(number, e.to_string());
+        let record = Epd::parse(line).map_err(invalid)?;
+        let solution = match record.line("pv").map_err(invalid)? {
+            Some(line) => line,
+            None => record.moves("bm").map_err(invalid)?.unwrap_or_default().into_iter().take(1).collect(),
+        };
+        if solution.is_empty() {
+            return Err(PuzzleError::Invalid(number, String::from("no pv or bm operation")));
+        }
+        let id = record.id().map_or_else(|| number.to_string(), String::from);
+        puzzles.push(Puzzle { id, board: record.board, solution, rating: None });
+    }
+    Ok(puzzles)
+}
+
+// Lines with something on them, numbered from 1, leaving out comments.
+fn numbered_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn first_line(text: &str) -> usize {
+    numbered_lines(text).next().map_or(0, |(number, _)| number)
+}
+
+fn parse_rating(field: Option<&&str>) -> Option<i32> {
+    field.and_then(|rating| rating.parse().ok())
+}
+
+// The moves of a solution, each played in turn from the position. Move numbers are skipped.
+fn play_out(board: &ChessBoard, moves: &str) -> Result<Vec<Move>, String> {
+    let mut board = board.clone();
+    let mut line = Vec::new();
+    for text in moves.split_whitespace().filter(|word| !word.ends_with('.')) {
+        let mv = epd::parse_move(&board, text).map_err(|e| e.to_string())?;
+        board.make_move(mv);
+        line.push(mv);
+    }
+    Ok(line)
+}
+
+// How the solver has done, over every session.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PuzzleStats {
+    pub rating: i32,
+    pub streak: u32,  // Puzzles solved in a row
+    pub best_streak: u32,
+    pub solved: u32,
+    pub failed: u32,
+}
+
+impl Default for PuzzleStats {
+    fn default() -> PuzzleStats {
+        PuzzleStats { rating: DEFAULT_RATING, streak: 0, best_streak: 0, solved: 0, failed: 0 }
+    }
+}
+
+impl PuzzleStats {
+    // The stats saved by an earlier session, or a fresh start when there are none. The file has a line for
+    // each number, like `rating 1500`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PuzzleStats, PuzzleError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(PuzzleStats::default()),
+            Err(e) => return Err(PuzzleError::Io(e)),
+        };
+        let mut stats = PuzzleStats::default();
+        for (_, line) in numbered_lines(&text) {
+            let mut words = line.split_whitespace();
+            let (name, value) = (words.next().unwrap_or_default(), words.next().unwrap_or_default());
+            let invalid = || PuzzleError::Stats(line.to_string());
+            match name {
+                "rating" => stats.rating = value.parse().map_err(|_| invalid())?,
+                "streak" => stats.streak = value.parse().map_err(|_| invalid())?,
+                "best_streak" => stats.best_streak = value.parse().map_err(|_| invalid())?,
+                "solved" => stats.solved = value.parse().map_err(|_| invalid())?,
+                "failed" => stats.failed = value.parse().map_err(|_| invalid())?,
+                _ => {},
+            }
+        }
+        Ok(stats)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, format!(
+            "rating {}\nstreak {}\nbest_streak {}\nsolved {}\nfailed {}\n",
+            self.rating, self.streak, self.best_streak, self.solved, self.failed,
+        ))
+    }
+
+    // Scores an attempt at a puzzle of the given rating, unrated ones counting as the default, and returns
+    // how much the solver's rating changed.
+    pub fn record(&mut self, puzzle_rating: Option<i32>, solved: bool) -> i32 {
+        let difference = (puzzle_rating.unwrap_or(DEFAULT_RATING) - self.rating) as f64;
+        let expected = 1.0 / (1.0 + 10f64.powf(difference / 400.0));
+        let score = if solved { 1.0 } else { 0.0 };
+        let change = (K_FACTOR * (score - expected)).round() as i32;
+        self.rating += change;
+        if solved {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.failed += 1;
+            self.streak = 0;
+        }
+        change
+    }
+}
+
+// Where the stats are kept unless told otherwise, in the home directory.
+pub fn default_stats_path() -> PathBuf {
+    env::var_os("HOME").map_or_else(PathBuf::new, PathBuf::from).join(STATS_FILE)
+}
+
+// What became of a move tried in a puzzle. A rating change comes with the first miss or with solving the
+// puzzle without one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Attempt {
+    Wrong(Option<i32>),
+    Correct(Move),  // The opponent's reply, to be played next
+    Solved(Option<i32>),
+}
+
+// Works through a set of puzzles, choosing the next one nearest the solver's rating among those not yet tried.
+pub struct Trainer {
+    puzzles: Vec<Puzzle>,
+    tried: Vec<bool>,
+    current: usize,
+    played: usize,  // Moves of the current solution made so far
+    scored: bool,  // The current puzzle has already changed the rating
+    pub stats: PuzzleStats,
+}
+
+impl Trainer {
+    // None when there are no puzzles to set.
+    pub fn new(puzzles: Vec<Puzzle>, stats: PuzzleStats) -> Option<Trainer> {
+        if puzzles.is_empty() {
+            return None;
+        }
+        let tried = vec![false; puzzles.len()];
+        let mut trainer = Trainer { puzzles, tried, current: 0, played: 0, scored: false, stats };
+        trainer.current = trainer.nearest();
+        Some(trainer)
+    }
+
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzles[self.current]
+    }
+
+    pub fn finished(&self) -> bool {
+        self.played >= self.puzzle().solution.len()
+    }
+
+    // Moves on to another puzzle, starting over once every one has been tried. Leaving one unsolved counts
+    // as failing it.
+    pub fn next_puzzle(&mut self) -> &Puzzle {
+        if !self.finished() {
+            self.score(false);
+        }
+        self.tried[self.current] = true;
+        if self.tried.iter().all(|&tried| tried) {
+            self.tried = vec![false; self.puzzles.len()];
+            self.tried[self.current] = true;
+        }
+        self.current = self.nearest();
+        self.played = 0;
+        self.scored = false;
+        self.puzzle()
+    }
+
+    // Checks the solver's move in `board`, the position the puzzle has reached. Any move that mates solves it.
+    pub fn attempt(&mut self, board: &ChessBoard, mv: Move) -> Attempt {
+        let expected = self.puzzle().solution.get(self.played).copied();
+        let mut after = board.clone();
+        after.make_move(mv);
+        if expected != Some(mv) && !after.is_checkmate() {
+            return Attempt::Wrong(self.score(false));
+        }
+        self.played += 1;
+        let length = self.puzzle().solution.len();
+        // A last reply, after the solver's final move, needn't be played.
+        if after.is_checkmate() || self.played + 1 >= length {
+            self.played = length;
+            return Attempt::Solved(self.score(true));
+        }
+        let reply = self.puzzle().solution[self.played];
+        self.played += 1;
+        Attempt::Correct(reply)
+    }
+
+    // The move the solution plays next, which counts as a miss.
+    pub fn hint(&mut self) -> Option<(Move, Option<i32>)> {
+        let mv = self.puzzle().solution.get(self.played).copied()?;
+        Some((mv, self.score(false)))
+    }
+
+    fn score(&mut self, solved: bool) -> Option<i32> {
+        if self.scored {
+            return None;
+        }
+        self.scored = true;
+        Some(self.stats.record(self.puzzle().rating, solved))
+    }
+
+    // The untried puzzle rated closest to the solver, the first in the file of those as close.
+    fn nearest(&self) -> usize {
+        let rating = self.stats.rating;
+        (0..self.puzzles.len())
+            .filter(|&index| !self.tried[index])
+            .min_by_key(|&index| (self.puzzles[index].rating.unwrap_or(DEFAULT_RATING) - rating).abs())
+            .unwrap_or(0)
+    }
+}
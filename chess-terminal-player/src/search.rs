@@ -0,0 +1,307 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::chessboard::{Actions, ChessBoard, Move, PieceKind};
+use crate::evaluation::{evaluate, piece_value};
+use crate::transposition::{Bound, Entry, TranspositionTable, DEFAULT_MEGABYTES};
+
+// Score for mating right now, mates further away score a point less for every ply.
+pub const MATE: i32 = 100_000;
+const INFINITY: i32 = 1_000_000;
+const MAX_DEPTH: u32 = 64;
+const MAX_PLY: usize = 128;
+
+// How long the engine may think. With neither limit set it searches until stopped.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+}
+
+// What a completed iteration of the search found.
+#[derive(Clone, Debug, Default)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub score: i32,  // Centipawns for the side to move
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub pv: Vec<Move>,  // Principal variation, best move first
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+
+    // Moves until mate, negative when the side to move is the one getting mated.
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score > MATE - MAX_PLY as i32 {
+            Some((MATE - self.score + 1) / 2)
+        } else if self.score < -MATE + MAX_PLY as i32 {
+            Some(-(MATE + self.score) / 2)
+        } else {
+            None
+        }
+    }
+}
+
+// Iterative deepening alpha-beta search with a quiescence search on captures at the leaves.
+pub struct Engine {
+    pub limits: Limits,
+    stop: Arc<AtomicBool>,
+    stopped: bool,
+    nodes: u64,
+    deadline: Option<Instant>,
+    killers: Vec<[Option<Move>; 2]>,  // Quiet moves that caused a cutoff, per ply
+    table: TranspositionTable,
+}
+
+impl Engine {
+    pub fn new(limits: Limits) -> Engine {
+        Engine {
+            limits,
+            stop: Arc::new(AtomicBool::new(false)),
+            stopped: false,
+            nodes: 0,
+            deadline: None,
+            killers: vec![[None, None]; MAX_PLY],
+            table: TranspositionTable::new(DEFAULT_MEGABYTES),
+        }
+    }
+
+    // Replaces the transposition table with an empty one of about the given size.
+    pub fn set_table_size(&mut self, megabytes: usize) {
+        self.table = TranspositionTable::new(megabytes);
+    }
+
+    // Forgets earlier searches, for a new game.
+    pub fn clear_table(&mut self) {
+        self.table.clear();
+    }
+
+    // Setting the flag from another thread makes the search return as soon as it notices.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    pub fn best_move(&mut self, board: &ChessBoard) -> Option<Move> {
+        self.search(board, |_| {}).best_move()
+    }
+
+    // Searches one ply deeper at a time until a limit is reached, calling `report` after every depth.
+    // Returns the deepest completed iteration.
+    pub fn search<F: FnMut(&SearchInfo)>(&mut self, board: &ChessBoard, mut report: F) -> SearchInfo {
+        let start = Instant::now();
+        self.deadline = self.limits.time.map(|time| start + time);
+        self.stopped = false;
+        self.nodes = 0;
+        self.killers = vec![[None, None]; MAX_PLY];
+        self.table.new_search();
+
+        let mut board = board.clone();
+        let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let mut best = SearchInfo::default();
+        for depth in 1..=max_depth {
+            let mut pv = Vec::new();
+            let score = self.negamax(&mut board, depth, 0, -INFINITY, INFINITY, &mut pv);
+            // An unfinished iteration can't be trusted, unless there is nothing better.
+            if self.stopped && !best.pv.is_empty() {
+                break;
+            }
+            best = SearchInfo { depth, score, nodes: self.nodes, elapsed: start.elapsed(), pv };
+            if self.stopped {
+                break;
+            }
+            report(&best);
+            if best.mate_in().is_some() {
+                break;
+            }
+            // The next iteration takes several times as long as this one, don't start what can't finish.
+            if let Some(time) = self.limits.time {
+                if start.elapsed() * 2 > time {
+                    break;
+                }
+            }
+        }
+        if best.pv.is_empty() {
+            best.pv = board.legal_moves().into_iter().take(1).collect();
+        }
+        best
+    }
+
+    fn check_time(&mut self) {
+        if self.nodes.is_multiple_of(1024) {
+            let out_of_time = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if out_of_time || self.stop.load(Ordering::Relaxed) {
+                self.stopped = true;
+            }
+        }
+    }
+
+    fn negamax(&mut self, board: &mut ChessBoard, depth: u32, ply: usize, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        self.nodes += 1;
+        self.check_time();
+        if self.stopped {
+            return 0;
+        }
+        if ply > 0 && (board.is_repetition() || board.is_fifty_move_rule() || board.is_insufficient_material()) {
+            return 0;
+        }
+
+        let mut moves = board.legal_moves();
+        let in_check = board.is_check();
+        if moves.is_empty() {
+            return if in_check { -(MATE - ply as i32) } else { 0 };
+        }
+        if ply >= MAX_PLY - 1 {
+            return evaluate(board);
+        }
+        // Look one ply further when in check, there are few replies and missing one is costly.
+        let depth = if in_check { depth + 1 } else { depth };
+        if depth == 0 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        // A search of this position at least as deep may already settle it. Never at the root, which has
+        // to come back with a move.
+        let entry = self.table.probe(board.hash());
+        if let Some(entry) = entry {
+            if ply > 0 && entry.depth >= depth {
+                let score = score_from_table(entry.score, ply);
+                let settled = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if settled {
+                    if let Some(mv) = entry.best {
+                        pv.push(mv);
+                    }
+                    return score.clamp(alpha, beta);
+                }
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best = None;
+        self.order_moves(board, &mut moves, ply, entry.and_then(|e| e.best));
+        for mv in moves {
+            board.make_move(mv);
+            let mut line = Vec::new();
+            let score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            board.unmake_move();
+            if self.stopped {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                best = Some(mv);
+                pv.clear();
+                pv.push(mv);
+                pv.extend(line);
+                if score >= beta {
+                    if mv.action == Actions::Moves && mv.promotion.is_none() {
+                        self.store_killer(mv, ply);
+                    }
+                    self.table.store(Entry::new(board.hash(), depth, score_to_table(beta, ply), Bound::Lower, best));
+                    return beta;
+                }
+            }
+        }
+        let bound = if alpha > original_alpha { Bound::Exact } else { Bound::Upper };
+        self.table.store(Entry::new(board.hash(), depth, score_to_table(alpha, ply), bound, best));
+        alpha
+    }
+
+    // Plays out captures until the position is quiet, so the evaluation isn't taken in the middle of an exchange.
+    fn quiescence(&mut self, board: &mut ChessBoard, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        self.check_time();
+        if self.stopped {
+            return 0;
+        }
+
+        let stand_pat = evaluate(board);
+        if stand_pat >= beta || ply >= MAX_PLY - 1 {
+            return beta;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut captures: Vec<Move> = board.legal_moves().into_iter()
+            .filter(|m| m.action == Actions::Takes || m.action == Actions::EnPassant || m.promotion.is_some())
+            .collect();
+        self.order_moves(board, &mut captures, ply, None);
+        for mv in captures {
+            board.make_move(mv);
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.unmake_move();
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+        alpha
+    }
+
+    // Best moves first so alpha-beta cuts off sooner: the best move found here by an earlier search, then
+    // captures of the most valuable piece by the least valuable attacker, promotions, killer moves and the rest.
+    fn order_moves(&self, board: &ChessBoard, moves: &mut [Move], ply: usize, hash_move: Option<Move>) {
+        let killers = self.killers[ply];
+        moves.sort_by_cached_key(|mv| {
+            let attacker = board.piece_at(mv.from).map_or(0, |p| piece_value(p.kind));
+            let score = if Some(*mv) == hash_move {
+                100_000
+            } else if mv.action == Actions::Takes {
+                let victim = board.piece_at(mv.to).map_or(0, |p| piece_value(p.kind));
+                10_000 + 10 * victim - attacker
+            } else if mv.action == Actions::EnPassant {
+                10_000 + 10 * piece_value(PieceKind::Pawn) - attacker
+            } else if let Some(kind) = mv.promotion {
+                9_000 + piece_value(kind)
+            } else if killers.contains(&Some(*mv)) {
+                8_000
+            } else {
+                0
+            };
+            -score
+        });
+    }
+
+    fn store_killer(&mut self, mv: Move, ply: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+    }
+}
+
+// Mate scores count plies from the root, but a table entry may be reached at any ply. They are stored counting
+// from the position itself and converted back when read.
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score > MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score < -MATE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score > MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score < -MATE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
@@ -0,0 +1,939 @@
+use std::fs;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use termion::cursor;
+use termion::event::{Event, Key, MouseButton, MouseEvent};
+use termion::input::{MouseTerminal, TermRead};
+use termion::raw::IntoRawMode;
+use termion::terminal_size;
+
+use crate::book::Book;
+use crate::chessboard::{
+    square_name,
+    ChessBoard,
+    Colour,
+    DrawOptions,
+    GameResult,
+    Move,
+    PieceKind,
+    BOARD_HEIGHT,
+};
+use crate::clock::{Clock, TimeControl};
+use crate::network::{self, Message, Peer, PeerEvent, PROTOCOL_VERSION};
+use crate::evaluation;
+use crate::pgn::Game;
+use crate::puzzle::{Attempt, Trainer};
+use crate::search::{Engine, Limits, SearchInfo};
+use crate::tablebase::{Tablebase, Wdl};
+use crate::theme::{self, THEME_NAMES};
+use crate::uci::{UciClient, UciError};
+
+const HELP: &str = "Type a move like e4 or Nf3, or pick squares with the arrow keys and space or the mouse. \
+                    Commands: undo, redo, hint, analyze, flip, resign, draw, save FILE, engine white|black|off, \
+                    theme NAME, ascii on|off, coordinates on|off, quit";
+const PUZZLE_HELP: &str = "Find the best move, typed or picked with the arrow keys and space or the mouse. \
+                           Commands: next, hint, flip, theme NAME, ascii on|off, coordinates on|off, quit";
+// How hard the engine looks for a hint when the book has nothing.
+const HINT_LIMITS: Limits = Limits { depth: Some(6), time: Some(Duration::from_secs(2)) };
+// How deep the principal variations of an analysis go.
+const ANALYSIS_LIMITS: Limits = Limits { depth: Some(6), time: Some(Duration::from_secs(5)) };
+
+// What plays the computer's side.
+enum Opponent {
+    Builtin(Engine),
+    External(UciClient),
+}
+
+// The other player in a game over the network.
+struct Remote {
+    peer: Peer,
+    hosting: bool,  // The host's game counts when the two disagree
+    colour: Option<Colour>,  // The other player's side, unknown to the joiner until the host says hello
+    draw_offer: Option<Colour>,  // Side whose offer of a draw is open, until the next move
+}
+
+// Everything the game waits on, read from one channel.
+enum Input {
+    Terminal(io::Result<Event>),
+    Network(PeerEvent),
+}
+
+// Whether to keep reading input after a line has been handled.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+// An interactive game at the terminal, moves typed at a prompt below the board.
+pub struct Session {
+    board: ChessBoard,
+    options: DrawOptions,
+    message: String,
+    analysis: Vec<String>,  // Shown beside the board until the next command
+    ended: Option<GameResult>,  // Resignation or flag fall, the endings the board can't see
+    opponent: Opponent,
+    limits: Limits,
+    engine_side: Option<Colour>,
+    clock: Option<Clock>,
+    book: Option<Book>,
+    tablebase: Option<Tablebase>,
+    remote: Option<Remote>,
+    trainer: Option<Trainer>,
+    puzzle_stats: PathBuf,  // Where the trainer's rating and streak are saved
+    inbox: Receiver<Input>,
+    outbox: Sender<Input>,  // Handed to the threads feeding `inbox`
+}
+
+impl Session {
+    pub fn new(board: ChessBoard) -> Session {
+        let (outbox, inbox) = mpsc::channel();
+        Session {
+            board,
+            options: DrawOptions::default(),
+            message: String::from(HELP),
+            analysis: Vec::new(),
+            ended: None,
+            opponent: Opponent::Builtin(Engine::new(Limits::default())),
+            limits: Limits { depth: Some(4), time: None },
+            engine_side: None,
+            clock: None,
+            book: None,
+            tablebase: None,
+            remote: None,
+            trainer: None,
+            puzzle_stats: PathBuf::new(),
+            inbox,
+            outbox,
+        }
+    }
+
+    // Hands one side to the computer, which then replies to every move of the other side.
+    pub fn set_engine(&mut self, side: Option<Colour>, limits: Limits) {
+        self.engine_side = side;
+        self.limits = limits;
+    }
+
+    // Plays the computer's side with an external UCI engine instead of the built-in one, starting it on a new game.
+    pub fn set_external_engine(&mut self, mut client: UciClient) -> Result<(), UciError> {
+        client.new_game()?;
+        self.opponent = Opponent::External(client);
+        Ok(())
+    }
+
+    // Opening book the engine plays from while it has moves for the position, and hints come from.
+    pub fn set_book(&mut self, book: Book) {
+        self.book = Some(book);
+    }
+
+    // Sets puzzles to solve in place of a game, saving the solver's stats to `stats_path` after each one.
+    pub fn set_puzzles(&mut self, trainer: Trainer, stats_path: PathBuf) {
+        self.trainer = Some(trainer);
+        self.puzzle_stats = stats_path;
+        self.engine_side = None;
+        self.show_puzzle();
+    }
+
+    // Endgame tables the engine plays perfectly from once few enough pieces are left, and analysis reports.
+    pub fn set_tablebase(&mut self, tablebase: Tablebase) {
+        self.tablebase = Some(tablebase);
+    }
+
+    // Waits on `addr` for another player to join, who plays the other side to `colour`.
+    pub fn host(&mut self, addr: &str, colour: Colour) -> io::Result<SocketAddr> {
+        let outbox = self.outbox.clone();
+        let peer = Peer::host(addr, move |event| { let _ = outbox.send(Input::Network(event)); })?;
+        let local = peer.local_addr();
+        self.remote = Some(Remote { peer, hosting: true, colour: Some(colour.opponent()), draw_offer: None });
+        self.options.flipped = colour == Colour::Black;
+        self.message = format!("Waiting for the other player on port {}", local.port());
+        Ok(local)
+    }
+
+    // Joins a game hosted at `addr`, the host says which side to play.
+    pub fn join(&mut self, addr: &str) -> io::Result<()> {
+        let outbox = self.outbox.clone();
+        let peer = Peer::join(addr, move |event| { let _ = outbox.send(Input::Network(event)); })?;
+        self.remote = Some(Remote { peer, hosting: false, colour: None, draw_offer: None });
+        Ok(())
+    }
+
+    // Plays the game on a chess clock, which starts at once for the side to move.
+    pub fn set_clock(&mut self, control: TimeControl) {
+        self.clock = Some(Clock::new(control));
+        self.sync_clock();
+    }
+
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
+    // How the board is drawn: orientation, theme, ASCII pieces and coordinates.
+    pub fn draw_options(&mut self) -> &mut DrawOptions {
+        &mut self.options
+    }
+
+    pub fn board(&self) -> &ChessBoard {
+        &self.board
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        self.ended.or_else(|| self.board.result())
+    }
+
+    // Reads keys and mouse clicks in raw mode until the player quits, redrawing after every event, and
+    // whenever the time on a running clock changes.
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut stdout = MouseTerminal::from(io::stdout().into_raw_mode()?);
+        let mut input = String::new();
+
+        // Input is read on its own thread, so waiting for it can time out for the clock, and messages from
+        // the network come in between keys. The thread is left blocked on stdin when the game ends.
+        let sender = self.outbox.clone();
+        thread::spawn(move || {
+            for event in io::stdin().events() {
+                if sender.send(Input::Terminal(event)).is_err() { break; }
+            }
+        });
+
+        self.sync_clock();
+        self.render(&mut stdout, &input)?;
+        self.engine_turn(&mut stdout, &input)?;
+        loop {
+            let wait = self.clock.as_ref().and_then(|clock| clock.next_change(Instant::now()));
+            let received = match wait {
+                Some(wait) => match self.inbox.recv_timeout(wait) {
+                    Ok(received) => received,
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.check_flag();
+                        self.render(&mut stdout, &input)?;
+                        continue;
+                    },
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match self.inbox.recv() {
+                    Ok(received) => received,
+                    Err(_) => break,
+                },
+            };
+            let event = match received {
+                Input::Terminal(event) => event?,
+                Input::Network(event) => {
+                    self.receive(event);
+                    self.render(&mut stdout, &input)?;
+                    continue;
+                },
+            };
+            match event {
+                // Enter on an empty prompt picks the square under the cursor, like space does.
+                Event::Key(Key::Char('\n')) if input.trim().is_empty() => self.select_cursor(),
+                Event::Key(Key::Char('\n')) => {
+                    let line = std::mem::take(&mut input);
+                    if self.handle(&line) == Flow::Quit { break; }
+                },
+                // Space picks a square too, unless a command with spaces in it is being typed.
+                Event::Key(Key::Char(' ')) if input.is_empty() => self.select_cursor(),
+                Event::Key(Key::Char(c)) => input.push(c),
+                Event::Key(Key::Backspace) => { input.pop(); },
+                Event::Key(Key::Esc) => {
+                    input.clear();
+                    self.clear_selection();
+                },
+                Event::Key(Key::Up) => self.move_cursor(1, 0),
+                Event::Key(Key::Down) => self.move_cursor(-1, 0),
+                Event::Key(Key::Left) => self.move_cursor(0, -1),
+                Event::Key(Key::Right) => self.move_cursor(0, 1),
+                Event::Key(Key::Ctrl('c')) | Event::Key(Key::Ctrl('d')) => break,
+                Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) => {
+                    if let Some(square) = ChessBoard::square_at(x, y, &self.options) {
+                        self.options.cursor = Some(square);
+                        self.select(square);
+                    }
+                },
+                _ => {},
+            }
+            self.render(&mut stdout, &input)?;
+            self.engine_turn(&mut stdout, &input)?;
+        }
+        write!(stdout, "{}\r\n", cursor::Goto(1, BOARD_HEIGHT + 4))?;
+        stdout.flush()
+    }
+
+    // Acts on a line typed at the prompt, either a command or a move in Standard Algebraic Notation.
+    pub fn handle(&mut self, line: &str) -> Flow {
+        self.message.clear();
+        self.analysis.clear();
+        self.check_flag();
+        let networked = self.remote.is_some();
+        let puzzles = self.trainer.is_some();
+        match line.trim() {
+            "" => {},
+            "undo" | "redo" | "resign" | "draw" | "analyze" | "analyse" | "engine off" | "engine white" | "engine black"
+                if puzzles => {
+                self.message = String::from("Not allowed while solving puzzles");
+            },
+            "undo" | "redo" | "engine off" | "engine white" | "engine black" if networked => {
+                self.message = String::from("Not allowed in a game over the network");
+            },
+            "draw" => self.offer_draw(),
+            "quit" | "exit" => return Flow::Quit,
+            "help" if puzzles => self.message = String::from(PUZZLE_HELP),
+            "help" => self.message = String::from(HELP),
+            "next" if puzzles => self.next_puzzle(),
+            "hint" if puzzles => self.puzzle_hint(),
+            "flip" => self.options.flipped = !self.options.flipped,
+            "ascii on" => self.options.ascii = true,
+            "ascii off" => self.options.ascii = false,
+            "coordinates on" => self.options.hide_coordinates = false,
+            "coordinates off" => self.options.hide_coordinates = true,
+            "undo" => self.undo(),
+            "hint" => self.hint(),
+            "analyze" | "analyse" => self.analyze(),
+            "redo" => self.redo(),
+            "engine off" => self.engine_side = None,
+            "engine white" => self.engine_side = Some(Colour::White),
+            "engine black" => self.engine_side = Some(Colour::Black),
+            "resign" => {
+                if self.result().is_some() {
+                    self.message = String::from("The game is already over");
+                } else if let Some(remote) = &self.remote {
+                    // Only ever the player at this end, whoever is to move, and only once the other end knows.
+                    if let Some(colour) = remote.colour {
+                        match remote.peer.send(&Message::Resign) {
+                            Ok(()) => self.ended = Some(GameResult::Resignation(colour)),
+                            Err(e) => self.message = format!("Can't resign, {}", e),
+                        }
+                    }
+                } else {
+                    self.ended = Some(GameResult::Resignation(self.board.side_to_move().opponent()));
+                    self.sync_clock();
+                }
+            },
+            command if command.starts_with("save ") => self.save(command["save ".len()..].trim()),
+            command if command.starts_with("theme ") => {
+                let name = command["theme ".len()..].trim();
+                match theme::for_terminal(name) {
+                    Some(theme) => self.options.theme = theme,
+                    None => self.message = format!("Unknown theme, {}, try {}", name, THEME_NAMES.join(", ")),
+                }
+            },
+            movetext => {
+                if self.result().is_some() && !puzzles {
+                    self.message = String::from("The game is over, undo or quit");
+                    return Flow::Continue;
+                }
+                match self.board.parse_san(movetext) {
+                    Ok(mv) => self.play_own(mv),
+                    Err(e) => self.message = e.to_string(),
+                }
+            },
+        }
+        Flow::Continue
+    }
+
+    // Plays a move made at this end, sent on to the other player in a game over the network.
+    fn play_own(&mut self, mv: Move) {
+        if self.trainer.is_some() {
+            return self.play_puzzle(mv);
+        }
+        let remote = match &self.remote {
+            Some(remote) => remote,
+            None => return self.play(mv),
+        };
+        let side = self.board.side_to_move();
+        if !remote.peer.is_connected() || remote.colour.is_none() {
+            self.message = String::from("Waiting for the other player to connect");
+        } else if remote.colour == Some(side) {
+            self.message = format!("Waiting for {:?} to move", side);
+        } else {
+            let san = self.board.san(&mv);
+            match remote.peer.send(&Message::Move(san)) {
+                Ok(()) => self.play(mv),
+                Err(e) => self.message = format!("Can't send the move, {}", e),
+            }
+        }
+    }
+
+    fn play(&mut self, mv: Move) {
+        self.board.make_move(mv);
+        if let Some(remote) = &mut self.remote {
+            remote.draw_offer = None;
+        }
+        if let Some(clock) = &mut self.clock {
+            clock.press(Instant::now());
+        }
+        self.sync_clock();
+        self.clear_selection();
+    }
+
+    // Ends the game if the side to move has run out of time. It loses, unless the other side has nothing
+    // left to mate with.
+    fn check_flag(&mut self) {
+        let clock = match &mut self.clock {
+            Some(clock) if self.ended.is_none() && self.board.result().is_none() => clock,
+            _ => return,
+        };
+        clock.tick(Instant::now());
+        if let Some(flagged) = clock.flagged() {
+            let winner = flagged.opponent();
+            self.ended = Some(if self.board.can_checkmate(winner) {
+                GameResult::Timeout(winner)
+            } else {
+                GameResult::TimeoutVsInsufficientMaterial
+            });
+        }
+    }
+
+    // Runs the side to move's clock while the game is on and stops it when the game is over.
+    fn sync_clock(&mut self) {
+        let over = self.result().is_some();
+        let side = self.board.side_to_move();
+        if let Some(clock) = &mut self.clock {
+            if over { clock.stop(Instant::now()) } else { clock.start(side, Instant::now()) }
+        }
+    }
+
+    // Writes the game so far as PGN, with the time left after each move on a clocked game.
+    fn save(&mut self, path: &str) {
+        let mut game = Game::from_board(&self.board);
+        if let Some(result) = self.result() {
+            game.result = String::from(result.pgn_token());
+            game.set_tag("Result", result.pgn_token());
+        }
+        if let Some(clock) = &self.clock {
+            game.set_clock(clock);
+        }
+        self.message = match fs::write(path, game.to_string()) {
+            Ok(()) => format!("Saved to {}", path),
+            Err(e) => format!("Can't save {}, {}", path, e),
+        };
+    }
+
+    fn engine_to_move(&self) -> bool {
+        self.engine_side == Some(self.board.side_to_move()) && self.result().is_none()
+    }
+
+    // Lets the engine reply when it is its move, showing that it is thinking while the search runs.
+    fn engine_turn<W: Write>(&mut self, out: &mut W, input: &str) -> io::Result<()> {
+        if !self.engine_to_move() {
+            return Ok(());
+        }
+        self.message = String::from("Thinking...");
+        self.render(out, input)?;
+        // On the clock, the engine thinks no longer than its share of the time left.
+        let mut limits = self.limits;
+        if let Some(clock) = &self.clock {
+            let share = clock.think_time(self.board.side_to_move(), Instant::now());
+            limits.time = Some(limits.time.map_or(share, |time| time.min(share)));
+        }
+        let book_move = self.book.as_ref().and_then(|book| book.pick(&self.board));
+        let table_move = match book_move {
+            Some(_) => None,
+            None => self.tablebase.as_ref().and_then(|tablebase| tablebase.best_move(&self.board)),
+        };
+        let known_move = book_move.or(table_move);
+        let reply = match &mut self.opponent {
+            _ if known_move.is_some() => Ok(known_move),
+            Opponent::Builtin(engine) => {
+                engine.limits = limits;
+                Ok(engine.best_move(&self.board))
+            },
+            Opponent::External(client) => client.best_move(&self.board, limits).map(Some),
+        };
+        self.check_flag();
+        self.message = match reply {
+            _ if self.result().is_some() => String::new(),
+            Ok(Some(mv)) => {
+                let san = self.board.san(&mv);
+                self.play(mv);
+                let source = match (book_move, table_move) {
+                    (Some(_), _) => " from the book",
+                    (_, Some(_)) => " from the tablebase",
+                    _ => "",
+                };
+                format!("Engine played {}{}", san, source)
+            },
+            Ok(None) => String::new(),
+            // Asking again would likely fail the same way, hand the side back to the player.
+            Err(e) => {
+                self.engine_side = None;
+                format!("{}, engine turned off", e)
+            },
+        };
+        self.render(out, input)
+    }
+
+    // Suggests a move for the side to move: the book's moves with how often it plays them, or else what the
+    // engine would play after a short think.
+    fn hint(&mut self) {
+        if self.result().is_some() {
+            self.message = String::from("The game is over");
+            return;
+        }
+        let moves = self.book.as_ref().map(|book| book.moves(&self.board)).unwrap_or_default();
+        if !moves.is_empty() {
+            let total: u32 = moves.iter().map(|m| m.weight as u32).sum();
+            let list: Vec<String> = moves.iter()
+                .map(|m| format!("{} {}%", self.board.san(&m.mv), (m.weight as u32 * 100 + total / 2) / total))
+                .collect();
+            self.message = format!("Book moves: {}", list.join(", "));
+            return;
+        }
+        if let Some(mv) = self.tablebase.as_ref().and_then(|tablebase| tablebase.best_move(&self.board)) {
+            self.message = format!("Hint: {} from the tablebase", self.board.san(&mv));
+            return;
+        }
+        let suggestion = match &mut self.opponent {
+            Opponent::Builtin(engine) => {
+                engine.limits = HINT_LIMITS;
+                Ok(engine.best_move(&self.board))
+            },
+            Opponent::External(client) => client.best_move(&self.board, HINT_LIMITS).map(Some),
+        };
+        self.message = match suggestion {
+            Ok(Some(mv)) => format!("Hint: {}", self.board.san(&mv)),
+            Ok(None) => String::from("No moves to suggest"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    // Explains the position: the static evaluation term by term, then what the built-in engine sees at each
+    // depth, all from white's side.
+    fn analyze(&mut self) {
+        let terms = evaluation::breakdown(&self.board);
+        self.analysis = vec![
+            String::from("Evaluation for White"),
+            format!("  Material        {}", pawns(terms.material)),
+            format!("  Placement       {}", pawns(terms.placement)),
+            format!("  Mobility        {}", pawns(terms.mobility)),
+            format!("  King safety     {}", pawns(terms.king_safety)),
+            format!("  Pawn structure  {}", pawns(terms.pawn_structure)),
+            format!("  Total           {}", pawns(terms.total())),
+        ];
+        if self.board.result().is_some() {
+            self.message = String::from("No moves to look at, the game is over");
+            return;
+        }
+        if let Some(wdl) = self.tablebase.as_ref().and_then(|tablebase| tablebase.probe_wdl(&self.board)) {
+            let dtz = self.tablebase.as_ref().and_then(|tablebase| tablebase.probe_dtz(&self.board));
+            self.analysis.push(String::new());
+            self.analysis.extend(endgame(self.board.side_to_move(), wdl, dtz));
+        }
+        self.analysis.push(String::new());
+        let board = &self.board;
+        let mut lines = Vec::new();
+        let mut report = |info: &SearchInfo| lines.push(variation(board, info));
+        // An external engine's thinking can't be split into terms, so the analysis is always the built-in engine's.
+        match &mut self.opponent {
+            Opponent::Builtin(engine) => {
+                engine.limits = ANALYSIS_LIMITS;
+                engine.search(board, &mut report);
+            },
+            Opponent::External(_) => { Engine::new(ANALYSIS_LIMITS).search(board, &mut report); },
+        }
+        self.analysis.extend(lines);
+    }
+
+    // Sets up the trainer's current puzzle, the solver's side at the bottom of the board.
+    fn show_puzzle(&mut self) {
+        let puzzle = match &self.trainer {
+            Some(trainer) => trainer.puzzle(),
+            None => return,
+        };
+        let rating = puzzle.rating.map_or_else(|| String::from("unrated"), |rating| format!("rated {}", rating));
+        self.message = format!("Puzzle {}, {}: {:?} to play", puzzle.id, rating, puzzle.solver());
+        self.options.flipped = puzzle.solver() == Colour::Black;
+        self.board = puzzle.board.clone();
+        self.clear_selection();
+    }
+
+    // Moves on, a puzzle left unsolved costing rating as if it had been failed.
+    fn next_puzzle(&mut self) {
+        let trainer = match &mut self.trainer {
+            Some(trainer) => trainer,
+            None => return,
+        };
+        let (rating, failed) = (trainer.stats.rating, trainer.stats.failed);
+        trainer.next_puzzle();
+        let skipped = (trainer.stats.failed > failed).then(|| trainer.stats.rating - rating);
+        let rating = trainer.stats.rating;
+        self.show_puzzle();
+        if skipped.is_some() {
+            self.message = format!("Skipped{}. {}", rating_change(skipped, rating), self.message);
+            self.save_puzzle_stats();
+        }
+    }
+
+    // Checks the solver's move against the solution, playing the reply to it when it is right.
+    fn play_puzzle(&mut self, mv: Move) {
+        let trainer = match &mut self.trainer {
+            Some(trainer) => trainer,
+            None => return,
+        };
+        if trainer.finished() {
+            self.message = String::from("Solved, type next for another puzzle");
+            return;
+        }
+        let san = self.board.san(&mv);
+        let attempt = trainer.attempt(&self.board, mv);
+        let rating = trainer.stats.rating;
+        self.message = match attempt {
+            Attempt::Wrong(change) => {
+                self.clear_selection();
+                format!("{} isn't it, try again or type hint{}", san, rating_change(change, rating))
+            },
+            Attempt::Correct(reply) => {
+                self.play(mv);
+                let reply_san = self.board.san(&reply);
+                self.play(reply);
+                format!("{} is right, {} was the reply. Keep going", san, reply_san)
+            },
+            Attempt::Solved(change) => {
+                self.play(mv);
+                format!("{} solves it{}. Type next for another puzzle", san, rating_change(change, rating))
+            },
+        };
+        if let Attempt::Wrong(Some(_)) | Attempt::Solved(Some(_)) = attempt {
+            self.save_puzzle_stats();
+        }
+    }
+
+    // Shows the next move of the solution, which counts as missing the puzzle.
+    fn puzzle_hint(&mut self) {
+        let trainer = match &mut self.trainer {
+            Some(trainer) => trainer,
+            None => return,
+        };
+        let (mv, change) = match trainer.hint() {
+            Some(hint) => hint,
+            None => {
+                self.message = String::from("Solved, type next for another puzzle");
+                return;
+            },
+        };
+        self.message = format!("The move is {}{}", self.board.san(&mv), rating_change(change, trainer.stats.rating));
+        if change.is_some() {
+            self.save_puzzle_stats();
+        }
+    }
+
+    fn save_puzzle_stats(&mut self) {
+        if let Some(trainer) = &self.trainer {
+            if let Err(e) = trainer.stats.save(&self.puzzle_stats) {
+                self.message = format!("Can't save the puzzle rating to {}: {}", self.puzzle_stats.display(), e);
+            }
+        }
+    }
+
+    // Offers the other player a draw, or takes up the offer they made.
+    fn offer_draw(&mut self) {
+        let remote = match &mut self.remote {
+            Some(remote) => remote,
+            None => {
+                self.message = String::from("Draws are offered in games over the network");
+                return;
+            },
+        };
+        let (theirs, ours) = match remote.colour {
+            Some(colour) => (colour, colour.opponent()),
+            None => return,
+        };
+        if self.ended.is_some() || self.board.result().is_some() {
+            self.message = String::from("The game is already over");
+        } else if let Err(e) = remote.peer.send(&Message::OfferDraw) {
+            self.message = format!("Can't offer a draw, {}", e);
+        } else if remote.draw_offer == Some(theirs) {
+            self.ended = Some(GameResult::Agreement);
+        } else {
+            remote.draw_offer = Some(ours);
+            self.message = String::from("Draw offered");
+        }
+    }
+
+    // Acts on what the other player sent. Anything that doesn't fit the game here means the two ends have got
+    // out of step, which the host's game settles.
+    pub fn receive(&mut self, event: PeerEvent) {
+        let remote = match &mut self.remote {
+            Some(remote) => remote,
+            None => return,
+        };
+        let over = self.ended.is_some() || self.board.result().is_some();
+        let mut out_of_step = false;
+        match event {
+            PeerEvent::Connected => {
+                let colour = if remote.hosting { remote.colour } else { None };
+                let _ = remote.peer.send(&Message::Hello { version: PROTOCOL_VERSION, colour });
+                if remote.hosting {
+                    let _ = remote.peer.send(&Message::sync(&self.board, self.ended));
+                }
+                self.message = String::from("Connected");
+            },
+            PeerEvent::Disconnected => {
+                remote.draw_offer = None;
+                self.message = String::from(if remote.hosting {
+                    "The other player has gone, waiting for them to come back"
+                } else {
+                    "Connection lost, trying to connect again"
+                });
+            },
+            PeerEvent::Garbled(e) => self.message = e.to_string(),
+            PeerEvent::Received(Message::Hello { version, colour }) => {
+                if version != PROTOCOL_VERSION {
+                    self.message = format!("The other side speaks version {} of the protocol, not {}", version,
+                                           PROTOCOL_VERSION);
+                }
+                if let (false, Some(ours)) = (remote.hosting, colour) {
+                    remote.colour = Some(ours.opponent());
+                    self.options.flipped = ours == Colour::Black;
+                    self.message = format!("Connected, playing {:?}", ours);
+                }
+            },
+            PeerEvent::Received(Message::Move(san)) => {
+                match self.board.parse_san(&san) {
+                    Ok(mv) if !over && remote.colour == Some(self.board.side_to_move()) => {
+                        let san = self.board.san(&mv);
+                        self.play(mv);
+                        self.message = format!("{} played", san);
+                    },
+                    _ => out_of_step = true,
+                }
+            },
+            PeerEvent::Received(Message::OfferDraw) if !over => {
+                let ours = remote.colour.map(|colour| colour.opponent());
+                if ours.is_some() && remote.draw_offer == ours {
+                    self.ended = Some(GameResult::Agreement);
+                } else {
+                    remote.draw_offer = remote.colour;
+                    self.message = String::from("The other player offers a draw, type draw to agree");
+                }
+            },
+            PeerEvent::Received(Message::OfferDraw) => {},
+            PeerEvent::Received(Message::Resign) => {
+                if let Some(colour) = remote.colour {
+                    self.ended = Some(GameResult::Resignation(colour.opponent()));
+                }
+            },
+            PeerEvent::Received(Message::SyncRequest) => out_of_step = true,
+            PeerEvent::Received(Message::Sync { .. }) if remote.hosting => out_of_step = true,
+            PeerEvent::Received(Message::Sync { fen, moves, ended }) => match network::replay(&fen, &moves) {
+                Ok(board) => {
+                    // The host's word on resignations and draws stands, flag falls are seen on each side's clock.
+                    let flag = self.ended.filter(|result| {
+                        matches!(result, GameResult::Timeout(_) | GameResult::TimeoutVsInsufficientMaterial)
+                    });
+                    let ended = ended.or(flag);
+                    if board.to_fen() != self.board.to_fen() || board.history().len() != self.board.history().len()
+                        || ended != self.ended {
+                        self.message = String::from("Brought back in step with the host");
+                    }
+                    self.board = board;
+                    self.ended = ended;
+                    self.clear_selection();
+                },
+                Err(e) => self.message = format!("Can't follow the host's game, {}", e),
+            },
+        }
+        if out_of_step {
+            if let Some(remote) = &self.remote {
+                let answer = if remote.hosting { Message::sync(&self.board, self.ended) } else { Message::SyncRequest };
+                let _ = remote.peer.send(&answer);
+            }
+        }
+    }
+
+    // Moves the cursor a square up, down, left or right as seen on screen.
+    fn move_cursor(&mut self, up: i32, right: i32) {
+        let (up, right) = if self.options.flipped { (-up, -right) } else { (up, right) };
+        let (row, col) = self.options.cursor.unwrap_or((0, 4));
+        let row = (row as i32 + up).clamp(0, 7) as usize;
+        let col = (col as i32 + right).clamp(0, 7) as usize;
+        self.options.cursor = Some((row, col));
+    }
+
+    fn select_cursor(&mut self) {
+        if let Some(square) = self.options.cursor {
+            self.select(square);
+        }
+    }
+
+    // Picking a piece of the side to move selects it, picking one of its destinations then plays the move.
+    // Pawns reaching the last rank become queens, type the move to promote to anything else.
+    fn select(&mut self, square: (usize, usize)) {
+        self.message.clear();
+        if self.result().is_some() && self.trainer.is_none() {
+            self.message = String::from("The game is over, undo or quit");
+            return;
+        }
+        if let Some(from) = self.options.selected {
+            if square == from {
+                self.clear_selection();
+                return;
+            }
+            let chosen = self.board.legal_moves().into_iter()
+                .find(|m| m.from == from && m.to == square && (m.promotion.is_none() || m.promotion == Some(PieceKind::Queen)));
+            if let Some(mv) = chosen {
+                self.play_own(mv);
+                return;
+            }
+        }
+        match self.board.piece_at(square) {
+            Some(piece) if piece.colour == self.board.side_to_move() => {
+                let destinations: Vec<(usize, usize)> = self.board.legal_moves().into_iter()
+                    .filter(|m| m.from == square)
+                    .map(|m| m.to)
+                    .collect();
+                if destinations.is_empty() {
+                    self.message = format!("The piece on {} can't move", square_name(square));
+                }
+                self.options.selected = Some(square);
+                self.options.destinations = destinations;
+            },
+            _ => {
+                if self.options.selected.is_some() {
+                    self.message = format!("Can't move there, {}", square_name(square));
+                }
+                self.clear_selection();
+            },
+        }
+    }
+
+    fn clear_selection(&mut self) {
+        self.options.selected = None;
+        self.options.destinations.clear();
+    }
+
+    // Takes back a resignation, or else the last move, along with the time it took on the clock. Against the
+    // engine its reply is taken back too, so it's the player's move again. A flag fall is taken back with the
+    // move before it, the flagged side gets back the time it had then.
+    fn undo(&mut self) {
+        self.clear_selection();
+        if let Some(GameResult::Resignation(_)) = self.ended.take() {
+            self.sync_clock();
+            return;
+        }
+        if self.undo_move().is_none() {
+            self.message = String::from("Nothing to undo");
+        } else if self.engine_to_move() {
+            self.undo_move();
+        }
+        self.sync_clock();
+    }
+
+    fn redo(&mut self) {
+        self.clear_selection();
+        if self.ended.is_some() || self.redo_move().is_none() {
+            self.message = String::from("Nothing to redo");
+        } else if self.engine_to_move() {
+            self.redo_move();
+        }
+        self.sync_clock();
+    }
+
+    fn undo_move(&mut self) -> Option<Move> {
+        let mv = self.board.undo()?;
+        if let Some(clock) = &mut self.clock {
+            clock.undo(Instant::now());
+        }
+        Some(mv)
+    }
+
+    fn redo_move(&mut self) -> Option<Move> {
+        let mv = self.board.redo()?;
+        if let Some(clock) = &mut self.clock {
+            clock.redo(Instant::now());
+        }
+        Some(mv)
+    }
+
+    fn render<W: Write>(&self, out: &mut W, input: &str) -> io::Result<()> {
+        let mut options = self.options.clone();
+        options.panel = self.analysis.clone();
+        if let (Some(trainer), true) = (&self.trainer, options.panel.is_empty()) {
+            let stats = &trainer.stats;
+            options.panel = vec![
+                format!("Puzzle {}", trainer.puzzle().id),
+                String::new(),
+                format!("Rating       {}", stats.rating),
+                format!("Streak       {}", stats.streak),
+                format!("Best streak  {}", stats.best_streak),
+                format!("Solved       {} of {}", stats.solved, stats.solved + stats.failed),
+            ];
+        }
+        options.width = terminal_size().ok().map(|(width, _)| width);
+        if let Some(clock) = &self.clock {
+            let now = Instant::now();
+            options.clocks = Some([clock.remaining(Colour::White, now), clock.remaining(Colour::Black, now)]);
+        }
+        self.board.draw(&options);
+        let mut status = match self.result() {
+            Some(result) => result.to_string(),
+            None if self.board.is_check() => format!("{:?} to move, check", self.board.side_to_move()),
+            None => format!("{:?} to move", self.board.side_to_move()),
+        };
+        if let (Some(remote), None) = (&self.remote, self.result()) {
+            if let Some(colour) = remote.colour {
+                status.push_str(&format!(", you play {:?}", colour.opponent()));
+            }
+            if !remote.peer.is_connected() {
+                status.push_str(", not connected");
+            }
+        }
+        write!(out, "{}{}", cursor::Goto(1, BOARD_HEIGHT + 2), status)?;
+        write!(out, "{}{}", cursor::Goto(1, BOARD_HEIGHT + 3), self.message)?;
+        write!(out, "{}> {}", cursor::Goto(1, BOARD_HEIGHT + 4), input)?;
+        out.flush()
+    }
+}
+
+// Centipawns as pawns with a sign, "+0.35".
+fn pawns(centipawns: i32) -> String {
+    format!("{:+.2}", centipawns as f64 / 100.0)
+}
+
+// A change in the puzzle rating as it is shown after a move, ", rating 1512 (+12)".
+fn rating_change(change: Option<i32>, rating: i32) -> String {
+    change.map_or_else(String::new, |change| format!(", rating {} ({:+})", rating, change))
+}
+
+// What the tablebase says of a position, "Tablebase: White wins" and how many plies to the next capture or pawn
+// move, which the fifty-move rule counts from.
+fn endgame(side: Colour, wdl: Wdl, dtz: Option<i32>) -> Vec<String> {
+    let (winner, loser) = match side {
+        Colour::White => ("White", "Black"),
+        _ => ("Black", "White"),
+    };
+    let result = match wdl {
+        Wdl::Win => format!("{} wins", winner),
+        Wdl::CursedWin => format!("{} wins, but not inside fifty moves", winner),
+        Wdl::Draw => String::from("Draw"),
+        Wdl::BlessedLoss => format!("{} wins, but not inside fifty moves", loser),
+        Wdl::Loss => format!("{} wins", loser),
+    };
+    let mut lines = vec![format!("Tablebase: {}", result)];
+    if let Some(dtz) = dtz.filter(|&dtz| dtz != 0) {
+        lines.push(format!("  {} plies to a capture or pawn move", dtz.abs()));
+    }
+    lines
+}
+
+// One depth of a search as a line of the analysis, "Depth 3  +0.25  e4 e5 Nf3", the score from white's side.
+fn variation(board: &ChessBoard, info: &SearchInfo) -> String {
+    let white = board.side_to_move() == Colour::White;
+    let score = match info.mate_in() {
+        Some(moves) if (moves > 0) == white => format!("White mates in {}", moves.abs()),
+        Some(moves) => format!("Black mates in {}", moves.abs()),
+        None => pawns(if white { info.score } else { -info.score }),
+    };
+    let mut line = board.clone();
+    let sans: Vec<String> = info.pv.iter().map(|mv| {
+        let san = line.san(mv);
+        line.make_move(*mv);
+        san
+    }).collect();
+    format!("Depth {}  {}  {}", info.depth, score, sans.join(" "))
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::chessboard::Move;
+use crate::epd::{Epd, EpdError};
+use crate::search::{Engine, Limits};
+
+// Engine test suites, EPD files of positions with the best moves to find (`bm`) or the moves to avoid (`am`), like
+// Win at Chess. The engine searches each position afresh under the same limits, and solves it when the move it
+// settles on is one of the best moves and none of the moves to avoid.
+
+#[derive(Debug)]
+pub enum SuiteError {
+    Io(io::Error),
+    Invalid(usize, String),  // (line, reason)
+}
+
+impl fmt::Display for SuiteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SuiteError::Io(e) => write!(f, "{}", e),
+            SuiteError::Invalid(line, reason) => write!(f, "Invalid test position on line {}, {}", line, reason),
+        }
+    }
+}
+
+impl From<io::Error> for SuiteError {
+    fn from(e: io::Error) -> SuiteError {
+        SuiteError::Io(e)
+    }
+}
+
+// A position of a suite and what counts as solving it.
+pub struct Test {
+    pub id: String,
+    pub epd: Epd,
+    pub best: Vec<Move>,
+    pub avoid: Vec<Move>,
+}
+
+impl Test {
+    pub fn solved_by(&self, mv: Move) -> bool {
+        (self.best.is_empty() || self.best.contains(&mv)) && !self.avoid.contains(&mv)
+    }
+
+    // What the position asks for, "bm Qg6" or "am Qxd5", as written in the file.
+    pub fn expected(&self) -> String {
+        ["bm", "am"].iter()
+            .filter_map(|opcode| self.epd.operands(opcode).map(|moves| format!("{} {}", opcode, moves.join(" "))))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+// How the engine did on one position.
+pub struct Outcome {
+    pub id: String,
+    pub played: Option<String>,  // In SAN, None when the engine found no move
+    pub solved: bool,
+}
+
+pub struct Suite {
+    pub name: String,
+    pub tests: Vec<Test>,
+}
+
+impl Suite {
+    // Reads a suite, named after its file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Suite, SuiteError> {
+        let path = path.as_ref();
+        let name = path.file_stem().map_or_else(|| path.display().to_string(), |stem| stem.to_string_lossy().into_owned());
+        Suite::parse(&name, &fs::read_to_string(path)?)
+    }
+
+    pub fn parse(name: &str, text: &str) -> Result<Suite, SuiteError> {
+        let mut tests = Vec::new();
+        let lines = text.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+        for (number, line) in lines.filter(|(_, line)| !line.is_empty() && !line.starts_with('#')) {
+            let invalid = |e: EpdError| SuiteError::Invalid(number, e.to_string());
+            let epd = Epd::parse(line).map_err(invalid)?;
+            let best = epd.moves("bm").map_err(invalid)?.unwrap_or_default();
+            let avoid = epd.moves("am").map_err(invalid)?.unwrap_or_default();
+            if best.is_empty() && avoid.is_empty() {
+                return Err(SuiteError::Invalid(number, String::from("no bm or am operation")));
+            }
+            let id = epd.id().map_or_else(|| format!("{}:{}", name, number), String::from);
+            tests.push(Test { id, epd, best, avoid });
+        }
+        Ok(Suite { name: name.to_string(), tests })
+    }
+
+    // Searches every position in turn, calling `report` as each is done.
+    pub fn run<F: FnMut(&Test, &Outcome)>(&self, limits: Limits, mut report: F) -> Vec<Outcome> {
+        let mut engine = Engine::new(limits);
+        let mut outcomes = Vec::new();
+        for test in &self.tests {
+            // Nothing carried over from the position before, so each result stands on its own.
+            engine.clear_table();
+            let mv = engine.best_move(&test.epd.board);
+            let outcome = Outcome {
+                id: test.id.clone(),
+                played: mv.map(|mv| test.epd.board.san(&mv)),
+                solved: mv.is_some_and(|mv| test.solved_by(mv)),
+            };
+            report(test, &outcome);
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+}
@@ -0,0 +1,1041 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::bitboard;
+use crate::chessboard::{Actions, ChessBoard, Colour, Move, Piece, PieceKind};
+
+// Syzygy endgame tablebases: for every position with a few pieces, whether the side to move wins, draws or
+// loses (the .rtbw files), and how many plies it takes to get to the next capture or pawn move while playing for
+// that result, the distance to zeroing (the .rtbz files). The layout of the files is described with Stockfish's
+// prober, src/syzygy/tbprobe.cpp, which this follows.
+//
+// A table covers one set of material, named after it with the stronger side first: KRvK, KBNvK, KPvKP. The
+// other side being the stronger one is the same table with the colours swapped and the board turned round.
+
+// Pieces in the biggest tables there are.
+const MAX_PIECES: usize = 7;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+// Flags at the start of every file.
+const SPLIT: u8 = 1;  // Separate tables for either side to move
+const HAS_PAWNS: u8 = 2;
+
+// Flags of each table within a file. All but the last are for distance to zeroing.
+const STM: u8 = 1;  // The side to move the table is for, a distance table only stores one
+const MAPPED: u8 = 2;  // Values go through a map, most common first
+const WIN_PLIES: u8 = 4;  // Distances to a win are in plies rather than moves
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;  // The map has 16-bit values
+const SINGLE_VALUE: u8 = 128;  // The whole table is one value
+
+// Number of ways to place the leading pieces of a table without pawns: three different pieces, or else the two
+// kings, with the first in the a1-d1-d4 triangle.
+const UNIQUE_PIECES_SIZE: u64 = 31332;
+const KINGS_SIZE: u64 = 462;
+
+// Rank of a square less its file: 0 on the a1-h8 diagonal, below it negative.
+const fn off_diagonal(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+// Squares below the a1-h8 diagonal, numbered 0 to 27.
+const MAP_B1H1H7: [u64; 64] = {
+    let mut map = [0; 64];
+    let (mut square, mut code) = (0, 0);
+    while square < 64 {
+        if off_diagonal(square) < 0 {
+            map[square] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+    map
+};
+
+// Squares of the a1-d1-d4 triangle, those below the diagonal numbered 0 to 5 and the diagonal 6 to 9.
+const MAP_A1D1D4: [usize; 64] = {
+    let mut map = [0; 64];
+    let mut code = 0;
+    let mut pass = 0;
+    while pass < 2 {
+        let mut square = 0;
+        while square < 28 {
+            if square % 8 <= 3 && off_diagonal(square) <= 0 && (off_diagonal(square) == 0) == (pass == 1) {
+                map[square] = code;
+                code += 1;
+            }
+            square += 1;
+        }
+        pass += 1;
+    }
+    map
+};
+
+// The 462 ways to place the two kings, the first in the a1-d1-d4 triangle and, when it is on the diagonal,
+// the second not above it. Both kings on the diagonal come last.
+const MAP_KK: [[u64; 64]; 10] = {
+    let mut map = [[0; 64]; 10];
+    let mut code = 0;
+    let mut pass = 0;
+    while pass < 2 {
+        let mut idx = 0;
+        while idx < 10 {
+            let mut first = 0;
+            while first < 28 {
+                if MAP_A1D1D4[first] == idx && (idx > 0 || first == 1) {
+                    let mut second = 0;
+                    while second < 64 {
+                        let rows = (first / 8) as i32 - (second / 8) as i32;
+                        let cols = (first % 8) as i32 - (second % 8) as i32;
+                        let touching = rows >= -1 && rows <= 1 && cols >= -1 && cols <= 1;
+                        let first_on = off_diagonal(first) == 0;
+                        let both_on = first_on && off_diagonal(second) == 0;
+                        if !touching && !(first_on && off_diagonal(second) > 0) && both_on == (pass == 1) {
+                            map[idx][second] = code;
+                            code += 1;
+                        }
+                        second += 1;
+                    }
+                }
+                first += 1;
+            }
+            idx += 1;
+        }
+        pass += 1;
+    }
+    map
+};
+
+// BINOMIAL[k][n] ways to choose k things from n.
+const BINOMIAL: [[u64; 64]; MAX_PIECES + 1] = {
+    let mut table = [[0; 64]; MAX_PIECES + 1];
+    table[0][0] = 1;
+    let mut n = 1;
+    while n < 64 {
+        let mut k = 0;
+        while k <= MAX_PIECES && k <= n {
+            let with = if k > 0 { table[k - 1][n - 1] } else { 0 };
+            let without = if k < n { table[k][n - 1] } else { 0 };
+            table[k][n] = with + without;
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+};
+
+// Squares a2 to h7 numbered 47 down to 0, a file and its mirror image at a time from the edge in, rank by rank.
+// Of several pawns the one with the highest number leads: nearest the edge, then furthest back.
+const MAP_PAWNS: [usize; 64] = {
+    let mut map = [0; 64];
+    let mut file = 0;
+    while file < 4 {
+        let mut rank = 1;
+        while rank < 7 {
+            let available = 47 - 2 * (file * 6 + rank - 1);
+            map[rank * 8 + file] = available;
+            map[rank * 8 + 7 - file] = available - 1;
+            rank += 1;
+        }
+        file += 1;
+    }
+    map
+};
+
+// Where the index of the leading pawns starts for each square of the first of them, by how many there are, and
+// the size of each file's table.
+const LEAD_PAWNS: ([[u64; 64]; MAX_PIECES], [[u64; 4]; MAX_PIECES]) = {
+    let mut index = [[0; 64]; MAX_PIECES];
+    let mut size = [[0; 4]; MAX_PIECES];
+    let mut count = 1;
+    while count < MAX_PIECES {
+        let mut file = 0;
+        while file < 4 {
+            let mut idx = 0;
+            let mut rank = 1;
+            while rank < 7 {
+                let square = rank * 8 + file;
+                index[count][square] = idx;
+                idx += BINOMIAL[count - 1][MAP_PAWNS[square]];
+                rank += 1;
+            }
+            size[count][file] = idx;
+            file += 1;
+        }
+        count += 1;
+    }
+    (index, size)
+};
+
+// What a position comes to with best play from both sides, for the side to move. A cursed win is a win that
+// takes too long for the fifty-move rule, and so is a draw, a blessed loss the other side of one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            i32::MIN..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+}
+
+impl fmt::Display for Wdl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Wdl::Loss => write!(f, "loss"),
+            Wdl::BlessedLoss => write!(f, "blessed loss"),
+            Wdl::Draw => write!(f, "draw"),
+            Wdl::CursedWin => write!(f, "cursed win"),
+            Wdl::Win => write!(f, "win"),
+        }
+    }
+}
+
+// What went wrong reading a tablebase.
+#[derive(Debug)]
+pub enum TablebaseError {
+    Io(io::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for TablebaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TablebaseError::Io(e) => write!(f, "{}", e),
+            TablebaseError::Invalid(reason) => write!(f, "Invalid tablebase, {}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for TablebaseError {
+    fn from(e: io::Error) -> TablebaseError {
+        TablebaseError::Io(e)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Kind {
+    Wdl,
+    Dtz,
+}
+
+// How to read one table out of a file: a side to move, and with pawns a file for the leading pawn.
+#[derive(Clone, Debug, Default)]
+struct Pairs {
+    flags: u8,
+    min_sym_len: u8,  // The value itself in a table of a single value
+    block_size: u64,
+    span: u64,  // Values between entries of the sparse index
+    blocks: u64,
+    block_lengths: usize,  // Offsets into the file
+    block_lengths_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    lowest_sym: usize,
+    btree: usize,
+    data: usize,
+    base64: Vec<u64>,  // Lowest symbol of each length, padded out to 64 bits
+    symlen: Vec<u8>,  // How many values, less one, each symbol stands for
+    pieces: [u8; MAX_PIECES],  // In the order they are indexed, colour and kind like `piece_code`
+    group_len: [usize; MAX_PIECES + 1],  // Pieces in each group, ending with 0
+    group_idx: [u64; MAX_PIECES + 1],  // What each group's index is multiplied by, the last the table's size
+    map_idx: [usize; 4],  // Where the map of each result starts, in a distance table
+}
+
+// A table file read into memory.
+struct Table {
+    kind: Kind,
+    bytes: Vec<u8>,
+    symmetric: bool,  // Both sides have the same pieces
+    has_pawns: bool,
+    unique_pieces: bool,  // Either side has a piece besides the king that it only has one of
+    pawn_count: [usize; 2],  // The leading side's pawns, then the other side's
+    piece_count: usize,
+    pairs: Vec<Vec<Pairs>>,  // By side to move, then file
+    map: usize,
+}
+
+// Which table to probe for a position.
+enum Probed {
+    Value(i32),
+    OtherSide,  // The distance table only has the other side to move
+}
+
+// Piece codes the files use: 1 to 6 for white's pawn, knight, bishop, rook, queen and king, 9 to 14 for black's.
+fn piece_code(piece: Piece) -> u8 {
+    let kind = match piece.kind {
+        PieceKind::Pawn => 1,
+        PieceKind::Knight => 2,
+        PieceKind::Bishop => 3,
+        PieceKind::Rook => 4,
+        PieceKind::Queen => 5,
+        PieceKind::King => 6,
+        PieceKind::None => 0,
+    };
+    if piece.colour == Colour::Black { kind | 8 } else { kind }
+}
+
+// The pieces of one side the way table names write them, "KRP".
+fn material(board: &ChessBoard, colour: Colour) -> String {
+    let kinds = [
+        (PieceKind::King, 'K'),
+        (PieceKind::Queen, 'Q'),
+        (PieceKind::Rook, 'R'),
+        (PieceKind::Bishop, 'B'),
+        (PieceKind::Knight, 'N'),
+        (PieceKind::Pawn, 'P'),
+    ];
+    kinds.iter()
+        .flat_map(|(kind, letter)| {
+            let count = board.bitboards().pieces(*kind, colour).count_ones() as usize;
+            std::iter::repeat_n(*letter, count)
+        })
+        .collect()
+}
+
+fn is_zeroing(board: &ChessBoard, mv: &Move) -> bool {
+    matches!(mv.action, Actions::Takes | Actions::EnPassant)
+        || board.piece_at(mv.from).is_some_and(|piece| piece.kind == PieceKind::Pawn)
+}
+
+// The distance to zeroing of a position just before the capture or pawn move that keeps its result.
+fn dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => 101,
+        -1 => -101,
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+impl Table {
+    // Reads a table's file, named `name` after its material with the stronger side first.
+    fn parse(name: &str, kind: Kind, bytes: Vec<u8>) -> Result<Table, TablebaseError> {
+        let invalid = |reason: &str| TablebaseError::Invalid(format!("{}, {}", name, reason));
+        let sides: Vec<&str> = name.split('v').collect();
+        if sides.len() != 2 || !sides.iter().all(|side| side.starts_with('K')) {
+            return Err(invalid("not a table name"));
+        }
+        let count = |side: &str, letter: char| side.chars().filter(|c| *c == letter).count();
+        let pawns = [count(sides[0], 'P'), count(sides[1], 'P')];
+        // With pawns on both sides, the side with fewer leads, for better compression.
+        let first_leads = pawns[1] == 0 || (pawns[0] > 0 && pawns[1] >= pawns[0]);
+        let mut table = Table {
+            kind,
+            bytes,
+            symmetric: sides[0] == sides[1],
+            has_pawns: pawns[0] + pawns[1] > 0,
+            unique_pieces: sides.iter().any(|side| "QRBN".chars().any(|letter| count(side, letter) == 1)),
+            pawn_count: if first_leads { pawns } else { [pawns[1], pawns[0]] },
+            piece_count: sides[0].len() + sides[1].len(),
+            pairs: Vec::new(),
+            map: 0,
+        };
+        if table.piece_count > MAX_PIECES {
+            return Err(invalid("too many pieces"));
+        }
+        let magic = if kind == Kind::Wdl { WDL_MAGIC } else { DTZ_MAGIC };
+        if table.bytes.len() < 6 || table.bytes[..4] != magic {
+            return Err(invalid("not a Syzygy table"));
+        }
+        let flags = table.bytes[4];
+        if (flags & HAS_PAWNS != 0) != table.has_pawns || (flags & SPLIT != 0) == table.symmetric {
+            return Err(invalid("its pieces don't match its name"));
+        }
+        table.read_layout().ok_or_else(|| invalid("truncated or corrupt"))?;
+        Ok(table)
+    }
+
+    fn byte(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(offset).copied()
+    }
+
+    fn u16_le(&self, offset: usize) -> Option<u16> {
+        Some(u16::from_le_bytes([self.byte(offset)?, self.byte(offset + 1)?]))
+    }
+
+    fn u32_le(&self, offset: usize) -> Option<u32> {
+        Some(u32::from_le_bytes([self.byte(offset)?, self.byte(offset + 1)?, self.byte(offset + 2)?, self.byte(offset + 3)?]))
+    }
+
+    fn u32_be(&self, offset: usize) -> Option<u32> {
+        Some(u32::from_be_bytes([self.byte(offset)?, self.byte(offset + 1)?, self.byte(offset + 2)?, self.byte(offset + 3)?]))
+    }
+
+    // The symbols a symbol stands for, from the 12-bit pairs of the binary tree.
+    fn left(&self, pairs: &Pairs, sym: usize) -> Option<usize> {
+        let at = pairs.btree + 3 * sym;
+        Some((((self.byte(at + 1)? & 0xf) as usize) << 8) | self.byte(at)? as usize)
+    }
+
+    fn right(&self, pairs: &Pairs, sym: usize) -> Option<usize> {
+        let at = pairs.btree + 3 * sym;
+        Some(((self.byte(at + 2)? as usize) << 4) | (self.byte(at + 1)? >> 4) as usize)
+    }
+
+    fn files(&self) -> usize {
+        if self.has_pawns { 4 } else { 1 }
+    }
+
+    fn pairs(&self, stm: usize, file: usize) -> &Pairs {
+        let by_side = &self.pairs[stm % self.pairs.len()];
+        &by_side[if self.has_pawns { file } else { 0 }]
+    }
+
+    // Works out where everything is in the file: the order of the pieces, the sizes of the tables, the maps of
+    // a distance table, and then the sparse indexes, block lengths and compressed data of every table in turn.
+    fn read_layout(&mut self) -> Option<()> {
+        let sides = if self.kind == Kind::Wdl && !self.symmetric { 2 } else { 1 };
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        self.pairs = vec![vec![Pairs::default(); self.files()]; sides];
+        let mut at = 5;
+        for file in 0..self.files() {
+            let order = [self.byte(at)?, if both_pawns { self.byte(at + 1)? } else { 0xff }];
+            at += 1 + both_pawns as usize;
+            for k in 0..self.piece_count {
+                let byte = self.byte(at + k)?;
+                for side in 0..sides {
+                    self.pairs[side][file].pieces[k] = if side == 0 { byte & 0xf } else { byte >> 4 };
+                }
+            }
+            at += self.piece_count;
+            for side in 0..sides {
+                let order = [(order[0] >> (4 * side)) & 0xf, (order[1] >> (4 * side)) & 0xf];
+                self.set_groups(side, file, order)?;
+            }
+        }
+        at += at & 1;
+
+        for file in 0..self.files() {
+            for side in 0..sides {
+                at = self.set_sizes(side, file, at)?;
+            }
+        }
+        if self.kind == Kind::Dtz {
+            at = self.set_map(at)?;
+        }
+        for file in 0..self.files() {
+            for side in 0..sides {
+                self.pairs[side][file].sparse_index = at;
+                at += self.pairs[side][file].sparse_index_size * 6;
+            }
+        }
+        for file in 0..self.files() {
+            for side in 0..sides {
+                self.pairs[side][file].block_lengths = at;
+                at += self.pairs[side][file].block_lengths_size * 2;
+            }
+        }
+        for file in 0..self.files() {
+            for side in 0..sides {
+                at = (at + 0x3f) & !0x3f;
+                let pairs = &mut self.pairs[side][file];
+                pairs.data = at;
+                at = at.checked_add(usize::try_from(pairs.blocks.checked_mul(pairs.block_size)?).ok()?)?;
+                if pairs.blocks > 0 && at > self.bytes.len() {
+                    return None;
+                }
+            }
+        }
+        Some(())
+    }
+
+    // Splits the pieces into the groups that are indexed together: the leading pawns or pieces, then the other
+    // side's pawns, then each set of like pieces. The file gives the order the groups' indexes are combined in.
+    fn set_groups(&mut self, side: usize, file: usize, order: [u8; 2]) -> Option<()> {
+        let (has_pawns, unique, pawn_count, piece_count) = (self.has_pawns, self.unique_pieces, self.pawn_count, self.piece_count);
+        let pairs = &mut self.pairs[side][file];
+        let mut first_len: i32 = if has_pawns { 0 } else if unique { 3 } else { 2 };
+        let mut n = 0;
+        pairs.group_len[0] = 1;
+        for i in 1..piece_count {
+            first_len -= 1;
+            if first_len > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+                pairs.group_len[n] += 1;
+            } else {
+                n += 1;
+                pairs.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        pairs.group_len[n] = 0;
+        let both_pawns = has_pawns && pawn_count[1] > 0;
+        if order[0] as usize >= n || (order[1] != 0xf && order[1] as usize >= n) {
+            return None;
+        }
+
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free = 64 - pairs.group_len[0] - if both_pawns { pairs.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                pairs.group_idx[0] = idx;
+                idx *= if has_pawns {
+                    LEAD_PAWNS.1[pairs.group_len[0]][file]
+                } else if unique {
+                    UNIQUE_PIECES_SIZE
+                } else {
+                    KINGS_SIZE
+                };
+            } else if k == order[1] {
+                pairs.group_idx[1] = idx;
+                idx *= BINOMIAL[pairs.group_len[1]][48 - pairs.group_len[0]];
+            } else {
+                pairs.group_idx[next] = idx;
+                idx *= BINOMIAL[pairs.group_len[next]][free];
+                free -= pairs.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        pairs.group_idx[n] = idx;
+        Some(())
+    }
+
+    // Reads a table's header: block sizes, the canonical Huffman code and the pairs its symbols expand into.
+    fn set_sizes(&mut self, side: usize, file: usize, mut at: usize) -> Option<usize> {
+        let flags = self.byte(at)?;
+        if flags & SINGLE_VALUE != 0 {
+            let value = self.byte(at + 1)?;
+            let pairs = &mut self.pairs[side][file];
+            pairs.flags = flags;
+            pairs.min_sym_len = value;
+            return Some(at + 2);
+        }
+        let size = {
+            let pairs = &self.pairs[side][file];
+            pairs.group_idx[pairs.group_len.iter().position(|len| *len == 0)?]
+        };
+        let block_size = 1u64.checked_shl(self.byte(at + 1)? as u32)?;
+        let span = 1u64.checked_shl(self.byte(at + 2)? as u32)?;
+        let padding = self.byte(at + 3)? as usize;
+        let blocks = self.u32_le(at + 4)? as u64;
+        let (max_sym_len, min_sym_len) = (self.byte(at + 8)?, self.byte(at + 9)?);
+        if max_sym_len < min_sym_len || max_sym_len > 32 {
+            return None;
+        }
+        at += 10;
+        let lowest_sym = at;
+        let lengths = (max_sym_len - min_sym_len + 1) as usize;
+        let mut base64 = vec![0u64; lengths];
+        for i in (0..lengths - 1).rev() {
+            let (lowest, next) = (self.u16_le(lowest_sym + 2 * i)? as u64, self.u16_le(lowest_sym + 2 * i + 2)? as u64);
+            base64[i] = (base64[i + 1] + lowest).wrapping_sub(next) / 2;
+        }
+        for (i, base) in base64.iter_mut().enumerate() {
+            *base = base.checked_shl(64 - i as u32 - min_sym_len as u32).unwrap_or(0);
+        }
+        at += lengths * 2;
+        let symbols = self.u16_le(at)? as usize;
+        at += 2;
+        let btree = at;
+
+        let mut pairs = std::mem::take(&mut self.pairs[side][file]);
+        pairs.flags = flags;
+        pairs.min_sym_len = min_sym_len;
+        pairs.block_size = block_size;
+        pairs.span = span;
+        pairs.blocks = blocks;
+        pairs.sparse_index_size = usize::try_from(size.div_ceil(span)).ok()?;
+        pairs.block_lengths_size = blocks as usize + padding;
+        pairs.lowest_sym = lowest_sym;
+        pairs.btree = btree;
+        pairs.base64 = base64;
+        pairs.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                pairs.symlen[sym] = self.symlen(&mut pairs, sym, &mut visited)?;
+            }
+        }
+        self.pairs[side][file] = pairs;
+        Some(btree + symbols * 3 + (symbols & 1))
+    }
+
+    // How many values, less one, a symbol expands into, its pairs worked out first.
+    fn symlen(&self, pairs: &mut Pairs, sym: usize, visited: &mut [bool]) -> Option<u8> {
+        visited[sym] = true;
+        let right = self.right(pairs, sym)?;
+        if right == 0xfff {
+            return Some(0);
+        }
+        let left = self.left(pairs, sym)?;
+        for child in [left, right].iter() {
+            if !*visited.get(*child)? {
+                pairs.symlen[*child] = self.symlen(pairs, *child, visited)?;
+            }
+        }
+        Some(pairs.symlen[left].wrapping_add(pairs.symlen[right]).wrapping_add(1))
+    }
+
+    // A distance table stores the distances for each result in order of how often they come up, with a map
+    // back to the distances themselves.
+    fn set_map(&mut self, mut at: usize) -> Option<usize> {
+        self.map = at;
+        for file in 0..self.files() {
+            let flags = self.pairs[0][file].flags;
+            if flags & MAPPED == 0 {
+                continue;
+            }
+            for i in 0..4 {
+                if flags & WIDE != 0 {
+                    at += at & 1;
+                    self.pairs[0][file].map_idx[i] = (at - self.map) / 2 + 1;
+                    at += 2 * self.u16_le(at)? as usize + 2;
+                } else {
+                    self.pairs[0][file].map_idx[i] = at - self.map + 1;
+                    at += self.byte(at)? as usize + 1;
+                }
+            }
+        }
+        Some(at + (at & 1))
+    }
+
+    // The value stored at `idx`. The sparse index gives a block near it, the block lengths the block it is in,
+    // and the Huffman code of that block the symbol holding it, which the pairs then expand down to the value.
+    fn decompress(&self, pairs: &Pairs, idx: u64) -> Option<u16> {
+        if pairs.flags & SINGLE_VALUE != 0 {
+            return Some(pairs.min_sym_len as u16);
+        }
+        let k = usize::try_from(idx / pairs.span).ok()?;
+        if k >= pairs.sparse_index_size {
+            return None;
+        }
+        let entry = pairs.sparse_index + 6 * k;
+        let mut block = self.u32_le(entry)? as usize;
+        let mut offset = self.u16_le(entry + 4)? as i64 + (idx % pairs.span) as i64 - (pairs.span / 2) as i64;
+        let block_length = |block: usize| {
+            if block < pairs.block_lengths_size { self.u16_le(pairs.block_lengths + 2 * block) } else { None }
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? as i64 + 1;
+        }
+        while offset > block_length(block)? as i64 {
+            offset -= block_length(block)? as i64 + 1;
+            block += 1;
+        }
+
+        let mut at = pairs.data + usize::try_from(block as u64 * pairs.block_size).ok()?;
+        let mut buffer = ((self.u32_be(at)? as u64) << 32) | self.u32_be(at + 4)? as u64;
+        at += 8;
+        let mut bits = 64;
+        let min_sym_len = pairs.min_sym_len as u32;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buffer < *pairs.base64.get(len)? {
+                len += 1;
+            }
+            sym = ((buffer - pairs.base64[len]) >> (64 - len as u32 - min_sym_len)) as usize;
+            sym += self.u16_le(pairs.lowest_sym + 2 * len)? as usize;
+            let values = *pairs.symlen.get(sym)? as i64 + 1;
+            if offset < values {
+                break;
+            }
+            offset -= values;
+            let used = len as u32 + min_sym_len;
+            buffer = buffer.checked_shl(used).unwrap_or(0);
+            bits -= used;
+            if bits <= 32 {
+                bits += 32;
+                buffer |= (self.u32_be(at)? as u64) << (64 - bits);
+                at += 4;
+            }
+        }
+        while pairs.symlen[sym] != 0 {
+            let left = self.left(pairs, sym)?;
+            let values = *pairs.symlen.get(left)? as i64 + 1;
+            if offset < values {
+                sym = left;
+            } else {
+                offset -= values;
+                sym = self.right(pairs, sym)?;
+            }
+        }
+        self.left(pairs, sym).map(|value| value as u16)
+    }
+
+    // Turns a stored distance back into plies to zeroing for a position whose result is `wdl`.
+    fn map_score(&self, file: usize, value: u16, wdl: i32) -> Option<i32> {
+        if self.kind == Kind::Wdl {
+            return Some(value as i32 - 2);
+        }
+        let pairs = self.pairs(0, file);
+        let mut value = value as usize;
+        if pairs.flags & MAPPED != 0 {
+            // The maps are kept win, loss, cursed win, blessed loss.
+            let map_idx = pairs.map_idx[[1, 3, 0, 2, 0][(wdl + 2) as usize]];
+            value = if pairs.flags & WIDE != 0 {
+                self.u16_le(self.map + 2 * (map_idx + value))? as usize
+            } else {
+                self.byte(self.map + map_idx + value)? as usize
+            };
+        }
+        let plies = (wdl == 2 && pairs.flags & WIN_PLIES != 0) || (wdl == -2 && pairs.flags & LOSS_PLIES != 0);
+        Some(if plies { value as i32 + 1 } else { 2 * value as i32 + 1 })
+    }
+
+    // Probes the table for a position with this table's material, `flipped` when black has the pieces the
+    // name gives first. The position is turned into an index the way the generator numbered them, after
+    // mirroring it into the part of the board the table covers.
+    fn probe(&self, board: &ChessBoard, flipped: bool, wdl: i32) -> Option<Probed> {
+        let bitboards = board.bitboards();
+        let black_to_move = board.side_to_move() == Colour::Black;
+        // A table for the same pieces on both sides only has white to move, turn the board round for black.
+        let flip = flipped || (self.symmetric && black_to_move);
+        let (flip_colour, flip_squares) = if flip { (8, 56) } else { (0, 0) };
+        let stm = (flip != black_to_move) as usize;
+
+        let mut squares = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut file = 0;
+        if self.has_pawns {
+            let lead = self.pairs(0, 0).pieces[0] ^ flip_colour;
+            let colour = if lead & 8 != 0 { Colour::Black } else { Colour::White };
+            lead_pawns = bitboards.pieces(PieceKind::Pawn, colour);
+            for square in bitboard::squares(lead_pawns) {
+                squares[size] = square ^ flip_squares;
+                size += 1;
+            }
+            let leading = (0..size).max_by_key(|i| MAP_PAWNS[squares[*i]])?;
+            squares.swap(0, leading);
+            file = squares[0] % 8;
+            if file > 3 {
+                file = 7 - file;
+            }
+        }
+        let lead_count = size;
+
+        // A distance table only has one side to move, though without pawns the same pieces on both sides turn
+        // round to either.
+        if self.kind == Kind::Dtz {
+            let side = (self.pairs(stm, file).flags & STM) as usize;
+            let either_side = self.symmetric && !self.has_pawns;
+            if side != stm && !either_side {
+                return Some(Probed::OtherSide);
+            }
+        }
+
+        for square in bitboard::squares(bitboards.occupied() & !lead_pawns) {
+            if size == MAX_PIECES {
+                return None;
+            }
+            squares[size] = square ^ flip_squares;
+            pieces[size] = piece_code(board.piece_at(bitboard::square(square))?) ^ flip_colour;
+            size += 1;
+        }
+        if size != self.piece_count {
+            return None;
+        }
+
+        // Line the pieces up in the order the table has them.
+        let pairs = self.pairs(stm, file);
+        for i in lead_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|j| pairs.pieces[i] == pieces[*j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+        // The leading piece goes on the a to d files.
+        if squares[0] % 8 > 3 {
+            squares[..size].iter_mut().for_each(|square| *square ^= 7);
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = LEAD_PAWNS.0[lead_count][squares[0]];
+            squares[1..lead_count].sort_by_key(|square| MAP_PAWNS[*square]);
+            for (i, square) in squares.iter().enumerate().take(lead_count).skip(1) {
+                idx += BINOMIAL[i][MAP_PAWNS[*square]];
+            }
+        } else {
+            // Without pawns the leading piece also goes on the first four ranks, and below the a1-h8 diagonal
+            // unless it is on it, in which case the first piece of the leading group that isn't decides.
+            if squares[0] / 8 > 3 {
+                squares[..size].iter_mut().for_each(|square| *square ^= 56);
+            }
+            if let Some(i) = (0..pairs.group_len[0]).find(|i| off_diagonal(squares[*i]) != 0) {
+                if off_diagonal(squares[i]) > 0 {
+                    for square in squares[i..size].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+            }
+            idx = if self.unique_pieces { unique_index(&squares) } else { MAP_KK[MAP_A1D1D4[squares[0]]][squares[1]] };
+        }
+
+        // The rest of the pieces group by group, each group's squares in order, counting only the squares the
+        // groups before it leave free.
+        idx *= pairs.group_idx[0];
+        let mut start = pairs.group_len[0];
+        let mut other_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while pairs.group_len[next] != 0 {
+            let len = pairs.group_len[next];
+            squares[start..start + len].sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let taken = squares[..start].iter().filter(|s| square > **s).count();
+                let free = (square - taken).checked_sub(if other_pawns { 8 } else { 0 })?;
+                n += BINOMIAL[i + 1][free];
+            }
+            other_pawns = false;
+            idx += n * pairs.group_idx[next];
+            start += len;
+            next += 1;
+        }
+        let value = self.decompress(pairs, idx)?;
+        self.map_score(file, value, wdl).map(Probed::Value)
+    }
+}
+
+// Index of three different leading pieces, the first below the a1-h8 diagonal or all of them on it.
+fn unique_index(squares: &[usize]) -> u64 {
+    let adjust1 = (squares[1] > squares[0]) as u64;
+    let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+    let (s0, s1, s2) = (squares[0] as u64, squares[1] as u64, squares[2] as u64);
+    let rank = |square: u64| square / 8;
+    if off_diagonal(squares[0]) != 0 {
+        (MAP_A1D1D4[squares[0]] as u64 * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+    } else if off_diagonal(squares[1]) != 0 {
+        (6 * 63 + rank(s0) * 28 + MAP_B1H1H7[squares[1]]) * 62 + s2 - adjust2
+    } else if off_diagonal(squares[2]) != 0 {
+        6 * 63 * 62 + 4 * 28 * 62 + rank(s0) * 7 * 28 + (rank(s1) - adjust1) * 28 + MAP_B1H1H7[squares[2]]
+    } else {
+        6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank(s0) * 7 * 6 + (rank(s1) - adjust1) * 6 + (rank(s2) - adjust2)
+    }
+}
+
+// Tables read so far by name and kind, None for a file that couldn't be read.
+type Loaded = HashMap<(String, Kind), Option<Arc<Table>>>;
+
+// A directory of Syzygy files. Tables are read when first probed and kept.
+pub struct Tablebase {
+    files: HashMap<(String, Kind), PathBuf>,
+    tables: Mutex<Loaded>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    // Finds the tables in a directory. It is an error for there to be none.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Tablebase, TablebaseError> {
+        let mut files = HashMap::new();
+        let mut max_pieces = 0;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let kind = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("rtbw") => Kind::Wdl,
+                Some("rtbz") => Kind::Dtz,
+                _ => continue,
+            };
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                max_pieces = max_pieces.max(name.len().saturating_sub(1));
+                files.insert((String::from(name), kind), path.clone());
+            }
+        }
+        if files.is_empty() {
+            return Err(TablebaseError::Invalid(String::from("no .rtbw or .rtbz files")));
+        }
+        Ok(Tablebase { files, tables: Mutex::new(HashMap::new()), max_pieces })
+    }
+
+    // Pieces on the board in the biggest table, kings included.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    // Whether the side to move wins, draws or loses, if the tables cover the position. Positions where castling
+    // is still allowed aren't covered.
+    pub fn probe_wdl(&self, board: &ChessBoard) -> Option<Wdl> {
+        if !self.covers(board) {
+            return None;
+        }
+        self.search(&mut board.clone(), false).map(|(wdl, _)| Wdl::from_value(wdl))
+    }
+
+    // Plies to the next capture or pawn move playing for the result, positive when the side to move wins and
+    // negative when it loses, 0 for a draw. Cursed wins and blessed losses are more than 100 plies away.
+    pub fn probe_dtz(&self, board: &ChessBoard) -> Option<i32> {
+        if !self.covers(board) {
+            return None;
+        }
+        self.dtz(&mut board.clone())
+    }
+
+    // The move that keeps the best result fastest: the shortest way to the next capture or pawn move when winning,
+    // the longest when losing.
+    pub fn best_move(&self, board: &ChessBoard) -> Option<Move> {
+        if !self.covers(board) {
+            return None;
+        }
+        let mut board = board.clone();
+        let mut best: Option<(i64, Move)> = None;
+        for mv in board.legal_moves() {
+            let zeroing = is_zeroing(&board, &mv);
+            board.make_move(mv);
+            let dtz = if board.is_checkmate() {
+                Some(1)
+            } else if zeroing {
+                self.search(&mut board, false).map(|(wdl, _)| dtz_before_zeroing(-wdl))
+            } else if board.is_repetition() {
+                Some(0)
+            } else {
+                self.dtz(&mut board).map(|dtz| -dtz + (-dtz).signum())
+            };
+            board.unmake_move();
+            let dtz = dtz? as i64;
+            let rank = match dtz {
+                d if d > 0 => i64::MAX / 2 - d,
+                d if d < 0 => i64::MIN / 2 - d,
+                _ => 0,
+            };
+            if best.is_none_or(|(best, _)| rank > best) {
+                best = Some((rank, mv));
+            }
+        }
+        best.map(|(_, mv)| mv)
+    }
+
+    fn covers(&self, board: &ChessBoard) -> bool {
+        let castling = board.game_state().castling;
+        let can_castle = castling.white_kingside || castling.white_queenside
+            || castling.black_kingside || castling.black_queenside;
+        !can_castle && (board.bitboards().occupied().count_ones() as usize) <= self.max_pieces
+    }
+
+    // The table for the position's material, and whether black has the pieces the table's name gives first.
+    fn table(&self, board: &ChessBoard, kind: Kind) -> Option<(Arc<Table>, bool)> {
+        let (white, black) = (material(board, Colour::White), material(board, Colour::Black));
+        let (name, flipped) = match format!("{}v{}", white, black) {
+            name if self.files.contains_key(&(name.clone(), kind)) => (name, false),
+            _ => (format!("{}v{}", black, white), true),
+        };
+        let mut tables = self.tables.lock().unwrap();
+        let table = tables.entry((name.clone(), kind)).or_insert_with(|| {
+            let path = self.files.get(&(name.clone(), kind))?;
+            let bytes = fs::read(path).ok()?;
+            Table::parse(&name, kind, bytes).ok().map(Arc::new)
+        });
+        table.clone().map(|table| {
+            let symmetric = table.symmetric;
+            (table, flipped && !symmetric)
+        })
+    }
+
+    fn probe_table(&self, board: &ChessBoard, kind: Kind, wdl: i32) -> Option<Probed> {
+        // Two bare kings have no table.
+        if board.bitboards().occupied().count_ones() == 2 {
+            return Some(Probed::Value(0));
+        }
+        let (table, flipped) = self.table(board, kind)?;
+        table.probe(board, flipped, wdl)
+    }
+
+    // The result of a position, with the captures (and pawn moves, `zeroing`) searched first: the tables don't
+    // bother to store what they lead to, or anything about en passant. Also says whether the best of these moves
+    // is the best move there is, in which case the distance tables don't have the position either.
+    fn search(&self, board: &mut ChessBoard, zeroing: bool) -> Option<(i32, bool)> {
+        let moves = board.legal_moves();
+        if moves.is_empty() {
+            return Some((if board.is_check() { -2 } else { 0 }, true));
+        }
+        let mut best = -2;
+        let mut searched = 0;
+        for mv in moves.iter() {
+            let capture = matches!(mv.action, Actions::Takes | Actions::EnPassant);
+            let searched_first = capture || (zeroing && is_zeroing(board, mv));
+            if !searched_first {
+                continue;
+            }
+            searched += 1;
+            board.make_move(*mv);
+            let value = self.search(board, false).map(|(value, _)| -value);
+            board.unmake_move();
+            let value = value?;
+            if value > best {
+                best = value;
+                if value >= 2 {
+                    return Some((value, true));
+                }
+            }
+        }
+        let every_move = searched == moves.len();
+        let value = if every_move {
+            best
+        } else {
+            match self.probe_table(board, Kind::Wdl, 0)? {
+                Probed::Value(value) => value,
+                Probed::OtherSide => return None,
+            }
+        };
+        if best >= value {
+            Some((best, best > 0 || every_move))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    fn dtz(&self, board: &mut ChessBoard) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(board, true)?;
+        if wdl == 0 {
+            return Some(0);
+        }
+        if zeroing_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+        let sign = wdl.signum();
+        match self.probe_table(board, Kind::Dtz, wdl)? {
+            Probed::Value(dtz) => Some((dtz + if wdl.abs() == 1 { 100 } else { 0 }) * sign),
+            // Only the other side to move is stored, so look one move ahead for the quickest way to keep the result.
+            Probed::OtherSide => {
+                let mut best: Option<i32> = None;
+                for mv in board.legal_moves() {
+                    let zeroing = is_zeroing(board, &mv);
+                    board.make_move(mv);
+                    let dtz = if zeroing {
+                        self.search(board, false).map(|(value, _)| -dtz_before_zeroing(value))
+                    } else {
+                        self.dtz(board).map(|dtz| -dtz)
+                    };
+                    let mate = dtz == Some(1) && board.is_checkmate();
+                    board.unmake_move();
+                    let mut dtz = dtz?;
+                    if mate {
+                        best = Some(1);
+                    }
+                    if !zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz.signum() == sign && best.is_none_or(|best| dtz < best) {
+                        best = Some(dtz);
+                    }
+                }
+                Some(best.unwrap_or(-1))
+            },
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::env;
+
+use termion::color;
+
+// Names `Theme::named` knows, for listing in help and error messages.
+pub const THEME_NAMES: [&str; 4] = ["classic", "green", "blue", "wood"];
+
+// A terminal colour. Basic colours are the eight every terminal has, 0 black to 7 white, sent as the plain
+// SGR codes 30-37 and 40-47 (termion's named colours go through the 256-colour palette instead). Palette
+// colours need a 256-colour terminal and Rgb one with truecolor support.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shade {
+    Basic(u8),
+    Palette(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Shade {
+    pub fn bg(&self) -> String {
+        match *self {
+            Shade::Basic(n) => format!("\x1b[{}m", 40 + n.min(7)),
+            Shade::Palette(n) => color::Bg(color::AnsiValue(n)).to_string(),
+            Shade::Rgb(r, g, b) => color::Bg(color::Rgb(r, g, b)).to_string(),
+        }
+    }
+
+    pub fn fg(&self) -> String {
+        match *self {
+            Shade::Basic(n) => format!("\x1b[{}m", 30 + n.min(7)),
+            Shade::Palette(n) => color::Fg(color::AnsiValue(n)).to_string(),
+            Shade::Rgb(r, g, b) => color::Fg(color::Rgb(r, g, b)).to_string(),
+        }
+    }
+
+    // The nearest colour of the 6x6x6 cube in the 256-colour palette, for terminals without truecolor.
+    pub fn to_palette(&self) -> Shade {
+        match *self {
+            Shade::Rgb(r, g, b) => {
+                let level = |c: u8| if c < 48 { 0 } else if c < 115 { 1 } else { (c - 35) / 40 };
+                Shade::Palette(16 + 36 * level(r) + 6 * level(g) + level(b))
+            },
+            palette => palette,
+        }
+    }
+}
+
+// Colours `ChessBoard::draw` paints the board with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub light: Shade,
+    pub dark: Shade,
+    pub cursor: Shade,
+    pub selected: Shade,
+    pub destination: Shade,  // Where the selected piece can move
+    pub last_move: Shade,  // Both squares of the move just played
+    pub pieces: Option<Shade>,  // The terminal's own text colour when None
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::classic()
+    }
+}
+
+impl Theme {
+    // The terminal's basic white and black, works everywhere.
+    pub fn classic() -> Theme {
+        Theme {
+            light: Shade::Basic(7),
+            dark: Shade::Basic(0),
+            cursor: Shade::Basic(4),
+            selected: Shade::Basic(3),
+            destination: Shade::Basic(2),
+            last_move: Shade::Basic(6),
+            pieces: None,
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Theme> {
+        match name {
+            "classic" => Some(Theme::classic()),
+            // 256-colour palettes
+            "green" => Some(Theme {
+                light: Shade::Palette(187),
+                dark: Shade::Palette(65),
+                cursor: Shade::Palette(75),
+                selected: Shade::Palette(221),
+                destination: Shade::Palette(150),
+                last_move: Shade::Palette(186),
+                pieces: Some(Shade::Palette(16)),
+            }),
+            "blue" => Some(Theme {
+                light: Shade::Palette(153),
+                dark: Shade::Palette(67),
+                cursor: Shade::Palette(210),
+                selected: Shade::Palette(221),
+                destination: Shade::Palette(114),
+                last_move: Shade::Palette(152),
+                pieces: Some(Shade::Palette(16)),
+            }),
+            // Truecolor
+            "wood" => Some(Theme {
+                light: Shade::Rgb(240, 217, 181),
+                dark: Shade::Rgb(181, 136, 99),
+                cursor: Shade::Rgb(100, 149, 237),
+                selected: Shade::Rgb(246, 246, 105),
+                destination: Shade::Rgb(130, 151, 105),
+                last_move: Shade::Rgb(205, 210, 106),
+                pieces: Some(Shade::Rgb(0, 0, 0)),
+            }),
+            _ => None,
+        }
+    }
+
+    // The same theme with truecolor colours swapped for their nearest 256-colour ones.
+    pub fn to_palette(&self) -> Theme {
+        Theme {
+            light: self.light.to_palette(),
+            dark: self.dark.to_palette(),
+            cursor: self.cursor.to_palette(),
+            selected: self.selected.to_palette(),
+            destination: self.destination.to_palette(),
+            last_move: self.last_move.to_palette(),
+            pieces: self.pieces.map(|shade| shade.to_palette()),
+        }
+    }
+}
+
+// Whether the terminal says it shows truecolor, through COLORTERM as most terminals that do set it.
+pub fn truecolor() -> bool {
+    env::var("COLORTERM").map(|value| value == "truecolor" || value == "24bit").unwrap_or(false)
+}
+
+// Whether the locale in effect is UTF-8, so the Unicode chess glyphs and box characters will show. Taken to be
+// so when no locale is set at all.
+pub fn unicode_locale() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+        .filter_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+        .next()
+        .map(|value| {
+            let value = value.to_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        })
+        .unwrap_or(true)
+}
+
+// A named theme as this terminal can show it.
+pub fn for_terminal(name: &str) -> Option<Theme> {
+    let theme = Theme::named(name)?;
+    Some(if truecolor() { theme } else { theme.to_palette() })
+}
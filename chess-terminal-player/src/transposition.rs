@@ -0,0 +1,73 @@
+use std::mem;
+
+use crate::chessboard::Move;
+
+pub const DEFAULT_MEGABYTES: usize = 16;
+
+// How a stored score relates to the position's true score, which depends on the alpha-beta window it came from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,  // The search failed high, the true score is at least this
+    Upper,  // No move reached alpha, the true score is at most this
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,  // Full Zobrist hash, to tell apart positions sharing a slot
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best: Option<Move>,
+    generation: u8,
+}
+
+impl Entry {
+    pub fn new(key: u64, depth: u32, score: i32, bound: Bound, best: Option<Move>) -> Entry {
+        Entry { key, depth, score, bound, best, generation: 0 }
+    }
+}
+
+// Results of earlier searches by Zobrist hash, so a position reached again by another move order isn't searched
+// twice. The table has a fixed number of slots, each hash goes to one slot and a new entry replaces an old one
+// unless the old one came from a deeper search of another position during the same search.
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    generation: u8,  // Goes up with every search, to tell current entries from old ones
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        // A power of two number of slots, so the slot is just the low bits of the hash.
+        let slots = (megabytes.max(1) * 1024 * 1024 / mem::size_of::<Option<Entry>>()).next_power_of_two() / 2;
+        TranspositionTable { entries: vec![None; slots.max(1)], generation: 0 }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    // Entries from earlier searches are kept for move ordering and cutoffs, but any new entry may replace them.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries[self.slot(key)].filter(|entry| entry.key == key)
+    }
+
+    pub fn store(&mut self, entry: Entry) {
+        let slot = self.slot(entry.key);
+        let keep = match self.entries[slot] {
+            Some(old) => old.generation == self.generation && old.key != entry.key && old.depth > entry.depth,
+            None => false,
+        };
+        if !keep {
+            self.entries[slot] = Some(Entry { generation: self.generation, ..entry });
+        }
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key & (self.entries.len() as u64 - 1)) as usize
+    }
+}
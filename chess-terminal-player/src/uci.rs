@@ -0,0 +1,440 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::chessboard::{parse_square, square_name, ChessBoard, Colour, Move, PieceKind};
+use crate::search::{Engine, Limits, SearchInfo};
+use crate::transposition::DEFAULT_MEGABYTES;
+
+const ENGINE_NAME: &str = "Chess Player";
+const ENGINE_AUTHOR: &str = "Ertugrul Karademir";
+const MAX_HASH_MEGABYTES: usize = 1024;
+// Moves assumed left to play when the GUI gives the clock without `movestogo`.
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+// Kept back from the clock for the time it takes the answer to reach the GUI.
+const MOVE_OVERHEAD: u64 = 50;
+
+// A move in the long algebraic notation UCI uses, e7e8q for a promotion and e1g1 for castling. In Chess960
+// castling is written as the king taking its own rook, e1h1.
+pub fn format_move(board: &ChessBoard, mv: &Move) -> String {
+    let promotion = match mv.promotion {
+        Some(PieceKind::Queen) => "q",
+        Some(PieceKind::Rook) => "r",
+        Some(PieceKind::Bishop) => "b",
+        Some(PieceKind::Knight) => "n",
+        _ => "",
+    };
+    let to = match board.castling_rook(mv) {
+        Some(rook) if board.is_chess960() => rook,
+        _ => mv.to,
+    };
+    format!("{}{}{}", square_name(mv.from), square_name(to), promotion)
+}
+
+// Finds the legal move written in long algebraic notation.
+pub fn parse_move(board: &ChessBoard, text: &str) -> Option<Move> {
+    if !text.is_ascii() || text.len() < 4 || text.len() > 5 {
+        return None;
+    }
+    let from = parse_square(&text[0..2])?;
+    let to = parse_square(&text[2..4])?;
+    let promotion = match &text[4..] {
+        "" => None,
+        "q" => Some(PieceKind::Queen),
+        "r" => Some(PieceKind::Rook),
+        "b" => Some(PieceKind::Bishop),
+        "n" => Some(PieceKind::Knight),
+        _ => return None,
+    };
+    board.legal_moves().into_iter().find(|m| m.from == from && m.promotion == promotion && match board.castling_rook(m) {
+        Some(rook) if board.is_chess960() => rook == to,
+        _ => m.to == to,
+    })
+}
+
+// Speaks the Universal Chess Interface, reading commands from `input` until `quit` or the end of input.
+// Searches run on their own thread so `stop` and `isready` are answered while the engine thinks.
+pub fn run<R: BufRead, W: Write + Send + 'static>(input: R, output: W) -> io::Result<()> {
+    let output = Arc::new(Mutex::new(output));
+    let mut board = ChessBoard::new();
+    let mut chess960 = false;
+    let mut engine = Some(Engine::new(Limits::default()));  // None while searching, the search thread has it
+    let mut search: Option<Search> = None;
+
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("uci") => {
+                let mut out = output.lock().unwrap();
+                writeln!(out, "id name {}", ENGINE_NAME)?;
+                writeln!(out, "id author {}", ENGINE_AUTHOR)?;
+                writeln!(out, "option name Hash type spin default {} min 1 max {}", DEFAULT_MEGABYTES, MAX_HASH_MEGABYTES)?;
+                writeln!(out, "option name UCI_Chess960 type check default false")?;
+                writeln!(out, "uciok")?;
+                out.flush()?;
+            },
+            Some("isready") => {
+                let mut out = output.lock().unwrap();
+                writeln!(out, "readyok")?;
+                out.flush()?;
+            },
+            Some("ucinewgame") => {
+                stop(&mut search, &mut engine);
+                board = ChessBoard::new();
+                if let Some(engine) = engine.as_mut() {
+                    engine.clear_table();
+                }
+            },
+            Some("setoption") => {
+                stop(&mut search, &mut engine);
+                let words: Vec<&str> = words.collect();
+                if let ["name", name, "value", value] = words[..] {
+                    match (name.to_lowercase().as_str(), value.parse::<usize>()) {
+                        ("hash", Ok(megabytes)) => {
+                            if let Some(engine) = engine.as_mut() {
+                                engine.set_table_size(megabytes.clamp(1, MAX_HASH_MEGABYTES));
+                            }
+                        },
+                        ("uci_chess960", _) if value == "true" || value == "false" => chess960 = value == "true",
+                        _ => {
+                            let mut out = output.lock().unwrap();
+                            writeln!(out, "info string Invalid option, {}", words.join(" "))?;
+                            out.flush()?;
+                        },
+                    }
+                }
+            },
+            Some("position") => {
+                stop(&mut search, &mut engine);
+                match parse_position(&words.collect::<Vec<&str>>(), chess960) {
+                    Ok(position) => board = position,
+                    Err(e) => {
+                        let mut out = output.lock().unwrap();
+                        writeln!(out, "info string {}", e)?;
+                        out.flush()?;
+                    },
+                }
+            },
+            // Not part of UCI, but other engines answer it the same way, which makes comparing easy.
+            Some("go") if line.split_whitespace().nth(1) == Some("perft") => {
+                stop(&mut search, &mut engine);
+                let depth = line.split_whitespace().nth(2).and_then(|d| d.parse().ok()).unwrap_or(1);
+                let mut out = output.lock().unwrap();
+                write_divide(&mut *out, &board, depth)?;
+                out.flush()?;
+            },
+            Some("go") => {
+                stop(&mut search, &mut engine);
+                let (limits, infinite) = parse_go(&words.collect::<Vec<&str>>(), board.side_to_move());
+                let mut idle = engine.take().unwrap_or_else(|| Engine::new(Limits::default()));
+                idle.limits = limits;
+                search = Some(start_search(idle, &board, infinite, output.clone()));
+            },
+            Some("stop") => stop(&mut search, &mut engine),
+            Some("quit") => {
+                stop(&mut search, &mut engine);
+                return Ok(());
+            },
+            // Anything else is ignored, as the protocol asks.
+            _ => {},
+        }
+    }
+    // Piped input may end before the last search does, let it finish unless it would run forever.
+    if let Some(search) = search {
+        if search.bounded { search.wait(); } else { search.stop(); }
+    }
+    Ok(())
+}
+
+// Stops a running search and takes the engine back from it.
+fn stop(search: &mut Option<Search>, engine: &mut Option<Engine>) {
+    if let Some(search) = search.take() {
+        *engine = Some(search.stop());
+    }
+}
+
+// Node counts per first move, then the total.
+fn write_divide<W: Write>(out: &mut W, board: &ChessBoard, depth: u32) -> io::Result<()> {
+    let counts = board.divide(depth);
+    for (mv, nodes) in &counts {
+        writeln!(out, "{}: {}", format_move(board, mv), nodes)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "Nodes searched: {}", counts.iter().map(|(_, nodes)| nodes).sum::<u64>())
+}
+
+// A search running on its own thread.
+struct Search {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Engine>,
+    bounded: bool,  // Ends by itself, without waiting for `stop`
+}
+
+impl Search {
+    // Waits for the search to print its best move, and gets the engine back.
+    fn wait(self) -> Engine {
+        self.handle.join().unwrap_or_else(|_| Engine::new(Limits::default()))
+    }
+
+    fn stop(self) -> Engine {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait()
+    }
+}
+
+// `startpos` or `fen <fields>`, optionally followed by `moves` and the moves played since.
+fn parse_position(words: &[&str], chess960: bool) -> Result<ChessBoard, String> {
+    let moves_at = words.iter().position(|w| *w == "moves").unwrap_or(words.len());
+    let mut board = match words.first() {
+        Some(&"startpos") => ChessBoard::new(),
+        Some(&"fen") => ChessBoard::from_fen(&words[1..moves_at].join(" ")).map_err(|e| e.to_string())?,
+        _ => return Err(String::from("Invalid position, expected startpos or fen")),
+    };
+    if chess960 {
+        board.set_chess960(true);
+    }
+    for text in words.iter().skip(moves_at + 1) {
+        match parse_move(&board, text) {
+            Some(mv) => board.make_move(mv),
+            None => return Err(format!("Invalid move, {}", text)),
+        }
+    }
+    Ok(board)
+}
+
+// Turns the arguments of `go` into search limits. The second value is set for `go infinite`,
+// where the best move is held back until `stop` even if the search ends by itself.
+fn parse_go(words: &[&str], side: Colour) -> (Limits, bool) {
+    let mut limits = Limits::default();
+    let mut infinite = false;
+    let (mut time, mut increment, mut moves_to_go) = (None, 0, DEFAULT_MOVES_TO_GO);
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        let mut value = || words.next().and_then(|v| v.parse::<u64>().ok());
+        match *word {
+            "depth" => limits.depth = value().map(|d| d as u32),
+            "movetime" => limits.time = value().map(Duration::from_millis),
+            "wtime" if side == Colour::White => time = value(),
+            "btime" if side == Colour::Black => time = value(),
+            "winc" if side == Colour::White => increment = value().unwrap_or(0),
+            "binc" if side == Colour::Black => increment = value().unwrap_or(0),
+            "movestogo" => moves_to_go = value().unwrap_or(DEFAULT_MOVES_TO_GO).max(1),
+            "infinite" => infinite = true,
+            _ => {},
+        }
+    }
+    // An even share of the clock for the moves left, plus most of the increment, never more than is there.
+    if let (Some(time), None) = (time, limits.time) {
+        let budget = (time / moves_to_go + increment * 3 / 4).min(time.saturating_sub(MOVE_OVERHEAD));
+        limits.time = Some(Duration::from_millis(budget.max(1)));
+    }
+    (limits, infinite)
+}
+
+fn start_search<W: Write + Send + 'static>(
+    mut engine: Engine,
+    board: &ChessBoard,
+    infinite: bool,
+    output: Arc<Mutex<W>>,
+) -> Search {
+    let bounded = !infinite && (engine.limits.depth.is_some() || engine.limits.time.is_some());
+    let stop = engine.stop_handle();
+    stop.store(false, Ordering::Relaxed);
+    let board = board.clone();
+    let handle = thread::spawn(move || {
+        let info = engine.search(&board, |info| {
+            let mut out = output.lock().unwrap();
+            let _ = writeln!(out, "{}", format_info(&board, info));
+            let _ = out.flush();
+        });
+        if infinite {
+            while !engine.stop_handle().load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+        let mut out = output.lock().unwrap();
+        let _ = match info.best_move() {
+            Some(mv) => writeln!(out, "bestmove {}", format_move(&board, &mv)),
+            None => writeln!(out, "bestmove 0000"),
+        };
+        let _ = out.flush();
+        engine
+    });
+    Search { stop, handle, bounded }
+}
+
+fn format_info(board: &ChessBoard, info: &SearchInfo) -> String {
+    let score = match info.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    let millis = info.elapsed.as_millis() as u64;
+    let nps = info.nodes * 1000 / millis.max(1);
+    let pv: Vec<String> = info.pv.iter().map(|mv| format_move(board, mv)).collect();
+    format!("info depth {} score {} nodes {} nps {} time {} pv {}", info.depth, score, info.nodes, nps, millis, pv.join(" "))
+}
+
+// How long an external engine gets to answer `uci` and `isready`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Extra time an external engine gets past its move time before it is given up on.
+const MOVE_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum UciError {
+    Io(io::Error),
+    Timeout(&'static str),  // The reply that never came
+    Exited,
+    IllegalMove(String),
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciError::Io(e) => write!(f, "Engine I/O failed, {}", e),
+            UciError::Timeout(reply) => write!(f, "Engine didn't answer with {}", reply),
+            UciError::Exited => write!(f, "Engine exited"),
+            UciError::IllegalMove(text) => write!(f, "Engine played an illegal move, {}", text),
+        }
+    }
+}
+
+impl From<io::Error> for UciError {
+    fn from(e: io::Error) -> UciError {
+        UciError::Io(e)
+    }
+}
+
+// An external UCI engine running as a child process.
+pub struct UciClient {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,  // Fed by a thread reading the engine's stdout, so replies can time out
+    name: Option<String>,
+    chess960: bool,  // Whether the engine has been told to play Chess960
+}
+
+impl UciClient {
+    // Starts `program` and waits for it to finish the `uci` handshake.
+    pub fn spawn(program: &str, args: &[String]) -> Result<UciClient, UciError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or(UciError::Exited)?;
+        let stdout = child.stdout.take().ok_or(UciError::Exited)?;
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut client = UciClient { child, stdin, lines, name: None, chess960: false };
+        client.send("uci")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = client.receive(Some(deadline), "uciok")?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                client.name = Some(name.trim().to_string());
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        client.ready()?;
+        Ok(client)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // Tells the engine the next position is from a different game, so it can drop what it learnt from the last.
+    pub fn new_game(&mut self) -> Result<(), UciError> {
+        self.send("ucinewgame")?;
+        self.ready()
+    }
+
+    // Asks the engine for its move in the board's position, sent as the starting position and the moves since.
+    pub fn best_move(&mut self, board: &ChessBoard, limits: Limits) -> Result<Move, UciError> {
+        if board.is_chess960() != self.chess960 {
+            self.send(&format!("setoption name UCI_Chess960 value {}", board.is_chess960()))?;
+            self.chess960 = board.is_chess960();
+        }
+        let mut start = board.clone();
+        while start.unmake_move().is_some() {}
+        let moves: Vec<String> = board.history().iter().map(|record| format_move(board, &record.mv)).collect();
+        if moves.is_empty() {
+            self.send(&format!("position fen {}", start.to_fen()))?;
+        } else {
+            self.send(&format!("position fen {} moves {}", start.to_fen(), moves.join(" ")))?;
+        }
+
+        let mut go = String::from("go");
+        if let Some(depth) = limits.depth {
+            go.push_str(&format!(" depth {}", depth));
+        }
+        if let Some(time) = limits.time {
+            go.push_str(&format!(" movetime {}", time.as_millis()));
+        }
+        self.send(&go)?;
+
+        // The whole answer, info lines and all, has to come within the move time.
+        let deadline = limits.time.map(|time| Instant::now() + time + MOVE_GRACE);
+        loop {
+            let line = self.receive(deadline, "bestmove")?;
+            if let Some(text) = line.strip_prefix("bestmove ") {
+                let text = text.split_whitespace().next().unwrap_or("");
+                return parse_move(board, text).ok_or_else(|| UciError::IllegalMove(text.to_string()));
+            }
+        }
+    }
+
+    fn ready(&mut self) -> Result<(), UciError> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.receive(Some(deadline), "readyok")?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), UciError> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    // The engine's next line, waiting no later than `deadline` when one is given.
+    fn receive(&mut self, deadline: Option<Instant>, expected: &'static str) -> Result<String, UciError> {
+        match deadline {
+            Some(deadline) => self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())).map_err(|e| match e {
+                RecvTimeoutError::Timeout => UciError::Timeout(expected),
+                RecvTimeoutError::Disconnected => UciError::Exited,
+            }),
+            None => self.lines.recv().map_err(|_| UciError::Exited),
+        }
+    }
+}
+
+impl Drop for UciClient {
+    // Asks the engine to quit, and ends it if it doesn't.
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        for _ in 0..20 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
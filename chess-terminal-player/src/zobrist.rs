@@ -0,0 +1,54 @@
+use crate::bitboard::{colour_index, kind_index};
+use crate::chessboard::{CastlingRights, Piece, PieceKind, Colour};
+
+// Random keys for Zobrist hashing. A position's hash is the XOR of the keys of everything in it, so a move
+// only has to XOR out the keys of what it takes away and XOR in the keys of what it adds.
+
+const PIECE_KEYS: usize = 0;  // 2 colours x 6 kinds x 64 squares
+const SIDE_KEY: usize = 768;  // In the hash when black is to move
+const CASTLING_KEYS: usize = 769;  // One per combination of the four castling rights
+const EN_PASSANT_KEYS: usize = 785;  // One per file
+const KEY_COUNT: usize = 793;
+
+// Worked out at compile time, with a fixed seed so hashes are the same from run to run.
+const KEYS: [u64; KEY_COUNT] = keys(0x2545_f491_4f6c_dd1d);
+
+// SplitMix64, small and good enough to fill a key table.
+const fn keys(seed: u64) -> [u64; KEY_COUNT] {
+    let mut table = [0; KEY_COUNT];
+    let mut state = seed;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+// Key for a piece standing on a square, zero for an empty square.
+pub fn piece_key(piece: Piece, index: usize) -> u64 {
+    if piece.kind == PieceKind::None || piece.colour == Colour::None {
+        return 0;
+    }
+    KEYS[PIECE_KEYS + (colour_index(piece.colour) * 6 + kind_index(piece.kind)) * 64 + index]
+}
+
+pub fn side_key() -> u64 {
+    KEYS[SIDE_KEY]
+}
+
+pub fn castling_key(rights: CastlingRights) -> u64 {
+    let combination = rights.white_kingside as usize
+        | (rights.white_queenside as usize) << 1
+        | (rights.black_kingside as usize) << 2
+        | (rights.black_queenside as usize) << 3;
+    KEYS[CASTLING_KEYS + combination]
+}
+
+pub fn en_passant_key(col: usize) -> u64 {
+    KEYS[EN_PASSANT_KEYS + col]
+}
@@ -0,0 +1,98 @@
+extern crate chess_player;
+
+use chess_player::book::{polyglot_key, Book, BookError};
+use chess_player::chessboard::{ChessBoard, Move};
+
+fn after(moves: &[&str]) -> ChessBoard {
+    let mut board = ChessBoard::new();
+    for san in moves {
+        board.move_piece(san).unwrap();
+    }
+    board
+}
+
+// A book entry as Polyglot writes it, squares given as (row, col).
+fn entry(key: u64, from: (u16, u16), to: (u16, u16), promotion: u16, weight: u16) -> Vec<u8> {
+    let mv = promotion << 12 | from.0 << 9 | from.1 << 6 | to.0 << 3 | to.1;
+    let mut bytes = key.to_be_bytes().to_vec();
+    bytes.extend_from_slice(&mv.to_be_bytes());
+    bytes.extend_from_slice(&weight.to_be_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+    bytes
+}
+
+fn book(mut entries: Vec<Vec<u8>>) -> Book {
+    entries.sort();
+    Book::from_bytes(&entries.concat()).unwrap()
+}
+
+fn san(board: &ChessBoard, mv: Move) -> String {
+    board.san(&mv)
+}
+
+// The examples published with the Polyglot book format.
+#[test]
+fn keys_match_the_polyglot_examples() {
+    let cases: [(&[&str], u64); 9] = [
+        (&[], 0x463b96181691fc9c),
+        (&["e4"], 0x823c9b50fd114196),
+        (&["e4", "d5"], 0x0756b94461c50fb0),
+        (&["e4", "d5", "e5"], 0x662fafb965db29d4),
+        (&["e4", "d5", "e5", "f5"], 0x22a48b5a8e47ff78),
+        (&["e4", "d5", "e5", "f5", "Ke2"], 0x652a607ca3f242c1),
+        (&["e4", "d5", "e5", "f5", "Ke2", "Kf7"], 0x00fdd303c946bdd9),
+        (&["a4", "b5", "h4", "b4", "c4"], 0x3c8123ea7b067637),
+        (&["a4", "b5", "h4", "b4", "c4", "bxc3", "Ra3"], 0x5c3f9b829b279560),
+    ];
+    for (moves, key) in cases.iter() {
+        assert_eq!(polyglot_key(&after(moves)), *key, "{:?}", moves);
+    }
+    let fen = ChessBoard::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3").unwrap();
+    assert_eq!(polyglot_key(&fen), 0x652a607ca3f242c1);
+}
+
+#[test]
+fn moves_are_looked_up_heaviest_first() {
+    let start = polyglot_key(&ChessBoard::new());
+    let book = book(vec![
+        entry(start, (1, 3), (3, 3), 0, 10),
+        entry(start, (1, 4), (3, 4), 0, 30),
+        entry(start, (0, 6), (2, 5), 0, 0),
+        entry(start - 1, (1, 0), (2, 0), 0, 5),
+        entry(start + 1, (1, 7), (2, 7), 0, 5),
+    ]);
+    let board = ChessBoard::new();
+    let moves: Vec<(String, u16)> = book.moves(&board).iter().map(|m| (san(&board, m.mv), m.weight)).collect();
+    assert_eq!(moves, vec![(String::from("e4"), 30), (String::from("d4"), 10)]);
+
+    // Rolls fall into the moves in proportion to their weights.
+    let chosen: Vec<String> = [0, 29, 30, 39, 40].iter()
+        .map(|roll| san(&board, book.choose(&board, *roll).unwrap()))
+        .collect();
+    assert_eq!(chosen, vec!["e4", "e4", "d4", "d4", "e4"]);
+    assert!(book.moves(&after(&["e4"])).is_empty());
+    assert_eq!(book.pick(&after(&["e4"])), None);
+}
+
+#[test]
+fn castling_and_promotion_entries() {
+    let castles = ChessBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let promotes = ChessBoard::from_fen("8/2P5/8/8/8/8/8/k1K5 w - - 0 1").unwrap();
+    let book = book(vec![
+        entry(polyglot_key(&castles), (0, 4), (0, 7), 0, 2),
+        entry(polyglot_key(&castles), (0, 4), (0, 0), 0, 1),
+        entry(polyglot_key(&promotes), (6, 2), (7, 2), 1, 1),
+    ]);
+    let sans: Vec<String> = book.moves(&castles).iter().map(|m| san(&castles, m.mv)).collect();
+    assert_eq!(sans, vec!["O-O", "O-O-O"]);
+    let sans: Vec<String> = book.moves(&promotes).iter().map(|m| san(&promotes, m.mv)).collect();
+    assert_eq!(sans, vec!["c8=N"]);
+}
+
+#[test]
+fn broken_books_are_refused() {
+    assert!(matches!(Book::from_bytes(&[0; 20]), Err(BookError::Invalid(_))));
+    let unsorted = [entry(2, (1, 4), (3, 4), 0, 1), entry(1, (1, 3), (3, 3), 0, 1)].concat();
+    assert!(matches!(Book::from_bytes(&unsorted), Err(BookError::Invalid(_))));
+    assert!(matches!(Book::open("/nonexistent/book.bin"), Err(BookError::Io(_))));
+}
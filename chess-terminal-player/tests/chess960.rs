@@ -0,0 +1,100 @@
+extern crate chess_player;
+
+use chess_player::chessboard::{ChessBoard, FenError, STARTING_FEN};
+use chess_player::pgn::Game;
+use chess_player::uci::{format_move, parse_move};
+
+fn check(fen: &str, counts: &[u64]) {
+    let board = ChessBoard::from_fen(fen).unwrap();
+    for (depth, expected) in counts.iter().enumerate() {
+        assert_eq!(board.perft(depth as u32 + 1), *expected, "perft({}) of {}", depth + 1, fen);
+    }
+}
+
+// Reference counts from https://www.chessprogramming.org/Chess960_Perft_Results
+#[test]
+fn chess960_perft() {
+    check("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", &[21, 528, 12_189]);
+    check("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", &[21, 807, 18_002]);
+    check("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", &[20, 479, 10_471]);
+    check("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", &[22, 593, 13_440]);
+    check("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", &[28, 1120, 31_058]);
+}
+
+#[test]
+fn start_positions_by_number() {
+    assert_eq!(ChessBoard::chess960(518).unwrap().to_fen(), STARTING_FEN);
+    assert_eq!(ChessBoard::chess960(0).unwrap().to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    assert_eq!(ChessBoard::chess960(959).unwrap().to_fen(), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+    assert!(ChessBoard::chess960(960).is_none());
+
+    // Every number gives a different setup, bishops on opposite colours and the king between the rooks.
+    let mut ranks: Vec<String> = (0..960)
+        .map(|n| ChessBoard::chess960(n).unwrap().to_fen().split(['/', ' ']).nth(7).unwrap().to_string())
+        .collect();
+    for rank in &ranks {
+        let cols = |piece: char| rank.char_indices().filter(move |(_, c)| *c == piece).map(|(i, _)| i);
+        let bishops: Vec<usize> = cols('B').collect();
+        assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}", rank);
+        let rooks: Vec<usize> = cols('R').collect();
+        let king = cols('K').next().unwrap();
+        assert!(rooks[0] < king && king < rooks[1], "{}", rank);
+    }
+    ranks.sort();
+    ranks.dedup();
+    assert_eq!(ranks.len(), 960);
+}
+
+#[test]
+fn custom_back_ranks() {
+    let board = ChessBoard::from_back_rank("NRKBBRQN").unwrap();
+    assert!(board.is_chess960());
+    assert_eq!(board.to_fen(), "nrkbbrqn/pppppppp/8/8/8/8/PPPPPPPP/NRKBBRQN w KQkq - 0 1");
+    assert!(!ChessBoard::from_back_rank("RNBQKBNR").unwrap().is_chess960());
+    // No rook on one side of the king, no castling that way.
+    assert!(!ChessBoard::from_back_rank("KRNBBNQR").unwrap().game_state().castling.white_queenside);
+    assert_eq!(ChessBoard::from_back_rank("RNBQKBN").err(), Some(FenError::RankLength(1, String::from("RNBQKBN"))));
+    assert_eq!(ChessBoard::from_back_rank("RNBQKBNP").err(), Some(FenError::UnknownPiece('P')));
+}
+
+#[test]
+fn castling_from_other_files() {
+    // The king on b1 castles long by staying put, the rook on a1 goes to d1. Castling short, the rook
+    // starts on the king's destination.
+    let fen = "5k2/8/8/8/8/8/8/RK4R1 w KQ - 0 1";
+    let mut board = ChessBoard::from_fen(fen).unwrap();
+    let long = board.move_piece("O-O-O").unwrap();
+    assert_eq!(board.to_fen(), "5k2/8/8/8/8/8/8/2KR2R1 b - - 1 1");
+    board.undo();
+    assert_eq!(board.to_fen(), fen);
+    assert_eq!(long.to, (0, 2));
+
+    let short = board.move_piece("O-O").unwrap();
+    assert_eq!(board.to_fen(), "5k2/8/8/8/8/8/8/R4RK1 b - - 1 1");
+    board.undo();
+    assert_eq!(board.hash(), ChessBoard::from_fen(fen).unwrap().hash());
+
+    // The black rook on a1 would attack c1 once the castling rook leaves b1, so the king can't go there.
+    let shielded = ChessBoard::from_fen("5k2/8/8/8/8/8/8/rR1K3R w HB - 0 1").unwrap();
+    assert!(shielded.parse_san("O-O-O").is_err());
+    assert!(shielded.parse_san("O-O").is_ok());
+
+    // UCI writes castling as the king taking its rook in Chess960.
+    assert_eq!(format_move(&board, &short), "b1g1");
+    assert_eq!(format_move(&board, &long), "b1a1");
+    assert_eq!(parse_move(&board, "b1a1"), Some(long));
+}
+
+#[test]
+fn chess960_games_in_pgn() {
+    let mut board = ChessBoard::chess960(0).unwrap();
+    for san in ["f4", "f5", "Rf3", "Rf6", "O-O", "O-O"].iter() {
+        board.move_piece(san).unwrap();
+    }
+    let game = Game::from_board(&board);
+    assert_eq!(game.tag("Variant"), Some("Chess960"));
+    assert_eq!(game.tag("FEN"), Some("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"));
+    let read = Game::parse(&game.to_string()).unwrap().replay().unwrap();
+    assert!(read.is_chess960());
+    assert_eq!(read.to_fen(), board.to_fen());
+}
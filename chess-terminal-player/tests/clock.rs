@@ -0,0 +1,147 @@
+extern crate chess_player;
+
+use std::time::{Duration, Instant};
+
+use chess_player::chessboard::{ChessBoard, Colour, GameResult};
+use chess_player::clock::{format_time, Bonus, Clock, TimeControl};
+use chess_player::pgn::Game;
+
+fn secs(s: u64) -> Duration {
+    Duration::from_secs(s)
+}
+
+#[test]
+fn time_controls_read_and_write() {
+    let blitz: TimeControl = "300+2".parse().unwrap();
+    assert_eq!(blitz, TimeControl { time: secs(300), moves: None, bonus: Bonus::Fischer(secs(2)) });
+    let classical: TimeControl = "40/5400".parse().unwrap();
+    assert_eq!(classical, TimeControl { time: secs(5400), moves: Some(40), bonus: Bonus::None });
+    let delay: TimeControl = "180d5".parse().unwrap();
+    assert_eq!(delay.bonus, Bonus::Bronstein(secs(5)));
+    for text in ["300+2", "40/5400", "180d5", "40/7200+30", "60"].iter() {
+        assert_eq!(text.parse::<TimeControl>().unwrap().to_string(), *text);
+    }
+    for text in ["", "0", "5m", "0/300", "300+", "+2", "300x2"].iter() {
+        assert!(text.parse::<TimeControl>().is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn times_on_display() {
+    assert_eq!(format_time(secs(3900)), "1:05:00");
+    assert_eq!(format_time(secs(299)), "4:59");
+    assert_eq!(format_time(secs(10)), "0:10");
+    assert_eq!(format_time(Duration::from_millis(7_380)), "0:07.3");
+}
+
+#[test]
+fn fischer_increment() {
+    let start = Instant::now();
+    let mut clock = Clock::new("60+2".parse().unwrap());
+    clock.start(Colour::White, start);
+    assert_eq!(clock.remaining(Colour::White, start + secs(5)), secs(55));
+    clock.press(start + secs(5));
+    assert_eq!(clock.remaining(Colour::White, start + secs(5)), secs(57));
+    assert_eq!(clock.running(), Some(Colour::Black));
+    clock.press(start + secs(15));
+    assert_eq!(clock.remaining(Colour::Black, start + secs(15)), secs(52));
+    assert_eq!(clock.move_times(), vec![secs(57), secs(52)]);
+}
+
+#[test]
+fn bronstein_delay_gives_back_at_most_the_time_taken() {
+    let start = Instant::now();
+    let mut clock = Clock::new("60d3".parse().unwrap());
+    clock.start(Colour::White, start);
+    clock.press(start + secs(2));
+    assert_eq!(clock.remaining(Colour::White, start + secs(2)), secs(60));
+    clock.press(start + secs(12));
+    assert_eq!(clock.remaining(Colour::Black, start + secs(12)), secs(53));
+}
+
+#[test]
+fn moves_per_period_add_time_after_the_last_move_of_a_period() {
+    let start = Instant::now();
+    let mut clock = Clock::new("2/60".parse().unwrap());
+    clock.start(Colour::White, start);
+    let mut now = start;
+    for _ in 0..3 {
+        now += secs(10);
+        clock.press(now);
+    }
+    // White has made two moves in 20 seconds, black one in 10.
+    assert_eq!(clock.remaining(Colour::White, now), secs(100));
+    assert_eq!(clock.remaining(Colour::Black, now), secs(50));
+}
+
+#[test]
+fn flag_falls_when_time_runs_out() {
+    let start = Instant::now();
+    let mut clock = Clock::new("10".parse().unwrap());
+    clock.start(Colour::White, start);
+    clock.tick(start + secs(9));
+    assert_eq!(clock.flagged(), None);
+    // Pressing too late doesn't save the flag or hand over to black.
+    clock.press(start + secs(11));
+    assert_eq!(clock.flagged(), Some(Colour::White));
+    assert_eq!(clock.running(), None);
+    assert_eq!(clock.remaining(Colour::White, start + secs(11)), Duration::ZERO);
+    assert!(clock.move_times().is_empty());
+}
+
+#[test]
+fn undo_and_redo_go_back_to_earlier_readings() {
+    let start = Instant::now();
+    let mut clock = Clock::new("60".parse().unwrap());
+    clock.start(Colour::White, start);
+    clock.press(start + secs(5));
+    clock.press(start + secs(20));
+    clock.undo(start + secs(30));
+    assert_eq!(clock.running(), Some(Colour::Black));
+    assert_eq!(clock.remaining(Colour::Black, start + secs(30)), secs(60));
+    clock.redo(start + secs(40));
+    assert_eq!(clock.running(), Some(Colour::White));
+    assert_eq!(clock.remaining(Colour::Black, start + secs(40)), secs(45));
+    assert_eq!(clock.move_times(), vec![secs(55), secs(45)]);
+}
+
+#[test]
+fn flag_fall_against_a_bare_king_is_a_draw() {
+    let board = ChessBoard::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+    assert!(board.can_checkmate(Colour::White));
+    assert!(!board.can_checkmate(Colour::Black));
+    let knight = ChessBoard::from_fen("4k3/8/8/8/8/8/8/4KN2 b - - 0 1").unwrap();
+    assert!(!knight.can_checkmate(Colour::White));
+    let knight_against_pawn = ChessBoard::from_fen("4k3/4p3/8/8/8/8/8/4KN2 b - - 0 1").unwrap();
+    assert!(knight_against_pawn.can_checkmate(Colour::White));
+    assert_eq!(GameResult::Timeout(Colour::White).pgn_token(), "1-0");
+    assert_eq!(GameResult::TimeoutVsInsufficientMaterial.pgn_token(), "1/2-1/2");
+}
+
+#[test]
+fn clock_times_in_pgn() {
+    let start = Instant::now();
+    let mut board = ChessBoard::new();
+    let mut clock = Clock::new("300+2".parse().unwrap());
+    clock.start(Colour::White, start);
+    for (san, at) in [("e4", 3), ("e5", 5), ("Nf3", 70)].iter() {
+        board.move_piece(san).unwrap();
+        clock.press(start + secs(*at));
+    }
+    let mut game = Game::from_board(&board);
+    game.moves[0].comment = Some(String::from("Best by test"));
+    game.set_clock(&clock);
+    assert_eq!(game.tag("TimeControl"), Some("300+2"));
+
+    let text = game.to_string();
+    let movetext = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    assert!(movetext.contains("1. e4 {[%clk 0:04:59] Best by test} 1... e5 {[%clk 0:05:00]} 2. Nf3 {[%clk 0:03:56]}"), "{}", text);
+    let read = Game::parse(&text).unwrap();
+    let times: Vec<Option<Duration>> = read.moves.iter().map(|m| m.clock()).collect();
+    assert_eq!(times, vec![Some(secs(299)), Some(secs(300)), Some(secs(236))]);
+
+    // Writing the time again replaces the one there.
+    let mut m = read.moves[0].clone();
+    m.set_clock(secs(3725));
+    assert_eq!(m.comment.as_deref(), Some("[%clk 1:02:05] Best by test"));
+}
@@ -0,0 +1,68 @@
+extern crate chess_player;
+
+use chess_player::chessboard::ChessBoard;
+use chess_player::evaluation::{breakdown, evaluate, Breakdown};
+
+fn terms(fen: &str) -> Breakdown {
+    breakdown(&ChessBoard::from_fen(fen).unwrap())
+}
+
+// The same position with the colours swapped, white's pieces where black's were.
+fn mirror(fen: &str) -> String {
+    let fields: Vec<&str> = fen.split(' ').collect();
+    let ranks: Vec<String> = fields[0].split('/').rev().map(|rank| rank.chars().map(|c| {
+        if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }
+    }).collect()).collect();
+    let side = if fields[1] == "w" { "b" } else { "w" };
+    format!("{} {} - - 0 1", ranks.join("/"), side)
+}
+
+#[test]
+fn terms_add_up_to_the_evaluation() {
+    assert_eq!(terms("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), Breakdown::default());
+    let fens = [
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+    ];
+    for fen in fens.iter() {
+        let board = ChessBoard::from_fen(fen).unwrap();
+        let total = breakdown(&board).total();
+        assert_eq!(evaluate(&board), if fen.contains(" w ") { total } else { -total });
+        // Every term is the same for the other side of the mirrored position.
+        let mirrored = terms(&mirror(fen));
+        let original = breakdown(&board);
+        assert_eq!(mirrored.material, -original.material);
+        assert_eq!(mirrored.placement, -original.placement);
+        assert_eq!(mirrored.mobility, -original.mobility);
+        assert_eq!(mirrored.king_safety, -original.king_safety);
+        assert_eq!(mirrored.pawn_structure, -original.pawn_structure);
+    }
+}
+
+#[test]
+fn mobility_counts_squares_a_piece_can_reach() {
+    // The centralised knight reaches 8 squares, the cornered one 2.
+    assert_eq!(terms("n3k3/8/8/8/3N4/8/8/4K3 w - - 0 1").mobility, 4 * 8 - 4 * 2);
+    // Squares an enemy pawn guards don't count, b5 here, though the pawn itself can be taken.
+    assert_eq!(terms("n3k3/8/2p5/8/3N4/8/8/4K3 w - - 0 1").mobility, 4 * 7 - 4 * 2);
+}
+
+#[test]
+fn king_safety_looks_at_the_pawns_in_front() {
+    assert_eq!(terms("r2q2k1/5ppp/8/8/8/8/5PPP/R2Q2K1 w - - 0 1").king_safety, 0);
+    assert!(terms("r2q2k1/5ppp/8/8/8/8/5P1P/R2Q2K1 w - - 0 1").king_safety < 0);
+    assert!(terms("r2q2k1/5ppp/8/8/8/6P1/5P1P/R2Q2K1 w - - 0 1").king_safety < 0);
+    // Without the queens the king comes out, its safety no longer counts.
+    assert_eq!(terms("6k1/5ppp/8/8/8/8/7P/6K1 w - - 0 1").king_safety, 0);
+}
+
+#[test]
+fn pawn_structure_finds_doubled_isolated_and_passed_pawns() {
+    // Doubled and isolated, but both passed.
+    assert_eq!(terms("4k3/8/8/8/8/2P5/2P5/4K3 w - - 0 1").pawn_structure, -15 - 2 * 15 + 10 + 15);
+    // Two passed pawns side by side, against a lone passed pawn further on.
+    assert_eq!(terms("4k3/8/8/p7/8/8/3PP3/4K3 w - - 0 1").pawn_structure, 2 * 10 - (25 - 15));
+    // A pawn held up by the pawns in front of it is no longer passed.
+    assert_eq!(terms("4k3/8/8/3p4/8/8/3PP3/4K3 w - - 0 1").pawn_structure, 15);
+}
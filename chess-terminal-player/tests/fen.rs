@@ -0,0 +1,105 @@
+extern crate chess_player;
+
+use chess_player::chessboard::{ChessBoard, Colour, FenError};
+
+fn error(fen: &str) -> FenError {
+    ChessBoard::from_fen(fen).err().unwrap()
+}
+
+#[test]
+fn positions_read_back_as_written() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w kq f6 0 3",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 40",
+        "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 99 120",
+    ];
+    for fen in fens.iter() {
+        assert_eq!(ChessBoard::from_fen(fen).unwrap().to_fen(), *fen);
+    }
+    assert_eq!(ChessBoard::new().to_fen(), fens[0]);
+    // As in EPD, the clocks can be left off.
+    let board = ChessBoard::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+    assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+}
+
+#[test]
+fn moves_keep_the_fields_up_to_date() {
+    let mut board = ChessBoard::new();
+    board.move_piece("e4").unwrap();
+    assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    board.move_piece("Nf6").unwrap();
+    board.move_piece("Ke2").unwrap();
+    assert_eq!(board.to_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2");
+    // The king going back doesn't bring back its rights.
+    board.move_piece("Ng8").unwrap();
+    board.move_piece("Ke1").unwrap();
+    assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b kq - 4 3");
+}
+
+#[test]
+fn en_passant_square_is_behind_the_pawn() {
+    assert!(ChessBoard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").is_ok());
+    assert!(ChessBoard::from_fen("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1").is_ok());
+    // Wrong rank for the side to move, or not a square.
+    assert_eq!(error("4k3/8/8/3pP3/8/8/8/4K3 w - d3 0 1"), FenError::EnPassant(String::from("d3")));
+    assert_eq!(error("4k3/8/8/8/3Pp3/8/8/4K3 b - d6 0 1"), FenError::EnPassant(String::from("d6")));
+    assert_eq!(error("4k3/8/8/3pP3/8/8/8/4K3 w - i6 0 1"), FenError::EnPassant(String::from("i6")));
+}
+
+#[test]
+fn castling_rights_need_a_king_at_home() {
+    assert!(ChessBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").is_ok());
+    let castling = |rights: &str| FenError::Castling(String::from(rights));
+    // The king has left its home rank, the same right twice, no such right.
+    assert_eq!(error("r3k2r/8/8/8/8/8/4K3/R6R w KQ - 0 1"), castling("KQ"));
+    assert_eq!(error("r3k2r/8/8/8/8/8/8/R3K2R w KK - 0 1"), castling("KK"));
+    assert_eq!(error("r3k2r/8/8/8/8/8/8/R3K2R w KX - 0 1"), castling("KX"));
+    // A rook file, as Shredder-FEN writes it, but not the king's own.
+    assert!(ChessBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1").is_ok());
+    assert_eq!(error("r3k2r/8/8/8/8/8/8/R3K2R w E - 0 1"), castling("E"));
+}
+
+// The side that just moved can't have left its king attacked.
+#[test]
+fn only_the_side_to_move_can_be_in_check() {
+    assert!(ChessBoard::from_fen("4k3/8/8/8/8/8/4Q3/4K3 b - - 0 1").unwrap().is_check());
+    assert_eq!(error("4k3/8/8/8/8/8/4Q3/4K3 w - - 0 1"), FenError::NotToMoveInCheck(Colour::Black));
+    assert_eq!(error("4k3/4r3/8/8/8/8/8/4K3 b - - 0 1"), FenError::NotToMoveInCheck(Colour::White));
+}
+
+#[test]
+fn every_error() {
+    let cases = [
+        ("8/8/8/8 w", FenError::FieldCount(2), "Expected 4 to 6 fields, found 2"),
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra", FenError::FieldCount(7), "Expected 4 to 6 fields, found 7"),
+        ("4k3/8/8/8/8/8/4K3 w - - 0 1", FenError::RankCount(7), "Expected 8 ranks, found 7"),
+        ("4k3/8/8/8/8/8/8/4K2 w - - 0 1", FenError::RankLength(1, String::from("4K2")),
+         "Rank 1 doesn't cover 8 squares, 4K2"),
+        ("4k3/8/8/9/8/8/8/4K3 w - - 0 1", FenError::RankLength(5, String::from("9")),
+         "Rank 5 doesn't cover 8 squares, 9"),
+        ("4k3/8/8/8/8/8/8/4K2X w - - 0 1", FenError::UnknownPiece('X'), "Unknown piece, X"),
+        ("4k3/8/8/8/8/8/8/8 w - - 0 1", FenError::KingCount(Colour::White, 0), "White needs exactly one king, found 0"),
+        ("4k2k/8/8/8/8/8/8/4K3 w - - 0 1", FenError::KingCount(Colour::Black, 2), "Black needs exactly one king, found 2"),
+        ("4k3/8/8/8/8/8/8/P3K3 w - - 0 1", FenError::PawnOnBackRank(String::from("a1")),
+         "Pawn on the first or last rank, a1"),
+        ("4k3/8/8/8/8/8/8/4K3 x - - 0 1", FenError::SideToMove(String::from("x")), "Invalid side to move, x"),
+        ("4k3/8/8/8/8/8/4Q3/4K3 w - - 0 1", FenError::NotToMoveInCheck(Colour::Black),
+         "Black is in check with the other side to move"),
+        ("4k3/8/8/8/8/8/8/K070 w - - 0 1", FenError::RankLength(1, String::from("K070")),
+         "Rank 1 doesn't cover 8 squares, K070"),
+        ("4k3/8/8/8/8/8/8/4K3 w Z - 0 1", FenError::Castling(String::from("Z")), "Invalid castling rights, Z"),
+        ("4k3/8/8/8/8/8/8/4K3 w - e4 0 1", FenError::EnPassant(String::from("e4")), "Invalid en passant square, e4"),
+        ("4k3/8/8/8/8/8/8/4K3 w - - -1 1", FenError::HalfmoveClock(String::from("-1")), "Invalid halfmove clock, -1"),
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 0", FenError::FullmoveNumber(String::from("0")), "Invalid fullmove number, 0"),
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 x", FenError::FullmoveNumber(String::from("x")), "Invalid fullmove number, x"),
+    ];
+    for (fen, expected, message) in cases.iter() {
+        let found = error(fen);
+        assert_eq!(&found, expected, "{}", fen);
+        assert_eq!(found.to_string(), *message);
+    }
+}
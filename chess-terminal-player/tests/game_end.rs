@@ -0,0 +1,136 @@
+extern crate chess_player;
+
+use chess_player::chessboard::{ChessBoard, Colour, GameResult};
+
+fn board(fen: &str) -> ChessBoard {
+    ChessBoard::from_fen(fen).unwrap()
+}
+
+fn play(board: &mut ChessBoard, moves: &[&str]) {
+    for san in moves {
+        board.move_piece(san).unwrap();
+    }
+}
+
+#[test]
+fn checkmate() {
+    let mut fools_mate = ChessBoard::new();
+    play(&mut fools_mate, &["f3", "e5", "g4"]);
+    assert_eq!(fools_mate.result(), None);
+    play(&mut fools_mate, &["Qh4#"]);
+    assert!(fools_mate.is_check());
+    assert!(fools_mate.is_checkmate());
+    assert!(!fools_mate.is_stalemate());
+    assert_eq!(fools_mate.result(), Some(GameResult::Checkmate(Colour::Black)));
+
+    // Check that can be answered isn't mate.
+    let mut back_rank = board("6k1/5ppp/8/8/8/8/8/4R1K1 w - - 0 1");
+    play(&mut back_rank, &["Re8#"]);
+    assert_eq!(back_rank.result(), Some(GameResult::Checkmate(Colour::White)));
+    let escape = board("6k1/5pp1/8/8/8/8/8/4R1K1 w - - 0 1");
+    let mut check = escape.clone();
+    play(&mut check, &["Re8+"]);
+    assert!(check.is_check());
+    assert!(!check.is_checkmate());
+    assert_eq!(check.result(), None);
+}
+
+#[test]
+fn stalemate() {
+    let stalemate = board("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+    assert!(!stalemate.is_check());
+    assert!(stalemate.is_stalemate());
+    assert!(!stalemate.is_checkmate());
+    assert_eq!(stalemate.result(), Some(GameResult::Stalemate));
+    assert_eq!(stalemate.result().unwrap().winner(), None);
+
+    // A king hemmed in by a pawn it can't take.
+    let blocked = board("k7/P7/1K6/8/8/8/8/8 b - - 0 1");
+    assert_eq!(blocked.result(), Some(GameResult::Stalemate));
+    // White is to move here, not black.
+    assert_eq!(board("7k/5Q2/6K1/8/8/8/8/8 w - - 0 1").result(), None);
+}
+
+#[test]
+fn threefold_repetition() {
+    let mut board = ChessBoard::new();
+    play(&mut board, &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"]);
+    assert!(!board.is_threefold_repetition());
+    play(&mut board, &["Ng8"]);
+    assert!(board.is_threefold_repetition());
+    assert_eq!(board.result(), Some(GameResult::ThreefoldRepetition));
+
+    // Walking the kings out and back loses the castling rights, so the start isn't repeated.
+    let mut kings = ChessBoard::new();
+    play(&mut kings, &["e4", "e5"]);
+    for _ in 0..2 {
+        play(&mut kings, &["Ke2", "Ke7", "Ke1", "Ke8"]);
+    }
+    assert!(!kings.is_threefold_repetition());
+    play(&mut kings, &["Ke2", "Ke7", "Ke1", "Ke8"]);
+    assert!(kings.is_threefold_repetition());
+
+    // The same squares with the other side to move don't count.
+    let mut rooks = ChessBoard::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+    play(&mut rooks, &["Ra2", "Kd8", "Ra3", "Ke8", "Ra1"]);
+    assert!(!rooks.is_repetition());
+    play(&mut rooks, &["Kd8", "Ra2", "Ke8", "Ra1"]);
+    assert!(rooks.is_repetition());
+}
+
+#[test]
+fn fifty_move_rule() {
+    let mut quiet = board("4k3/8/8/8/8/8/4P3/R3K3 w - - 98 80");
+    play(&mut quiet, &["Ra2"]);
+    assert!(!quiet.is_fifty_move_rule());
+    assert_eq!(quiet.result(), None);
+    play(&mut quiet, &["Kd8"]);
+    assert!(quiet.is_fifty_move_rule());
+    assert_eq!(quiet.result(), Some(GameResult::FiftyMoveRule));
+
+    // A pawn move starts the count again.
+    let mut pawn = board("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80");
+    play(&mut pawn, &["e4"]);
+    assert!(!pawn.is_fifty_move_rule());
+    assert_eq!(pawn.to_fen(), "4k3/8/8/8/4P3/8/8/R3K3 b - e3 0 80");
+
+    // Mate on the hundredth half move still wins.
+    let mut mate = board("6k1/5ppp/8/8/8/8/8/4R1K1 w - - 99 80");
+    play(&mut mate, &["Re8#"]);
+    assert!(mate.is_fifty_move_rule());
+    assert_eq!(mate.result(), Some(GameResult::Checkmate(Colour::White)));
+}
+
+#[test]
+fn insufficient_material() {
+    let drawn = [
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1",  // Bare kings
+        "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",  // A bishop
+        "4k3/8/8/8/8/8/8/1N2K3 w - - 0 1",  // A knight
+        "2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1",  // Bishops on c8 and f1, both light squares
+        "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",  // Bishops on f8 and c1, both dark squares
+        "4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1",  // Two bishops of one side on dark squares
+    ];
+    for fen in drawn.iter() {
+        let position = board(fen);
+        assert!(position.is_insufficient_material(), "{}", fen);
+        assert_eq!(position.result(), Some(GameResult::InsufficientMaterial), "{}", fen);
+    }
+
+    let playable = [
+        "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1",  // Bishops on opposite colours can still mate
+        "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",  // Bishop and knight
+        "4k3/8/8/8/8/8/8/1N1NK3 w - - 0 1",  // Two knights, mate only with help
+        "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",  // A pawn
+        "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",  // A rook
+    ];
+    for fen in playable.iter() {
+        assert!(!board(fen).is_insufficient_material(), "{}", fen);
+        assert_eq!(board(fen).result(), None, "{}", fen);
+    }
+
+    // Taking the last piece ends the game.
+    let mut capture = board("4k3/8/8/8/8/8/4q3/4K3 w - - 0 1");
+    play(&mut capture, &["Kxe2"]);
+    assert_eq!(capture.result(), Some(GameResult::InsufficientMaterial));
+}
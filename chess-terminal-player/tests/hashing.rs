@@ -0,0 +1,85 @@
+extern crate chess_player;
+
+use chess_player::chessboard::ChessBoard;
+use chess_player::search::{Engine, Limits};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn play(board: &mut ChessBoard, moves: &[&str]) {
+    for san in moves {
+        board.move_piece(san).unwrap();
+    }
+}
+
+#[test]
+fn incremental_hash_matches_a_fresh_one() {
+    // A fixed but varied line of moves, each position checked against the same one read back from FEN.
+    let mut board = ChessBoard::from_fen(KIWIPETE).unwrap();
+    let mut hashes = vec![board.hash()];
+    for ply in 0..200 {
+        let moves = board.legal_moves();
+        if moves.is_empty() {
+            break;
+        }
+        board.make_move(moves[(ply * 7919) % moves.len()]);
+        assert_eq!(board.hash(), ChessBoard::from_fen(&board.to_fen()).unwrap().hash(), "{}", board.to_fen());
+        hashes.push(board.hash());
+    }
+    while board.unmake_move().is_some() {
+        hashes.pop();
+        assert_eq!(Some(&board.hash()), hashes.last());
+    }
+}
+
+#[test]
+fn transpositions_share_a_hash() {
+    let mut one = ChessBoard::new();
+    let mut other = ChessBoard::new();
+    play(&mut one, &["e4", "e6", "d4", "d5"]);
+    play(&mut other, &["d4", "d5", "e4", "e6"]);
+    assert_eq!(one.hash(), other.hash());
+
+    // Same pieces, other side to move.
+    let white = ChessBoard::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let black = ChessBoard::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+    assert_ne!(white.hash(), black.hash());
+}
+
+#[test]
+fn castling_rights_and_en_passant_count() {
+    let all = ChessBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let some = ChessBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1").unwrap();
+    assert_ne!(all.hash(), some.hash());
+
+    // An en passant square only matters when a pawn can take on it.
+    let capturable = ChessBoard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+    let without = ChessBoard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_ne!(capturable.hash(), without.hash());
+    let lone = ChessBoard::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 1").unwrap();
+    let lone_without = ChessBoard::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(lone.hash(), lone_without.hash());
+}
+
+#[test]
+fn repetition_by_hash() {
+    let mut board = ChessBoard::new();
+    play(&mut board, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+    assert!(board.is_repetition());
+    assert!(!board.is_threefold_repetition());
+    play(&mut board, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+    assert!(board.is_threefold_repetition());
+}
+
+#[test]
+fn search_with_table_finds_mate() {
+    let board = ChessBoard::from_fen("6k1/5ppp/8/8/8/8/5PPP/4R1K1 w - - 0 1").unwrap();
+    let mate = board.parse_san("Re8#").unwrap();
+    let mut engine = Engine::new(Limits { depth: Some(5), time: None });
+    let info = engine.search(&board, |_| {});
+    assert_eq!(info.mate_in(), Some(1));
+    assert_eq!(info.best_move(), Some(mate));
+    // A second search starts from what the first left in the table and must agree with it.
+    let again = engine.search(&board, |_| {});
+    assert_eq!(again.mate_in(), Some(1));
+    assert_eq!(again.best_move(), Some(mate));
+}
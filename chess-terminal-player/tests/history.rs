@@ -0,0 +1,128 @@
+extern crate chess_player;
+
+use chess_player::chessboard::{ChessBoard, Colour, Piece, PieceKind};
+
+fn play(board: &mut ChessBoard, moves: &[&str]) {
+    for san in moves {
+        board.move_piece(san).unwrap();
+    }
+}
+
+#[test]
+fn san_history_from_a_set_up_position() {
+    let mut board = ChessBoard::from_fen("4k3/8/8/8/8/8/7p/R3K3 b Q - 0 30").unwrap();
+    play(&mut board, &["Kd7", "O-O-O+", "Ke6", "Rd2", "Kf5", "Rxh2"]);
+    assert_eq!(board.san_history(), vec!["Kd7", "O-O-O+", "Ke6", "Rd2", "Kf5", "Rxh2"]);
+    // Undone moves are no longer part of it.
+    board.undo();
+    assert_eq!(board.san_history().len(), 5);
+}
+
+#[test]
+fn captures_and_material() {
+    let mut board = ChessBoard::new();
+    play(&mut board, &["e4", "d5", "exd5", "Qxd5", "Nc3", "Qa5", "Nb5", "Qxb5", "Bxb5+", "c6"]);
+    assert_eq!(board.captured(Colour::White), vec![
+        Piece::new(PieceKind::Queen, Colour::Black),
+        Piece::new(PieceKind::Pawn, Colour::Black),
+    ]);
+    assert_eq!(board.captured(Colour::Black), vec![
+        Piece::new(PieceKind::Knight, Colour::White),
+        Piece::new(PieceKind::Pawn, Colour::White),
+    ]);
+    assert_eq!(board.material_balance(), 6);
+}
+
+#[test]
+fn material_counts_promotions_and_en_passant() {
+    let mut board = ChessBoard::from_fen("4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+    assert_eq!(board.material_balance(), 1);
+    play(&mut board, &["exd6"]);
+    assert_eq!(board.captured(Colour::White), vec![Piece::new(PieceKind::Pawn, Colour::Black)]);
+    assert_eq!(board.material_balance(), 2);
+    play(&mut board, &["Kd7", "b8=Q"]);
+    assert_eq!(board.material_balance(), 10);
+}
+
+// Making a move and taking it back leaves the position, FEN and hash, exactly as it was.
+fn unmakes_cleanly(fen: &str, san: &str) {
+    let mut board = ChessBoard::from_fen(fen).unwrap();
+    let hash = board.hash();
+    let mv = board.parse_san(san).unwrap();
+    board.make_move(mv);
+    assert_ne!(board.to_fen(), fen, "{}", san);
+    assert_ne!(board.hash(), hash, "{}", san);
+    assert_eq!(board.unmake_move(), Some(mv));
+    assert_eq!(board.to_fen(), fen, "{}", san);
+    assert_eq!(board.hash(), hash, "{}", san);
+    assert!(board.history().is_empty());
+}
+
+#[test]
+fn unmake_restores_the_position() {
+    // Captures, by a piece and by a pawn.
+    unmakes_cleanly("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2", "exd5");
+    unmakes_cleanly("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 5 20", "Rxa8+");
+    // En passant, with the pawn taken off a different square from the one moved to.
+    unmakes_cleanly("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6");
+    unmakes_cleanly("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1", "exd3");
+    // Promotions, quietly and by capture.
+    unmakes_cleanly("3r4/4P1k1/8/8/8/8/8/4K3 w - - 0 1", "e8=Q");
+    unmakes_cleanly("3r4/4P1k1/8/8/8/8/8/4K3 w - - 0 1", "exd8=N");
+    unmakes_cleanly("4k3/8/8/8/8/8/p7/1R2K3 b - - 0 1", "axb1=Q+");
+    // Castling both ways, for both sides, and the rights it gives up.
+    for san in ["O-O", "O-O-O"].iter() {
+        unmakes_cleanly("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", san);
+        unmakes_cleanly("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", san);
+    }
+    // A king move losing both rights, and a rook move losing one.
+    unmakes_cleanly("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "Kf1");
+    unmakes_cleanly("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "Rb1");
+}
+
+#[test]
+fn a_whole_game_unmakes_back_to_the_start() {
+    let mut board = ChessBoard::new();
+    let mut positions = vec![(board.to_fen(), board.hash())];
+    for san in ["e4", "d5", "exd5", "c6", "dxc6", "Nf6", "cxb7", "e5", "bxa8=Q", "Bc5", "Qxb8", "O-O"].iter() {
+        board.move_piece(san).unwrap();
+        positions.push((board.to_fen(), board.hash()));
+    }
+    positions.pop();
+    while let Some((fen, hash)) = positions.pop() {
+        board.unmake_move().unwrap();
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(board.hash(), hash);
+    }
+    assert_eq!(board.unmake_move(), None);
+}
+
+#[test]
+fn undo_and_redo() {
+    let mut board = ChessBoard::new();
+    play(&mut board, &["e4", "e5", "Nf3"]);
+    let after = board.to_fen();
+    assert!(board.undo().is_some());
+    assert!(board.undo().is_some());
+    assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    // Moves come back in the order they were played.
+    let e5 = board.parse_san("e5").unwrap();
+    assert_eq!(board.redo(), Some(e5));
+    assert!(board.redo().is_some());
+    assert_eq!(board.to_fen(), after);
+    assert_eq!(board.redo(), None);
+
+    // Playing the undone move again by hand keeps the rest to redo.
+    board.undo();
+    board.undo();
+    play(&mut board, &["e5"]);
+    assert!(board.redo().is_some());
+    assert_eq!(board.to_fen(), after);
+
+    // Any other move drops them.
+    board.undo();
+    board.undo();
+    play(&mut board, &["c5"]);
+    assert_eq!(board.redo(), None);
+    assert_eq!(board.san_history(), vec!["e4", "c5"]);
+}
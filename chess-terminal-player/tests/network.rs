@@ -0,0 +1,234 @@
+extern crate chess_player;
+
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use chess_player::chessboard::{ChessBoard, Colour, GameResult};
+use chess_player::network::{replay, Message, Peer, PeerEvent, PROTOCOL_VERSION};
+use chess_player::session::Session;
+
+fn next(events: &Receiver<PeerEvent>) -> PeerEvent {
+    events.recv_timeout(Duration::from_secs(5)).expect("no event from the peer")
+}
+
+#[test]
+fn messages_read_and_write() {
+    let lines = [
+        "hello 1 black",
+        "hello 1",
+        "move Nf3",
+        "offer-draw",
+        "resign",
+        "sync",
+        "sync rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 moves e4 e5",
+        "sync rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 moves e4 resigned black",
+        "sync rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 drawn",
+    ];
+    for line in lines.iter() {
+        assert_eq!(line.parse::<Message>().unwrap().to_string(), *line);
+    }
+    assert_eq!("hello 1 black".parse(), Ok(Message::Hello { version: 1, colour: Some(Colour::Black) }));
+    let resigned = "sync 4k3/8/8/8/8/8/8/4K3 w - - 0 1 moves Kd2 resigned black".parse::<Message>().unwrap();
+    assert!(matches!(resigned, Message::Sync { ended: Some(GameResult::Resignation(Colour::White)), .. }));
+    let bad = ["", "hello", "hello one", "hello 1 red", "move", "move e4 e5", "sync moves e4", "sync drawn",
+               "sync 4k3/8/8/8/8/8/8/4K3 w - - 0 1 resigned red", "checkmate"];
+    for line in bad.iter() {
+        assert!(line.parse::<Message>().is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn sync_brings_back_the_whole_game() {
+    let mut board = ChessBoard::new();
+    for san in ["e4", "e5", "Nf3", "Nc6"].iter() {
+        board.move_piece(san).unwrap();
+    }
+    let (fen, moves) = match Message::sync(&board, Some(GameResult::Timeout(Colour::White))) {
+        // A flag fall is left for the other side's own clock.
+        Message::Sync { fen, moves, ended: None } => (fen, moves),
+        other => panic!("{:?}", other),
+    };
+    let copy = replay(&fen, &moves).unwrap();
+    assert_eq!(copy.to_fen(), board.to_fen());
+    assert_eq!(copy.san_history(), board.san_history());
+    // Every move is checked on the way.
+    assert!(replay(&fen, &[String::from("e4"), String::from("e4")]).is_err());
+}
+
+#[test]
+fn peers_talk_over_localhost_and_reconnect() {
+    let (to_host, host_events) = mpsc::channel();
+    let host = Peer::host("127.0.0.1:0", move |event| { let _ = to_host.send(event); }).unwrap();
+    let (to_joiner, joiner_events) = mpsc::channel();
+    let joiner = Peer::join(host.local_addr(), move |event| { let _ = to_joiner.send(event); }).unwrap();
+    assert_eq!(next(&host_events), PeerEvent::Connected);
+    assert_eq!(next(&joiner_events), PeerEvent::Connected);
+
+    joiner.send(&Message::Move(String::from("e4"))).unwrap();
+    assert_eq!(next(&host_events), PeerEvent::Received(Message::Move(String::from("e4"))));
+    host.send(&Message::OfferDraw).unwrap();
+    assert_eq!(next(&joiner_events), PeerEvent::Received(Message::OfferDraw));
+
+    // The joiner dials again after the connection drops, and the two carry on.
+    host.disconnect();
+    assert_eq!(next(&host_events), PeerEvent::Disconnected);
+    assert_eq!(next(&joiner_events), PeerEvent::Disconnected);
+    assert!(joiner.send(&Message::Resign).is_err());
+    assert_eq!(next(&host_events), PeerEvent::Connected);
+    assert_eq!(next(&joiner_events), PeerEvent::Connected);
+    joiner.send(&Message::SyncRequest).unwrap();
+    assert_eq!(next(&host_events), PeerEvent::Received(Message::SyncRequest));
+
+    // Closing for good leaves the host waiting for someone new.
+    joiner.close();
+    assert_eq!(next(&host_events), PeerEvent::Disconnected);
+    assert!(!host.is_connected());
+}
+
+// The other player's end of a session's connection, a plain socket as `nc` would be.
+struct Other {
+    reader: BufReader<TcpStream>,
+    partial: String,
+}
+
+impl Other {
+    fn new(stream: TcpStream) -> Other {
+        stream.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        Other { reader: BufReader::new(stream), partial: String::new() }
+    }
+
+    // The next line the session sent, if one comes within the read timeout.
+    fn try_line(&mut self) -> Option<String> {
+        match self.reader.read_line(&mut self.partial) {
+            Ok(n) if n > 0 && self.partial.ends_with('\n') => Some(std::mem::take(&mut self.partial).trim().to_string()),
+            Ok(_) => None,
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => None,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn line(&mut self) -> String {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            if let Some(line) = self.try_line() {
+                return line;
+            }
+        }
+        panic!("nothing from the session");
+    }
+
+    // Tells the session it is connected until its greeting comes through, the session's peer taking the
+    // connection on its own thread.
+    fn greeted_by(&mut self, session: &mut Session) -> String {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            session.receive(PeerEvent::Connected);
+            if let Some(line) = self.try_line() {
+                return line;
+            }
+        }
+        panic!("no greeting from the session");
+    }
+}
+
+fn received(message: &str) -> PeerEvent {
+    PeerEvent::Received(message.parse().unwrap())
+}
+
+// A session hosting as white, and the joiner's end of it.
+fn hosting() -> (Session, Other) {
+    let mut host = Session::new(ChessBoard::new());
+    let addr = host.host("127.0.0.1:0", Colour::White).unwrap();
+    let mut other = Other::new(TcpStream::connect(addr).unwrap());
+    assert_eq!(other.greeted_by(&mut host), format!("hello {} black", PROTOCOL_VERSION));
+    assert_eq!(other.line(), format!("sync {}", ChessBoard::new().to_fen()));
+    (host, other)
+}
+
+#[test]
+fn host_turns_down_illegal_and_out_of_turn_moves() {
+    let (mut host, mut other) = hosting();
+    // Black moving first, or playing white's move, gets the game sent back.
+    host.receive(received("move e5"));
+    assert!(host.board().history().is_empty());
+    assert_eq!(other.line(), format!("sync {}", ChessBoard::new().to_fen()));
+
+    host.handle("e4");
+    assert_eq!(other.line(), "move e4");
+    for illegal in ["move e4", "move Ke7", "move Qh4"].iter() {
+        host.receive(received(illegal));
+        assert_eq!(other.line(), format!("sync {} moves e4", ChessBoard::new().to_fen()), "{}", illegal);
+    }
+    assert_eq!(host.board().san_history(), vec!["e4"]);
+
+    host.receive(received("move e5"));
+    assert_eq!(host.board().san_history(), vec!["e4", "e5"]);
+    // White's moves are made at this end only.
+    host.receive(received("move Nf3"));
+    assert_eq!(host.board().san_history(), vec!["e4", "e5"]);
+    assert_eq!(other.line(), format!("sync {} moves e4 e5", ChessBoard::new().to_fen()));
+}
+
+#[test]
+fn host_game_wins_over_the_joiners() {
+    let (mut host, mut other) = hosting();
+    host.handle("d4");
+    assert_eq!(other.line(), "move d4");
+    host.receive(received(&format!("sync {} moves e4 e5", ChessBoard::new().to_fen())));
+    assert_eq!(host.board().san_history(), vec!["d4"]);
+    assert_eq!(other.line(), format!("sync {} moves d4", ChessBoard::new().to_fen()));
+    host.receive(received("sync"));
+    assert_eq!(other.line(), format!("sync {} moves d4", ChessBoard::new().to_fen()));
+}
+
+#[test]
+fn resigning_needs_the_other_player_there() {
+    let mut host = Session::new(ChessBoard::new());
+    let addr = host.host("127.0.0.1:0", Colour::White).unwrap();
+    // Nobody has joined yet, so nobody would hear it.
+    host.handle("resign");
+    assert_eq!(host.result(), None);
+
+    let mut other = Other::new(TcpStream::connect(addr).unwrap());
+    other.greeted_by(&mut host);
+    other.line();
+    host.handle("resign");
+    assert_eq!(other.line(), "resign");
+    assert_eq!(host.result(), Some(GameResult::Resignation(Colour::Black)));
+    // Coming back later, the joiner learns how the game ended.
+    host.receive(received("sync"));
+    assert_eq!(other.line(), format!("sync {} resigned white", ChessBoard::new().to_fen()));
+}
+
+#[test]
+fn joiner_takes_up_the_hosts_game() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut joiner = Session::new(ChessBoard::new());
+    joiner.join(&listener.local_addr().unwrap().to_string()).unwrap();
+    let mut other = Other::new(listener.accept().unwrap().0);
+    assert_eq!(other.greeted_by(&mut joiner), format!("hello {}", PROTOCOL_VERSION));
+    joiner.receive(received(&format!("hello {} black", PROTOCOL_VERSION)));
+
+    let start = ChessBoard::new().to_fen();
+    joiner.receive(received(&format!("sync {} moves e4 e5 Nf3", start)));
+    assert_eq!(joiner.board().san_history(), vec!["e4", "e5", "Nf3"]);
+    // Moving for the host's side is refused, and asks for the game again.
+    joiner.receive(received("move Nc6"));
+    assert_eq!(joiner.board().san_history(), vec!["e4", "e5", "Nf3"]);
+    assert_eq!(other.line(), "sync");
+
+    // The host's game replaces this one, moves, setup and ending alike.
+    joiner.handle("Nc6");
+    assert_eq!(other.line(), "move Nc6");
+    let set_up = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+    joiner.receive(received(&format!("sync {} moves e4 resigned black", set_up)));
+    assert_eq!(joiner.board().to_fen(), "4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+    assert_eq!(joiner.result(), Some(GameResult::Resignation(Colour::White)));
+    joiner.receive(received(&format!("sync {} moves e4 e5", start)));
+    assert_eq!(joiner.result(), None);
+    // A game that can't be followed leaves this one as it was.
+    joiner.receive(received(&format!("sync {} moves e4 e4", start)));
+    assert_eq!(joiner.board().san_history(), vec!["e4", "e5"]);
+}
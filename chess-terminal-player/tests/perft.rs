@@ -0,0 +1,81 @@
+extern crate chess_player;
+
+use chess_player::chessboard::{ChessBoard, STARTING_FEN};
+
+// Reference positions and node counts from https://www.chessprogramming.org/Perft_Results
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_4_MIRRORED: &str = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+fn check(fen: &str, counts: &[u64]) {
+    let board = ChessBoard::from_fen(fen).unwrap();
+    for (depth, expected) in counts.iter().enumerate() {
+        assert_eq!(board.perft(depth as u32 + 1), *expected, "perft({}) of {}", depth + 1, fen);
+    }
+}
+
+#[test]
+fn initial_position() {
+    check(STARTING_FEN, &[20, 400, 8902, 197_281]);
+}
+
+#[test]
+fn kiwipete() {
+    check(KIWIPETE, &[48, 2039, 97_862]);
+}
+
+#[test]
+fn position_3() {
+    check(POSITION_3, &[14, 191, 2812, 43_238, 674_624]);
+}
+
+#[test]
+fn position_4() {
+    check(POSITION_4, &[6, 264, 9467, 422_333]);
+}
+
+#[test]
+fn position_4_mirrored() {
+    check(POSITION_4_MIRRORED, &[6, 264, 9467, 422_333]);
+}
+
+#[test]
+fn position_5() {
+    check(POSITION_5, &[44, 1486, 62_379]);
+}
+
+#[test]
+fn position_6() {
+    check(POSITION_6, &[46, 2079, 89_890]);
+}
+
+// Deeper counts are slow without optimisations, run them with `cargo test --release -- --ignored`.
+#[test]
+#[ignore]
+fn deep() {
+    check(STARTING_FEN, &[20, 400, 8902, 197_281, 4_865_609]);
+    check(KIWIPETE, &[48, 2039, 97_862, 4_085_603]);
+    check(POSITION_5, &[44, 1486, 62_379, 2_103_487]);
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+    let board = ChessBoard::from_fen(KIWIPETE).unwrap();
+    let counts = board.divide(3);
+    assert_eq!(counts.len(), 48);
+    assert_eq!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>(), board.perft(3));
+
+    let castle = board.parse_san("O-O").unwrap();
+    assert_eq!(counts.iter().find(|(mv, _)| *mv == castle).map(|(_, nodes)| *nodes), Some(2059));
+}
+
+#[test]
+fn perft_leaves_the_board_as_it_was() {
+    let board = ChessBoard::from_fen(POSITION_4).unwrap();
+    board.perft(3);
+    assert_eq!(board.to_fen(), POSITION_4);
+    assert_eq!(board.legal_moves().len(), 6);
+}
@@ -0,0 +1,174 @@
+extern crate chess_player;
+
+use std::io::Cursor;
+
+use chess_player::chessboard::ChessBoard;
+use chess_player::pgn::{Game, PgnError, PgnReader};
+
+// The movetext of a game as written, without the tag section.
+fn movetext(game: &Game) -> String {
+    let text = game.to_string();
+    let start = text.find("\n\n").unwrap() + 2;
+    text[start..].trim_end().to_string()
+}
+
+#[test]
+fn tag_values_are_escaped() {
+    let mut game = Game::new();
+    game.set_tag("Event", "The \"Immortal\" Game");
+    game.set_tag("Site", "C:\\Games");
+    let text = game.to_string();
+    assert!(text.contains("[Event \"The \\\"Immortal\\\" Game\"]\n"), "{}", text);
+    assert!(text.contains("[Site \"C:\\\\Games\"]\n"), "{}", text);
+
+    let read = Game::parse(&text).unwrap();
+    assert_eq!(read.tag("Event"), Some("The \"Immortal\" Game"));
+    assert_eq!(read.tag("Site"), Some("C:\\Games"));
+    // The seven tag roster comes first, in order, whatever order the tags were set in.
+    let names: Vec<&str> = read.tags.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["Event", "Site", "Date", "Round", "White", "Black", "Result"]);
+
+    assert!(matches!(Game::parse("[Event \"Unclosed\"\n\n1. e4 *"), Err(PgnError::Tag(_))));
+    assert!(matches!(Game::parse("[Event Unquoted]\n\n1. e4 *"), Err(PgnError::Tag(_))));
+}
+
+#[test]
+fn comments() {
+    let game = Game::parse("{Before the game} 1. e4 {The king's pawn} {and more} e5 ; to the end of the line\n\
+                            2. Nf3 {Spread\n   over lines} *").unwrap();
+    assert_eq!(game.moves[0].comment_before.as_deref(), Some("Before the game"));
+    assert_eq!(game.moves[0].comment.as_deref(), Some("The king's pawn and more"));
+    assert_eq!(game.moves[1].comment.as_deref(), Some("to the end of the line"));
+    assert_eq!(game.moves[2].comment.as_deref(), Some("Spread over lines"));
+    // Black's move is numbered again after a comment.
+    assert_eq!(movetext(&game), "{Before the game} 1. e4 {The king's pawn and more} 1... e5\n\
+                                 {to the end of the line} 2. Nf3 {Spread over lines} *");
+
+    assert!(matches!(Game::parse("1. e4 {never closed *"), Err(PgnError::UnclosedComment)));
+    assert!(matches!(Game::parse("1. e4 } *"), Err(PgnError::UnexpectedCharacter('}'))));
+}
+
+#[test]
+fn nags() {
+    let game = Game::parse("1. e4! e5?? 2. Nf3!? $14 Nc6 $36 $18 3. Bb5?! a6? 4. Ba4!! *").unwrap();
+    let nags: Vec<&[u8]> = game.moves.iter().map(|m| m.nags.as_slice()).collect();
+    assert_eq!(nags, [&[1][..], &[4], &[5, 14], &[36, 18], &[6], &[2], &[3]]);
+    // The suffixes come off the moves, so they still replay.
+    assert_eq!(game.moves[0].san, "e4");
+    assert!(game.replay().is_ok());
+    assert_eq!(movetext(&game), "1. e4 $1 e5 $4 2. Nf3 $5 $14 Nc6 $36 $18 3. Bb5 $6 a6 $2 4. Ba4 $3 *");
+    assert!(matches!(Game::parse("1. e4 $x *"), Err(PgnError::UnexpectedCharacter('$'))));
+}
+
+#[test]
+fn nested_variations() {
+    let game = Game::parse("1. e4 e5 (1... c5 2. Nf3 (2. Nc3 Nc6) d6) (1... e6) 2. Nf3 *").unwrap();
+    assert_eq!(game.moves.len(), 3);
+    let variations = &game.moves[1].variations;
+    assert_eq!(variations.len(), 2);
+    let sicilian: Vec<&str> = variations[0].iter().map(|m| m.san.as_str()).collect();
+    assert_eq!(sicilian, ["c5", "Nf3", "d6"]);
+    let closed: Vec<&str> = variations[0][1].variations[0].iter().map(|m| m.san.as_str()).collect();
+    assert_eq!(closed, ["Nc3", "Nc6"]);
+    assert_eq!(variations[1][0].san, "e6");
+    assert_eq!(movetext(&game), "1. e4 e5 (1... c5 2. Nf3 (2. Nc3 Nc6) 2... d6) (1... e6) 2. Nf3 *");
+
+    for text in ["1. e4 (1. d4 *", "1. e4 e5) *", "(1. e4) *"].iter() {
+        assert!(matches!(Game::parse(text), Err(PgnError::UnbalancedVariation)), "{}", text);
+    }
+}
+
+#[test]
+fn movetext_wraps_at_79_columns() {
+    let mut board = ChessBoard::new();
+    // A Ruy Lopez long enough to need several lines.
+    let moves = "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O h3 Nb8 d4 Nbd7 c4 c6 cxb5 axb5 \
+                 Nc3 Bb7 Bg5 b4 Nb1 h6 Bh4 c5 dxe5 Nxe4 Bxe7 Qxe7 exd6 Qf6 Nbd2 Nxd6 Nc4 Nxc4 Bxc4 Nb6";
+    for san in moves.split_whitespace() {
+        board.move_piece(san).unwrap();
+    }
+    let game = Game::from_board(&board);
+    let text = movetext(&game);
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines.len() > 2);
+    for (i, line) in lines.iter().enumerate() {
+        assert!(line.len() <= 79, "{}", line);
+        assert!(!line.starts_with(' ') && !line.ends_with(' '));
+        // Every line but the last is as full as it can be.
+        if let Some(next) = lines.get(i + 1) {
+            let next_token = next.split(' ').next().unwrap();
+            assert!(line.len() + 1 + next_token.len() > 79, "{}", line);
+        }
+    }
+    assert_eq!(Game::parse(&game.to_string()).unwrap().replay().unwrap().to_fen(), board.to_fen());
+}
+
+#[test]
+fn parse_write_parse_round_trip() {
+    let text = "[Event \"Casual \\\"blitz\\\"\"]\n\
+                [Site \"?\"]\n\
+                [Date \"2024.01.02\"]\n\
+                [Round \"1\"]\n\
+                [White \"Anderssen\"]\n\
+                [Black \"Kieseritzky\"]\n\
+                [Result \"1-0\"]\n\
+                [ECO \"C33\"]\n\
+                \n\
+                {King's Gambit} 1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5 $5 (4... Nf6 5. Nf3 (5. Nc3) Qh5) \
+                5. Bxb5 Nf6 6. Nf3 {[%clk 0:04:52]} Qh6 7. d3 Nh5 8. Nh4 Qg5 9. Nf5 c6 10. g4 Nf6 11. Rg1! \
+                cxb5 12. h4 Qg6 13. h5 Qg5 14. Qf3 Ng8 15. Bxf4 Qf6 16. Nc3 Bc5 17. Nd5 Qxb2 18. Bd6 Bxg1 \
+                19. e5 Qxa1+ 20. Ke2 Na6 21. Nxg7+ Kd8 22. Qf6+ Nxf6 23. Be7# 1-0\n";
+    let game = Game::parse(text).unwrap();
+    assert_eq!(game.result, "1-0");
+    assert_eq!(game.moves.len(), 45);
+    assert!(game.replay().unwrap().is_checkmate());
+
+    let written = game.to_string();
+    let read = Game::parse(&written).unwrap();
+    assert_eq!(read, game);
+    assert_eq!(read.to_string(), written);
+
+    // Several games in one file, read in turn.
+    let file = format!("{}\n{}", written, Game::from_board(&ChessBoard::new()));
+    let games: Vec<Game> = PgnReader::new(Cursor::new(file)).map(|game| game.unwrap()).collect();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0], game);
+    assert!(games[1].moves.is_empty());
+}
+
+// A brace in a tag value or a `;` comment doesn't open a comment that swallows the next game.
+#[test]
+fn braces_outside_comments_keep_games_apart() {
+    let file = "[Event \"First\"]\n\
+                [Annotator \"a{b\"]\n\
+                \n\
+                1. e4 e5 ; {\n\
+                2. Nf3 *\n\
+                \n\
+                [Event \"Second\"]\n\
+                \n\
+                1. d4 *\n";
+    let games: Vec<Game> = PgnReader::new(Cursor::new(file)).map(|game| game.unwrap()).collect();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].tag("Annotator"), Some("a{b"));
+    assert_eq!(games[0].moves.len(), 3);
+    assert_eq!(games[1].tag("Event"), Some("Second"));
+    assert_eq!(games[1].moves.len(), 1);
+}
+
+#[test]
+fn games_from_a_position_keep_their_numbering() {
+    let game = Game::parse("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 40\"]\n\n40... Kd7 41. e4 *").unwrap();
+    assert_eq!(movetext(&game), "40... Kd7 41. e4 *");
+    assert!(game.replay().is_ok());
+}
+
+#[test]
+fn a_bad_fen_tag_still_writes() {
+    let game = Game::parse("[FEN \"8/8/8 w - - 0 1\"]\n\n1. e4 *").unwrap();
+    assert!(matches!(game.replay(), Err(PgnError::Fen(_))));
+    // Written back as read, the moves numbered from the start.
+    let text = game.to_string();
+    assert!(text.contains("[FEN \"8/8/8 w - - 0 1\"]\n"));
+    assert_eq!(movetext(&game), "1. e4 *");
+}
@@ -0,0 +1,150 @@
+extern crate chess_player;
+
+use std::env;
+use std::fs;
+
+use chess_player::chessboard::{ChessBoard, Colour};
+use chess_player::epd::Epd;
+use chess_player::puzzle::{parse_csv, parse_epd, Attempt, PuzzleStats, Trainer};
+
+const SCHOLARS_MATE: &str = "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+// The example the Lichess puzzle database is documented with.
+const LICHESS: &str = "PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags
+00sHx,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760,80,83,72,mate mateIn2 middlegame short,https://lichess.org/yyznGmXs/black#34,Italian_Game Italian_Game_Classical_Variation
+";
+
+fn sans(board: &ChessBoard, moves: &[chess_player::chessboard::Move]) -> Vec<String> {
+    let mut board = board.clone();
+    moves.iter().map(|mv| {
+        let san = board.san(mv);
+        board.make_move(*mv);
+        san
+    }).collect()
+}
+
+#[test]
+fn epd_operations() {
+    let epd = Epd::parse("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001 mate\"; c0 \"a;b\";")
+        .unwrap();
+    assert_eq!(epd.id(), Some("WAC.001 mate"));
+    assert_eq!(epd.operands("c0"), Some(&[String::from("a;b")][..]));
+    assert_eq!(epd.operands("am"), None);
+    let best = epd.moves("bm").unwrap().unwrap();
+    assert_eq!(sans(&epd.board, &best), ["Qg6"]);
+
+    // Move clocks before the operations, several moves, and no last semicolon.
+    let epd = Epd::parse("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1 am O-O O-O-O; pv Kf1 Kd7 Rh7+").unwrap();
+    assert_eq!(sans(&epd.board, &epd.moves("am").unwrap().unwrap()), ["O-O", "O-O-O"]);
+    assert_eq!(sans(&epd.board, &epd.line("pv").unwrap().unwrap()), ["Kf1", "Kd7", "Rh7+"]);
+
+    assert!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - bm Ke9;").unwrap().moves("bm").is_err());
+    assert!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - id \"open;").is_err());
+    assert!(Epd::parse("4k3/8/8/8 w - - bm Kd1;").is_err());
+}
+
+#[test]
+fn puzzle_files() {
+    let puzzles = parse_csv(&format!("# A comment\n\n{},Qxf7#,600", SCHOLARS_MATE)).unwrap();
+    assert_eq!((puzzles[0].id.as_str(), puzzles[0].rating), ("3", Some(600)));
+    assert!(parse_csv(&format!("{},Qxf7#\n{},Bc5 Qxf7#", SCHOLARS_MATE, SCHOLARS_MATE)).is_err());
+
+    // The move in the Lichess file is the opponent's, the puzzle starts after it.
+    let puzzles = parse_csv(LICHESS).unwrap();
+    let puzzle = &puzzles[0];
+    assert_eq!(puzzle.id, "00sHx");
+    assert_eq!(puzzle.rating, Some(1760));
+    assert_eq!(puzzle.solver(), Colour::White);
+    assert_eq!(puzzle.board.san_history(), ["Kd7"]);
+    assert_eq!(sans(&puzzle.board, &puzzle.solution), ["Be6+", "Kd8", "Qf8#"]);
+
+    let puzzles = parse_epd("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";\n\
+                             6k1/5ppp/8/8/8/8/8/R5K1 w - - pv Ra8#;").unwrap();
+    assert_eq!(puzzles.len(), 2);
+    assert_eq!(puzzles[0].id, "WAC.001");
+    assert_eq!(puzzles[1].id, "2");
+    assert_eq!(sans(&puzzles[1].board, &puzzles[1].solution), ["Ra8#"]);
+    let error = parse_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - id \"nothing\";").err().unwrap();
+    assert_eq!(error.to_string(), "Invalid puzzle on line 1, no pv or bm operation");
+}
+
+#[test]
+fn trainer_checks_moves_and_plays_replies() {
+    let puzzles = parse_csv(LICHESS).unwrap();
+    let mut trainer = Trainer::new(puzzles, PuzzleStats::default()).unwrap();
+    let mut board = trainer.puzzle().board.clone();
+
+    let wrong = board.parse_san("Qxg7").unwrap();
+    let rating = match trainer.attempt(&board, wrong) {
+        Attempt::Wrong(Some(change)) => change,
+        other => panic!("{:?}", other),
+    };
+    assert!(rating < 0);
+    assert_eq!(trainer.stats.failed, 1);
+
+    // Only the first miss counts.
+    let right = board.parse_san("Be6+").unwrap();
+    let reply = match trainer.attempt(&board, right) {
+        Attempt::Correct(reply) => reply,
+        other => panic!("{:?}", other),
+    };
+    board.make_move(right);
+    assert_eq!(board.san(&reply), "Kd8");
+    board.make_move(reply);
+    let mate = board.parse_san("Qf8#").unwrap();
+    assert_eq!(trainer.attempt(&board, mate), Attempt::Solved(None));
+    assert!(trainer.finished());
+    assert_eq!((trainer.stats.solved, trainer.stats.failed, trainer.stats.streak), (0, 1, 0));
+    assert_eq!(trainer.stats.rating, 1500 + rating);
+}
+
+#[test]
+fn trainer_rates_the_solver() {
+    // Each puzzle once, starting with the one nearest the rating, and any mate will do.
+    let text = format!("{},Qxf7#,1500\n{},Qxf7#,2400\n{},Ra8#,900",
+                       SCHOLARS_MATE, SCHOLARS_MATE, "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let mut trainer = Trainer::new(parse_csv(&text).unwrap(), PuzzleStats::default()).unwrap();
+    assert_eq!(trainer.puzzle().id, "1");
+    let board = trainer.puzzle().board.clone();
+    assert_eq!(trainer.attempt(&board, board.parse_san("Qxf7#").unwrap()), Attempt::Solved(Some(16)));
+    assert_eq!(trainer.next_puzzle().id, "3");
+    let (mv, change) = trainer.hint().unwrap();
+    assert_eq!(trainer.puzzle().board.san(&mv), "Ra8#");
+    assert_eq!(change, Some(-31));
+    assert_eq!(trainer.next_puzzle().id, "2");
+    // Skipping a puzzle fails it, though hardly costs anything against one rated so far above.
+    assert_eq!(trainer.next_puzzle().id, "1");
+
+    let stats = &trainer.stats;
+    assert_eq!((stats.rating, stats.streak, stats.best_streak, stats.solved, stats.failed), (1485, 0, 1, 1, 2));
+    assert!(Trainer::new(Vec::new(), PuzzleStats::default()).is_none());
+}
+
+#[test]
+fn skipping_a_puzzle_fails_it_once() {
+    let text = format!("{},Qxf7#,1500\n{},Qxf7#,1500", SCHOLARS_MATE, SCHOLARS_MATE);
+    let mut trainer = Trainer::new(parse_csv(&text).unwrap(), PuzzleStats::default()).unwrap();
+    trainer.next_puzzle();
+    assert_eq!((trainer.stats.rating, trainer.stats.failed), (1484, 1));
+    // A wrong move already cost the rating, moving on doesn't take more.
+    let board = trainer.puzzle().board.clone();
+    assert_eq!(trainer.attempt(&board, board.parse_san("Qh6").unwrap()), Attempt::Wrong(Some(-15)));
+    trainer.next_puzzle();
+    assert_eq!((trainer.stats.rating, trainer.stats.failed), (1469, 2));
+}
+
+#[test]
+fn stats_are_kept_between_sessions() {
+    let path = env::temp_dir().join(format!("chess-player-puzzle-stats-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    assert_eq!(PuzzleStats::load(&path).unwrap(), PuzzleStats::default());
+    let mut stats = PuzzleStats::default();
+    stats.record(Some(1800), true);
+    stats.record(Some(1200), true);
+    stats.save(&path).unwrap();
+    assert_eq!(PuzzleStats::load(&path).unwrap(), stats);
+    assert_eq!(stats.best_streak, 2);
+
+    fs::write(&path, "rating lots\n").unwrap();
+    assert!(PuzzleStats::load(&path).is_err());
+    fs::remove_file(&path).unwrap();
+}
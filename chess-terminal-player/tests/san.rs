@@ -0,0 +1,120 @@
+extern crate chess_player;
+
+use chess_player::chessboard::{ChessBoard, Colour, PieceKind, SanError};
+
+fn board(fen: &str) -> ChessBoard {
+    ChessBoard::from_fen(fen).unwrap()
+}
+
+// The squares of the move written, as (row, col) pairs.
+fn squares(board: &ChessBoard, san: &str) -> ((usize, usize), (usize, usize)) {
+    let mv = board.parse_san(san).unwrap();
+    (mv.from, mv.to)
+}
+
+#[test]
+fn disambiguation() {
+    // Knights on b1 and f1 can both go to d2.
+    let knights = board("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1");
+    assert_eq!(knights.parse_san("Nd2"), Err(SanError::Ambiguous(String::from("Nd2"))));
+    assert_eq!(squares(&knights, "Nbd2"), ((0, 1), (1, 3)));
+    assert_eq!(squares(&knights, "Nfd2"), ((0, 5), (1, 3)));
+
+    // Rooks on a1 and a5, told apart by rank.
+    let rooks = board("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1");
+    assert!(rooks.parse_san("Raa3").is_err());
+    assert_eq!(squares(&rooks, "R1a3"), ((0, 0), (2, 0)));
+    assert_eq!(squares(&rooks, "R5a3"), ((4, 0), (2, 0)));
+
+    // Queens on e4, h4 and h1 all reach e1, the one on h4 needs both file and rank.
+    let queens = board("2k5/8/8/8/4Q2Q/8/8/K6Q w - - 0 1");
+    assert!(matches!(queens.parse_san("Qhe1"), Err(SanError::Ambiguous(_))));
+    assert!(matches!(queens.parse_san("Q4e1"), Err(SanError::Ambiguous(_))));
+    assert_eq!(squares(&queens, "Qee1"), ((3, 4), (0, 4)));
+    assert_eq!(squares(&queens, "Qh4e1"), ((3, 7), (0, 4)));
+    let mv = queens.parse_san("Qh4e1").unwrap();
+    assert_eq!(queens.san(&mv), "Qh4e1");
+}
+
+#[test]
+fn promotion() {
+    let position = board("3r4/4P1k1/8/8/8/8/8/4K3 w - - 0 1");
+    for (san, piece) in [("e8=Q", PieceKind::Queen), ("e8Q", PieceKind::Queen), ("exd8=N", PieceKind::Knight),
+                         ("exd8R", PieceKind::Rook), ("e8=B+", PieceKind::Bishop)].iter() {
+        assert_eq!(position.parse_san(san).unwrap().promotion, Some(*piece), "{}", san);
+    }
+    assert_eq!(position.parse_san("e8"), Err(SanError::MissingPromotion(String::from("e8"))));
+    assert_eq!(position.parse_san("exd8"), Err(SanError::MissingPromotion(String::from("exd8"))));
+    // Only pawns promote, and not to kings.
+    assert_eq!(position.parse_san("Kd2=Q"), Err(SanError::Malformed(String::from("Kd2=Q"))));
+    assert!(matches!(position.parse_san("e8=K"), Err(SanError::Malformed(_))));
+}
+
+#[test]
+fn castling_with_letters_or_zeros() {
+    let position = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert_eq!(squares(&position, "O-O"), ((0, 4), (0, 6)));
+    assert_eq!(squares(&position, "0-0"), ((0, 4), (0, 6)));
+    assert_eq!(squares(&position, "O-O-O"), ((0, 4), (0, 2)));
+    assert_eq!(squares(&position, "0-0-0"), ((0, 4), (0, 2)));
+    let no_rights = board("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1");
+    assert_eq!(no_rights.parse_san("O-O"), Err(SanError::IllegalMove(String::from("O-O"))));
+}
+
+#[test]
+fn captures_suffixes_and_annotations() {
+    let mut position = ChessBoard::new();
+    position.move_piece("e4").unwrap();
+    position.move_piece("d5").unwrap();
+    assert_eq!(squares(&position, "exd5"), ((3, 4), (4, 3)));
+    assert_eq!(squares(&position, "e:d5"), ((3, 4), (4, 3)));
+    assert_eq!(squares(&position, "exd5!?"), ((3, 4), (4, 3)));
+    // A capture sign can be left off, but not added where nothing is taken.
+    assert_eq!(squares(&position, "ed5"), ((3, 4), (4, 3)));
+    assert!(position.parse_san("Nxf3").is_err());
+
+    let mate = board("r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4");
+    for san in ["Qxf7#", "Qxf7+", "Qxf7", "Q:f7", "Qxf7#!!", "Qxf7++"].iter() {
+        assert_eq!(squares(&mate, san), ((4, 7), (6, 5)), "{}", san);
+    }
+}
+
+#[test]
+fn errors() {
+    let start = ChessBoard::new();
+    assert_eq!(start.parse_san(""), Err(SanError::Empty));
+    assert_eq!(start.parse_san("  "), Err(SanError::Empty));
+    assert_eq!(start.parse_san("Zf3"), Err(SanError::Malformed(String::from("Zf3"))));
+    assert_eq!(start.parse_san("e9"), Err(SanError::Malformed(String::from("e9"))));
+    assert_eq!(start.parse_san("e5"), Err(SanError::WrongSide(Colour::White, String::from("e5"))));
+    assert_eq!(start.parse_san("Ke2"), Err(SanError::IllegalMove(String::from("Ke2"))));
+    // No pawn can go to d5, and the one that can take there needs its file written.
+    let capture = board("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1");
+    assert_eq!(capture.parse_san("d5"), Err(SanError::IllegalMove(String::from("d5"))));
+
+    assert_eq!(SanError::Empty.to_string(), "No move given");
+    assert_eq!(SanError::Malformed(String::from("Zf3")).to_string(), "Can't read move, Zf3");
+    assert_eq!(SanError::IllegalMove(String::from("Ke2")).to_string(), "Illegal move, Ke2");
+    assert_eq!(SanError::Ambiguous(String::from("Nd2")).to_string(),
+               "Ambiguous move, more than one piece can play Nd2");
+    assert_eq!(SanError::MissingPromotion(String::from("e8")).to_string(), "Missing promotion piece, e8");
+    assert_eq!(SanError::WrongSide(Colour::White, String::from("e5")).to_string(), "White to move, can't play e5");
+}
+
+// Every legal move written out in SAN reads back as the same move.
+#[test]
+fn written_moves_read_back() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "2k5/8/8/8/4Q2Q/8/8/K6Q w - - 0 1",
+    ];
+    for fen in fens.iter() {
+        let position = board(fen);
+        for mv in position.legal_moves() {
+            let san = position.san(&mv);
+            assert_eq!(position.parse_san(&san), Ok(mv), "{} in {}", san, fen);
+        }
+    }
+}
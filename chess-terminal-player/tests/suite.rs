@@ -0,0 +1,35 @@
+extern crate chess_player;
+
+use chess_player::search::Limits;
+use chess_player::suite::Suite;
+
+const SUITE: &str = "# Mate in one, a poisoned pawn, and a move the engine has no reason to play.
+6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"back rank\";
+4k3/8/4p3/3p4/8/8/8/3QK3 w - - am Qxd5; id \"poisoned\";
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm a3 h3;
+";
+
+#[test]
+fn counts_the_positions_solved() {
+    let suite = Suite::parse("sample", SUITE).unwrap();
+    assert_eq!(suite.tests.len(), 3);
+    assert_eq!(suite.tests[2].id, "sample:4");
+    assert_eq!(suite.tests[2].expected(), "bm a3 h3");
+
+    let mut reported = Vec::new();
+    let outcomes = suite.run(Limits { depth: Some(3), time: None }, |test, outcome| {
+        reported.push(test.id.clone());
+        assert_eq!(test.id, outcome.id);
+    });
+    assert_eq!(reported, ["back rank", "poisoned", "sample:4"]);
+    assert_eq!(outcomes[0].played.as_deref(), Some("Ra8#"));
+    let solved: Vec<bool> = outcomes.iter().map(|outcome| outcome.solved).collect();
+    assert_eq!(solved, [true, true, false]);
+}
+
+#[test]
+fn positions_need_something_to_find() {
+    let error = Suite::parse("sample", "\n4k3/8/8/8/8/8/8/3QK3 w - - id \"nothing\";").err().unwrap();
+    assert_eq!(error.to_string(), "Invalid test position on line 2, no bm or am operation");
+    assert!(Suite::parse("sample", "4k3/8/8/8/8/8/8/3QK3 w - - bm Qd9;").is_err());
+}
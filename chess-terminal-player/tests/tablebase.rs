@@ -0,0 +1,200 @@
+extern crate chess_player;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use chess_player::chessboard::ChessBoard;
+use chess_player::tablebase::{Tablebase, Wdl};
+
+// A directory of its own under the system's temporary directory, emptied first.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chess-player-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// A KQvK table that stores one value for each side to move: a win with white to move and a loss with black to
+// move. That is the whole truth once the prober has looked at black taking the queen, and at stalemate.
+fn single_value_kqvk() -> Vec<u8> {
+    vec![
+        0x71, 0xe8, 0x23, 0x5d,  // Magic
+        0x01,  // A table for each side to move, no pawns
+        0x00,  // The order the groups are indexed in
+        0x66, 0x55, 0xee,  // King, queen, king for both sides to move
+        0x00,  // Padding
+        0x80, 4,  // White to move, every position a win
+        0x80, 0,  // Black to move, every position a loss
+    ]
+}
+
+fn probe(tablebase: &Tablebase, fen: &str) -> Option<Wdl> {
+    tablebase.probe_wdl(&ChessBoard::from_fen(fen).unwrap())
+}
+
+#[test]
+fn reads_a_table_and_searches_captures() {
+    let dir = scratch_dir("kqvk");
+    fs::write(dir.join("KQvK.rtbw"), single_value_kqvk()).unwrap();
+    let tablebase = Tablebase::open(&dir).unwrap();
+    assert_eq!(tablebase.max_pieces(), 3);
+
+    assert_eq!(probe(&tablebase, "8/8/8/8/8/2k5/8/KQ6 w - - 0 1"), Some(Wdl::Win));
+    assert_eq!(probe(&tablebase, "k7/8/8/8/8/8/8/KQ6 b - - 0 1"), Some(Wdl::Loss));
+    // Black takes the queen.
+    assert_eq!(probe(&tablebase, "8/8/8/8/8/1k6/1Q6/7K b - - 0 1"), Some(Wdl::Draw));
+    // Stalemate.
+    assert_eq!(probe(&tablebase, "k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+    // Black with the queen is the same table with the colours swapped.
+    assert_eq!(probe(&tablebase, "kq6/8/8/8/8/8/8/7K b - - 0 1"), Some(Wdl::Win));
+    assert_eq!(probe(&tablebase, "kq6/8/8/8/8/8/8/7K w - - 0 1"), Some(Wdl::Loss));
+    // Bare kings need no table, other material isn't there.
+    assert_eq!(probe(&tablebase, "k7/8/8/8/8/8/8/7K w - - 0 1"), Some(Wdl::Draw));
+    assert_eq!(probe(&tablebase, "k7/8/8/8/8/8/8/6RK w - - 0 1"), None);
+    assert_eq!(probe(&tablebase, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), None);
+
+    // Without the distance tables, only positions decided by a capture or by the game being over have a distance.
+    let dtz = |fen| tablebase.probe_dtz(&ChessBoard::from_fen(fen).unwrap());
+    assert_eq!(dtz("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), Some(-1));
+    assert_eq!(dtz("8/8/8/8/8/1k6/1Q6/7K b - - 0 1"), Some(0));
+    assert_eq!(dtz("8/8/8/8/8/2k5/8/KQ6 w - - 0 1"), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn broken_tables() {
+    let dir = scratch_dir("broken");
+    assert!(Tablebase::open(&dir).is_err());
+    assert!(Tablebase::open(dir.join("missing")).is_err());
+
+    // Not a table, or a table for other pieces than its name says.
+    fs::write(dir.join("KQvK.rtbw"), b"not a tablebase").unwrap();
+    let mut krvk = single_value_kqvk();
+    krvk[4] = 0x03;
+    fs::write(dir.join("KRvK.rtbw"), krvk).unwrap();
+    let tablebase = Tablebase::open(&dir).unwrap();
+    assert_eq!(probe(&tablebase, "8/8/8/8/8/2k5/8/KQ6 w - - 0 1"), None);
+    assert_eq!(probe(&tablebase, "8/8/8/8/8/2k5/8/KR6 w - - 0 1"), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// The published tables, at least the 3 and 4 piece files, from the directory in SYZYGY_PATH. Without it the tests
+// that need them have nothing to check.
+fn syzygy() -> Option<Tablebase> {
+    let dir = env::var("SYZYGY_PATH").ok()?;
+    Some(Tablebase::open(&dir).unwrap_or_else(|e| panic!("Can't open the tables in {}: {}", dir, e)))
+}
+
+#[test]
+fn known_results() {
+    let tablebase = match syzygy() {
+        Some(tablebase) => tablebase,
+        None => return,
+    };
+    let results = [
+        ("8/8/8/8/8/2k5/8/KQ6 w - - 0 1", Wdl::Win),
+        ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", Wdl::Win),
+        ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", Wdl::Loss),
+        ("k7/8/8/8/8/8/P7/K7 w - - 0 1", Wdl::Draw),
+        ("8/8/8/3k4/8/8/8/KNN5 w - - 0 1", Wdl::Draw),
+        ("8/8/8/3k4/8/8/8/KBN5 w - - 0 1", Wdl::Win),
+        ("8/8/8/4k3/8/8/3K4/3R4 b - - 0 1", Wdl::Loss),
+        ("8/8/8/4K3/8/8/3k4/3r4 w - - 0 1", Wdl::Loss),
+        // En passant: only taking the pawn holds the draw.
+        ("8/8/8/8/Pp6/8/8/K5k1 b - a3 0 1", Wdl::Draw),
+        ("8/8/8/8/Pp6/8/8/K5k1 b - - 0 1", Wdl::Loss),
+    ];
+    for (fen, wdl) in results.iter() {
+        assert_eq!(probe(&tablebase, fen), Some(*wdl), "{}", fen);
+    }
+}
+
+// Distances in plies to the mate or pawn move that ends the count. The mates were searched out move by move, with
+// no tables, and in KPvK the king has to step aside before the pawn can go on.
+#[test]
+fn known_distances() {
+    let tablebase = match syzygy() {
+        Some(tablebase) => tablebase,
+        None => return,
+    };
+    let distances: [(&str, i32); 9] = [
+        ("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", 1),
+        ("8/8/8/8/8/1k6/8/K1Q5 w - - 0 1", 9),
+        ("8/8/8/8/8/2k5/8/KQ6 w - - 0 1", 11),
+        ("k7/8/2K5/8/8/8/8/1R6 w - - 0 1", 3),
+        ("2k5/8/2K5/8/8/8/8/7R b - - 0 1", -4),
+        ("k7/8/1K6/3N4/8/8/8/5B2 w - - 0 1", 7),
+        ("k7/8/1K6/8/8/8/8/NB6 w - - 0 1", 9),
+        ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", 3),
+        ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", -4),
+    ];
+    for &(fen, plies) in distances.iter() {
+        let dtz = tablebase.probe_dtz(&ChessBoard::from_fen(fen).unwrap()).unwrap();
+        // Tables that count in moves rather than plies can be out by one, never on a mate in one.
+        let slack = if plies == 1 { 0 } else { 1 };
+        assert!(dtz.signum() == plies.signum() && (dtz - plies).abs() <= slack, "{} {} {}", fen, dtz, plies);
+    }
+}
+
+// The result of a position is the best of the results its moves lead to.
+#[test]
+fn results_agree_with_the_moves() {
+    let tablebase = match syzygy() {
+        Some(tablebase) => tablebase,
+        None => return,
+    };
+    let fens = [
+        "8/8/8/3k4/8/8/2RK4/8 w - - 0 1",
+        "8/8/8/3k4/8/8/2RK4/8 b - - 0 1",
+        "8/4k3/8/8/8/8/3P4/3K4 w - - 0 1",
+        "8/2k5/8/8/8/1P6/8/1K6 b - - 0 1",
+        "8/8/2k5/8/2q5/8/3RK3/8 w - - 0 1",
+        "8/1p6/8/3k4/8/8/1P6/3K4 w - - 0 1",
+    ];
+    for fen in fens.iter() {
+        let mut board = ChessBoard::from_fen(fen).unwrap();
+        let wdl = tablebase.probe_wdl(&board).unwrap();
+        let mut best = None;
+        for mv in board.legal_moves() {
+            board.make_move(mv);
+            let after = if board.is_checkmate() {
+                Wdl::Win
+            } else {
+                reverse(tablebase.probe_wdl(&board).unwrap())
+            };
+            board.unmake_move();
+            best = best.max(Some(after));
+        }
+        assert_eq!(Some(wdl), best, "{}", fen);
+    }
+}
+
+fn reverse(wdl: Wdl) -> Wdl {
+    match wdl {
+        Wdl::Loss => Wdl::Win,
+        Wdl::BlessedLoss => Wdl::CursedWin,
+        Wdl::Draw => Wdl::Draw,
+        Wdl::CursedWin => Wdl::BlessedLoss,
+        Wdl::Win => Wdl::Loss,
+    }
+}
+
+// Playing the tables' moves for both sides wins won positions inside the fifty-move rule.
+#[test]
+fn best_moves_win() {
+    let tablebase = match syzygy() {
+        Some(tablebase) => tablebase,
+        None => return,
+    };
+    for fen in ["8/8/8/3k4/8/8/2RK4/8 w - - 0 1", "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", "8/8/3k4/8/8/8/1BN5/K7 w - - 0 1"].iter() {
+        let mut board = ChessBoard::from_fen(fen).unwrap();
+        let dtz = tablebase.probe_dtz(&board).unwrap();
+        assert!(dtz > 0 && dtz <= 100, "{} {}", fen, dtz);
+        while board.result().is_none() {
+            let mv = tablebase.best_move(&board).unwrap();
+            board.make_move(mv);
+        }
+        assert!(board.is_checkmate(), "{} ended {}", fen, board.to_fen());
+    }
+}
@@ -0,0 +1,37 @@
+extern crate chess_player;
+
+use chess_player::theme::{Shade, Theme, THEME_NAMES};
+
+#[test]
+fn every_listed_theme_exists() {
+    for name in THEME_NAMES.iter() {
+        assert!(Theme::named(name).is_some(), "{}", name);
+    }
+    assert_eq!(Theme::named("plaid"), None);
+    assert_eq!(Theme::named("classic"), Some(Theme::default()));
+}
+
+#[test]
+fn truecolor_falls_back_to_the_colour_cube() {
+    assert_eq!(Shade::Rgb(0, 0, 0).to_palette(), Shade::Palette(16));
+    assert_eq!(Shade::Rgb(255, 255, 255).to_palette(), Shade::Palette(231));
+    assert_eq!(Shade::Rgb(255, 0, 0).to_palette(), Shade::Palette(196));
+    assert_eq!(Shade::Rgb(181, 136, 99).to_palette(), Shade::Palette(137));
+    assert_eq!(Shade::Palette(65).to_palette(), Shade::Palette(65));
+
+    let wood = Theme::named("wood").unwrap().to_palette();
+    for shade in [wood.light, wood.dark, wood.cursor, wood.selected, wood.destination, wood.last_move].iter() {
+        assert!(matches!(shade, Shade::Palette(_)));
+    }
+}
+
+#[test]
+fn classic_uses_the_basic_colours() {
+    let classic = Theme::classic();
+    assert_eq!(classic.light.bg(), "\x1b[47m");
+    assert_eq!(classic.dark.bg(), "\x1b[40m");
+    assert_eq!(Shade::Basic(7).fg(), "\x1b[37m");
+    assert_eq!(Shade::Palette(7).bg(), "\x1b[48;5;7m");
+    // Nothing to fall back from.
+    assert_eq!(classic.to_palette(), classic);
+}
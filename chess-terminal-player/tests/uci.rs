@@ -0,0 +1,167 @@
+extern crate chess_player;
+
+use std::io::{self, Cursor, Write};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chess_player::chessboard::ChessBoard;
+use chess_player::search::Limits;
+use chess_player::uci::{self, parse_move, UciClient, UciError};
+
+// Output the server writes from its search threads, kept where the test can read it afterwards.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Runs the server on the commands given, one per line, and returns what it answered.
+fn run(commands: &[&str]) -> Vec<String> {
+    let output = Output::default();
+    uci::run(Cursor::new(commands.join("\n")), output.clone()).unwrap();
+    let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    text.lines().map(String::from).collect()
+}
+
+fn best_moves(lines: &[String]) -> Vec<&str> {
+    lines.iter().filter_map(|line| line.strip_prefix("bestmove ")).collect()
+}
+
+#[test]
+fn handshake() {
+    let lines = run(&["uci", "isready"]);
+    assert!(lines[0].starts_with("id name "));
+    assert!(lines.iter().any(|line| line == "option name UCI_Chess960 type check default false"));
+    let uciok = lines.iter().position(|line| line == "uciok").unwrap();
+    assert_eq!(lines[uciok + 1..], ["readyok"]);
+}
+
+#[test]
+fn searches_the_position_given() {
+    let lines = run(&["uci", "ucinewgame", "isready", "position startpos moves e2e4 e7e5 g1f3", "go depth 3"]);
+    assert!(lines.iter().any(|line| line == "readyok"));
+    // A line of info for each depth, then the move.
+    let depths: Vec<&str> = lines.iter().filter_map(|line| line.strip_prefix("info depth ")).map(|info| &info[..1]).collect();
+    assert_eq!(depths, ["1", "2", "3"]);
+    let moves = best_moves(&lines);
+    assert_eq!(moves.len(), 1);
+
+    let mut board = ChessBoard::new();
+    for text in ["e2e4", "e7e5", "g1f3"].iter() {
+        let mv = parse_move(&board, text).unwrap();
+        board.make_move(mv);
+    }
+    assert!(parse_move(&board, moves[0]).is_some(), "{}", moves[0]);
+    assert_eq!(lines.last().unwrap(), &format!("bestmove {}", moves[0]));
+}
+
+#[test]
+fn finds_mate_from_a_fen() {
+    let lines = run(&["position fen 6k1/5ppp/8/8/8/8/5PPP/4R1K1 w - - 0 1", "go depth 3"]);
+    assert_eq!(best_moves(&lines), ["e1e8"]);
+    assert!(lines.iter().any(|line| line.contains("score mate 1")));
+}
+
+#[test]
+fn stop_ends_an_infinite_search() {
+    let lines = run(&["position startpos moves d2d4", "go infinite", "isready", "stop", "isready", "quit"]);
+    let moves = best_moves(&lines);
+    assert_eq!(moves.len(), 1);
+    let mut board = ChessBoard::new();
+    board.move_piece("d4").unwrap();
+    assert!(parse_move(&board, moves[0]).is_some(), "{}", moves[0]);
+    // The search answers `isready` while it runs, and its move comes before the reply to the next one.
+    let bestmove = lines.iter().position(|line| line.starts_with("bestmove ")).unwrap();
+    let ready: Vec<usize> = lines.iter().enumerate().filter(|(_, line)| *line == "readyok").map(|(i, _)| i).collect();
+    assert_eq!(ready.len(), 2);
+    assert!(ready[0] < bestmove && bestmove < ready[1]);
+}
+
+#[test]
+fn bad_positions_are_reported() {
+    let lines = run(&["position startpos moves e2e5", "position fen 8/8/8 w - - 0 1", "position somewhere"]);
+    assert_eq!(lines, [
+        "info string Invalid move, e2e5",
+        "info string Expected 8 ranks, found 3",
+        "info string Invalid position, expected startpos or fen",
+    ]);
+}
+
+#[test]
+fn perft() {
+    let lines = run(&["position startpos", "go perft 2"]);
+    assert_eq!(lines.len(), 22);
+    assert!(lines.contains(&String::from("e2e4: 20")));
+    assert_eq!(lines.last().unwrap(), "Nodes searched: 400");
+}
+
+// The stand-in engine in examples/, built along with the tests, or built here when only this test was asked for.
+fn scripted_engine() -> String {
+    let exe = std::env::current_exe().unwrap();
+    let profile = exe.parent().unwrap().parent().unwrap();
+    let path = profile.join("examples").join(format!("scripted_engine{}", std::env::consts::EXE_SUFFIX));
+    if !path.exists() {
+        let mut build = Command::new(env!("CARGO"));
+        build.args(["build", "--example", "scripted_engine"]).current_dir(env!("CARGO_MANIFEST_DIR"));
+        if profile.ends_with("release") {
+            build.arg("--release");
+        }
+        assert!(build.status().unwrap().success());
+    }
+    path.to_string_lossy().into_owned()
+}
+
+fn engine(moves: &[&str]) -> UciClient {
+    let args: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+    UciClient::spawn(&scripted_engine(), &args).unwrap()
+}
+
+#[test]
+fn client_plays_the_engines_moves() {
+    let mut client = engine(&["e7e5", "b8c6"]);
+    assert_eq!(client.name(), Some("Scripted Engine"));
+    client.new_game().unwrap();
+
+    let limits = Limits { depth: None, time: Some(Duration::from_millis(100)) };
+    let mut board = ChessBoard::new();
+    board.move_piece("e4").unwrap();
+    let mv = client.best_move(&board, limits).unwrap();
+    assert_eq!(board.san(&mv), "e5");
+    board.make_move(mv);
+    board.move_piece("Nf3").unwrap();
+    let mv = client.best_move(&board, Limits { depth: Some(1), time: None }).unwrap();
+    assert_eq!(board.san(&mv), "Nc6");
+    // Out of moves, the engine says it has none.
+    board.make_move(mv);
+    board.move_piece("Bb5").unwrap();
+    assert!(matches!(client.best_move(&board, limits), Err(UciError::IllegalMove(text)) if text == "0000"));
+}
+
+#[test]
+fn client_rejects_illegal_moves() {
+    // White's move with black to play, and a queen move through its own pawn, then a legal one.
+    let mut client = engine(&["e2e4", "d8d1", "e7e5"]);
+    let mut board = ChessBoard::new();
+    board.move_piece("d4").unwrap();
+    for text in ["e2e4", "d8d1"].iter() {
+        match client.best_move(&board, Limits::default()) {
+            Err(UciError::IllegalMove(played)) => assert_eq!(played, *text),
+            other => panic!("{:?} for {}", other.map(|mv| board.san(&mv)), text),
+        }
+    }
+    let mv = client.best_move(&board, Limits::default()).unwrap();
+    assert_eq!(board.san(&mv), "e5");
+}
+
+#[test]
+fn client_reports_a_missing_engine() {
+    assert!(matches!(UciClient::spawn("./no-such-engine", &[]), Err(UciError::Io(_))));
+}